@@ -0,0 +1,65 @@
+use chessify::{Board, Color, Piece, Square};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use std::collections::HashMap;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+/// Look up every square, comparing the mailbox against the hash map it replaced.
+fn piece_lookup(c: &mut Criterion) {
+    let board: Board = Board::from_fen(KIWIPETE);
+    let map: HashMap<usize, (Piece, Color)> = board
+        .pieces()
+        .iter()
+        .enumerate()
+        .filter_map(|(s, cp)| cp.map(|cp| (s, (cp.piece, cp.color))))
+        .collect();
+
+    let mut group = c.benchmark_group("piece lookup");
+    group.bench_function("mailbox", |b| {
+        b.iter(|| {
+            (0..64)
+                .filter(|s| black_box(&board).piece_at(Square::from_index(*s)) == Some(Piece::Pawn))
+                .count()
+        })
+    });
+    group.bench_function("hash map", |b| {
+        b.iter(|| {
+            (0..64)
+                .filter(|s| black_box(&map).get(s).map(|(p, _)| *p) == Some(Piece::Pawn))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn fen(c: &mut Criterion) {
+    let board: Board = Board::from_fen(KIWIPETE);
+    c.bench_function("parse fen", |b| {
+        b.iter(|| Board::from_fen(black_box(KIWIPETE)))
+    });
+    c.bench_function("write fen", |b| b.iter(|| black_box(&board).to_fen()));
+    c.bench_function("display", |b| b.iter(|| black_box(&board).to_string()));
+}
+
+fn make_unmake(c: &mut Criterion) {
+    let mut board: Board = Board::from_fen(KIWIPETE);
+    let moves = board.legal_moves();
+    c.bench_function("make and unmake", |b| {
+        b.iter(|| {
+            for m in &moves {
+                board.make_move(*m);
+                board.unmake_move(*m);
+            }
+        })
+    });
+}
+
+fn perft(c: &mut Criterion) {
+    let board: Board = Board::from_fen(KIWIPETE);
+    c.bench_function("perft 3", |b| b.iter(|| black_box(&board).perft(3)));
+}
+
+criterion_group!(benches, piece_lookup, fen, make_unmake, perft);
+criterion_main!(benches);
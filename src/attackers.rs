@@ -0,0 +1,159 @@
+use crate::attacks::{
+    between, bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks,
+};
+use crate::bitboard::{Bitboard, EMPTY};
+use crate::board::Board;
+use crate::color::Color;
+use crate::piece::Piece;
+use crate::square::Square;
+
+impl Board {
+    /// Get all pieces of both colors that attack `s`.
+    pub fn attackers_to(&self, s: Square) -> Bitboard {
+        self.attackers_to_occupied(s, self.occupied())
+    }
+
+    /// Get all pieces of both colors that attack `s` if only the squares in `occupied` blocked
+    /// sliding pieces.
+    pub(crate) fn attackers_to_occupied(&self, s: Square, occupied: Bitboard) -> Bitboard {
+        self.attackers_by(s, Color::White, occupied) | self.attackers_by(s, Color::Black, occupied)
+    }
+
+    /// Get all pieces of color `by` that attack `s`, sliding pieces being blocked by `occupied`.
+    pub(crate) fn attackers_by(&self, s: Square, by: Color, occupied: Bitboard) -> Bitboard {
+        let bb = |p: Piece| self.piece_bitboard(p, by);
+        let queens: Bitboard = bb(Piece::Queen);
+
+        (pawn_attacks(!by, s) & bb(Piece::Pawn))
+            | (knight_attacks(s) & bb(Piece::Knight))
+            | (king_attacks(s) & bb(Piece::King))
+            | (bishop_attacks(s, occupied) & (bb(Piece::Bishop) | queens))
+            | (rook_attacks(s, occupied) & (bb(Piece::Rook) | queens))
+    }
+
+    /// Check whether any piece of color `by` attacks `s`.
+    ///
+    /// The square does not have to be empty, so this also tells whether a piece on it is defended.
+    pub fn is_square_attacked(&self, s: Square, by: Color) -> bool {
+        !self.attackers_by(s, by, self.occupied()).is_empty()
+    }
+
+    /// Get the pieces giving check to the king of the side to move.
+    ///
+    /// Returns an empty bitboard if the side to move has no king.
+    pub fn checkers(&self) -> Bitboard {
+        let us: Color = self.side_to_move();
+        match self.king_square(us) {
+            Some(s) => self.attackers_by(s, !us, self.occupied()),
+            None => EMPTY,
+        }
+    }
+
+    /// Check whether the king of the side to move is currently attacked.
+    pub fn is_check(&self) -> bool {
+        !self.checkers().is_empty()
+    }
+
+    /// Get the pieces of color `c` that are absolutely pinned to their own king, i.e., the ones
+    /// that are the only piece between their king and an enemy slider.
+    pub fn pinned(&self, c: Color) -> Bitboard {
+        let ksq: Square = match self.king_square(c) {
+            Some(s) => s,
+            None => return EMPTY,
+        };
+
+        let own: Bitboard = self.color_bitboard(c);
+        let mut pinned: Bitboard = EMPTY;
+        for sniper in self.x_ray_attackers(ksq, !c) {
+            pinned |= between(ksq, sniper) & own;
+        }
+        pinned
+    }
+
+    /// Get the sliding pieces of color `by` that would attack `s` if exactly one piece in
+    /// between was removed.
+    ///
+    /// If the piece in between belongs to the other color it is pinned to or skewered against
+    /// `s`, if it belongs to `by` moving it uncovers a discovered attack.
+    pub fn x_ray_attackers(&self, s: Square, by: Color) -> Bitboard {
+        let bb = |p: Piece| self.piece_bitboard(p, by);
+        let queens: Bitboard = bb(Piece::Queen);
+        let occupied: Bitboard = self.occupied();
+
+        let snipers: Bitboard = (rook_attacks(s, EMPTY) & (bb(Piece::Rook) | queens))
+            | (bishop_attacks(s, EMPTY) & (bb(Piece::Bishop) | queens));
+
+        let mut x_rays: Bitboard = EMPTY;
+        for sniper in snipers {
+            if (between(s, sniper) & occupied).popcount() == 1 {
+                x_rays |= Bitboard::from_square(sniper);
+            }
+        }
+        x_rays
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bb, sq};
+
+    #[test]
+    fn attackers_to() {
+        let b = Board::from_fen("4k3/8/3n4/8/1B2R3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(bb(&["d6", "d3"]), b.attackers_to(sq("e4")));
+        assert_eq!(bb(&["d6", "e4", "d3"]), b.attackers_to(sq("c4")));
+        assert_eq!(bb(&["b4"]), b.attackers_to(sq("c5")));
+        assert_eq!(EMPTY, b.attackers_to(sq("h8")));
+    }
+
+    #[test]
+    fn square_attacked() {
+        let b = Board::from_fen("4k3/8/3n4/8/1B2R3/3P4/8/4K3 w - - 0 1");
+        assert!(b.is_square_attacked(sq("e4"), Color::Black));
+        assert!(b.is_square_attacked(sq("c4"), Color::White));
+        assert!(!b.is_square_attacked(sq("a1"), Color::White));
+        assert!(!b.is_square_attacked(sq("h1"), Color::Black));
+        // The rook is defended by the pawn, but not by itself.
+        assert!(b.is_square_attacked(sq("e4"), Color::White));
+    }
+
+    #[test]
+    fn checkers() {
+        assert_eq!(EMPTY, Board::default().checkers());
+        assert!(!Board::default().is_check());
+
+        // Double check by a knight and a discovering rook.
+        let b = Board::from_fen("4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1");
+        assert_eq!(bb(&["d6", "e1"]), b.checkers());
+        assert!(b.is_check());
+    }
+
+    #[test]
+    fn pinned() {
+        let b = Board::from_fen("4k3/4r3/8/1b6/8/3N4/4B3/4K3 w - - 0 1");
+        assert_eq!(bb(&["e2"]), b.pinned(Color::White));
+        assert_eq!(EMPTY, b.pinned(Color::Black));
+
+        // Two pieces in between means neither is pinned.
+        let b = Board::from_fen("4k3/4r3/8/8/4P3/8/4B3/4K3 w - - 0 1");
+        assert_eq!(EMPTY, b.pinned(Color::White));
+
+        // An enemy piece in between is not pinned to our king.
+        let b = Board::from_fen("4k3/4r3/8/8/8/8/4n3/4K3 w - - 0 1");
+        assert_eq!(EMPTY, b.pinned(Color::White));
+    }
+
+    #[test]
+    fn x_ray_attackers() {
+        // The rook x-rays e1 through the bishop and the queen x-rays d5 through the knight.
+        let b = Board::from_fen("4k3/4r3/8/Q1n5/8/8/4B3/4K3 w - - 0 1");
+        assert_eq!(bb(&["e7"]), b.x_ray_attackers(sq("e1"), Color::Black));
+        assert_eq!(bb(&["a5"]), b.x_ray_attackers(sq("d5"), Color::White));
+        assert_eq!(EMPTY, b.x_ray_attackers(sq("e8"), Color::White));
+
+        // Moving the bishop uncovers a check by the rook.
+        let b = Board::from_fen("4k3/8/8/8/4B3/8/8/K3R3 w - - 0 1");
+        assert_eq!(bb(&["e1"]), b.x_ray_attackers(sq("e8"), Color::White));
+    }
+}
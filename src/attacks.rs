@@ -0,0 +1,484 @@
+use crate::bitboard::{Bitboard, EMPTY};
+use crate::color::{Color, NUM_COLORS};
+use crate::square::Square;
+
+use std::sync::OnceLock;
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Squares attacked by a knight, indexed by square.
+const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_table(&KNIGHT_OFFSETS);
+/// Squares attacked by a king, indexed by square.
+const KING_ATTACKS: [Bitboard; 64] = leaper_table(&KING_OFFSETS);
+/// Squares attacked by a pawn, indexed by color and square.
+///
+/// Remember that the row index grows towards the first rank, so white pawns attack "upwards".
+const PAWN_ATTACKS: [[Bitboard; 64]; NUM_COLORS] = [
+    leaper_table(&[(-1, -1), (-1, 1)]),
+    leaper_table(&[(1, -1), (1, 1)]),
+];
+
+const fn leaper_table(offsets: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table: [Bitboard; 64] = [EMPTY; 64];
+    let mut sq: usize = 0;
+    while sq < 64 {
+        let (row, file) = ((sq / 8) as i8, (sq % 8) as i8);
+        let mut b: u64 = 0;
+        let mut i: usize = 0;
+        while i < offsets.len() {
+            let (r, f) = (row + offsets[i].0, file + offsets[i].1);
+            if r >= 0 && r < 8 && f >= 0 && f < 8 {
+                b |= 1u64 << (r * 8 + f);
+            }
+            i += 1;
+        }
+        table[sq] = Bitboard(b);
+        sq += 1;
+    }
+    table
+}
+
+/// Magic multipliers for the rook attack lookup, indexed by square.
+const ROOK_MAGICS: [u64; 64] = [
+    0x0A80_0040_0080_1220,
+    0x10C0_1000_4000_2000,
+    0x0100_1020_0041_0009,
+    0x0B00_2100_0C10_0008,
+    0x4080_0800_8004_0002,
+    0x0200_0190_0408_0200,
+    0x0400_080A_1011_2684,
+    0x2080_0A4D_0006_2080,
+    0x2091_8000_2080_4000,
+    0x0044_4010_0020_0040,
+    0x1001_0020_0040_1108,
+    0x1001_8008_0110_0081,
+    0x0001_0005_0008_0010,
+    0x1000_8080_0200_0400,
+    0x0404_0004_8210_0108,
+    0x0003_0001_8261_0002,
+    0x0440_8480_02C0_0420,
+    0x2010_8900_4001_0021,
+    0x8800_1100_2004_4300,
+    0x0208_0101_0020_1000,
+    0x1222_0200_0410_2008,
+    0x0000_8080_0200_0400,
+    0x2004_0400_094A_9008,
+    0x0000_4200_0080_4401,
+    0x0040_0028_8000_4680,
+    0x0000_2002_4010_0040,
+    0x0020_0081_8020_1001,
+    0x0108_0080_800C_1000,
+    0x0104_0400_8080_0800,
+    0x4800_0200_8004_0080,
+    0x0002_0002_0084_0108,
+    0x00A1_0001_0000_6082,
+    0x8004_4000_8880_0260,
+    0x0100_8040_0080_2008,
+    0x0010_0080_1080_2002,
+    0x000C_8010_0080_0800,
+    0x0C51_8004_0280_0800,
+    0x0002_8002_0080_0400,
+    0x0000_8208_0400_0110,
+    0x4003_8080_4200_0401,
+    0x0020_8020_C001_8000,
+    0x4400_4020_1000_4009,
+    0x2210_0400_A800_E000,
+    0x0E02_0021_400A_0013,
+    0x10A0_0801_0011_0005,
+    0x0004_0100_0200_4040,
+    0x0024_0801_0204_0010,
+    0x4154_0891_0842_0014,
+    0x0182_4000_8000_2380,
+    0x0000_4001_1080_2100,
+    0x0000_1000_8020_0480,
+    0x100A_0008_2040_1200,
+    0x8081_0040_2080_1002,
+    0x0002_0004_0810_0200,
+    0x0322_3A10_0801_0C00,
+    0x0000_0083_1C01_4200,
+    0x4200_2080_0900_1041,
+    0xC001_0040_0088_1021,
+    0x1008_2001_0010_0841,
+    0x0000_0822_4092_0032,
+    0x4002_0008_0420_1102,
+    0xB821_0008_0400_0201,
+    0x4080_C208_1021_00A4,
+    0x0202_0900_418C_0CA2,
+];
+
+/// Magic multipliers for the bishop attack lookup, indexed by square.
+const BISHOP_MAGICS: [u64; 64] = [
+    0x002A_8404_0184_0308,
+    0x0002_0484_0400_4000,
+    0x1088_5081_0602_0000,
+    0x0604_0404_8400_0420,
+    0x1002_0210_0438_0001,
+    0x8041_0482_4000_0A30,
+    0x4084_0441_0410_3110,
+    0x0081_0040_4420_0840,
+    0x0442_4110_A101_0901,
+    0x0042_8208_4104_0080,
+    0x1001_0802_0400_2C09,
+    0x0001_4804_A104_1815,
+    0x0004_8202_1004_1001,
+    0x0001_8104_0340_0040,
+    0x0802_4041_0420_A084,
+    0x0410_1202_0101_0900,
+    0x0240_0485_0428_0200,
+    0x9402_0004_9004_0325,
+    0x2003_0010_1C09_8030,
+    0x0004_0028_4040_0800,
+    0x0002_0104_0211_0140,
+    0x0000_4032_0100_A060,
+    0x0042_0000_6104_6000,
+    0x0188_3000_8468_4808,
+    0x0010_1011_0802_1022,
+    0x8724_0480_2109_0C00,
+    0x502C_0202_C408_0010,
+    0x0008_0822_4802_0020,
+    0x0001_0200_8400_8400,
+    0x0891_0040_0208_2001,
+    0x000A_0210_0424_8200,
+    0x0011_0200_012A_8402,
+    0x2042_2084_3020_3904,
+    0x0C08_6208_1611_1880,
+    0x0002_0450_0441_0100,
+    0x0800_1201_8018_0080,
+    0x0140_0100_12C1_0040,
+    0x0050_1008_4040_2400,
+    0x0808_0200_8000_4801,
+    0x0004_8203_4102_0100,
+    0x001A_0124_2010_C080,
+    0x2018_6202_1001_2008,
+    0x8021_0400_220A_0400,
+    0x0020_0142_0082_0801,
+    0x0100_0881_0041_C400,
+    0x0020_0408_8020_5A01,
+    0x0010_8101_1102_E420,
+    0x0081_0604_810B_0208,
+    0x0000_6210_0421_0094,
+    0x0200_2108_0210_5811,
+    0x8008_0080_5808_0200,
+    0x5800_4000_8404_0010,
+    0x0000_0090_0202_2880,
+    0x9000_8830_0102_1010,
+    0x804A_8284_0404_0006,
+    0x2010_1218_0100_2800,
+    0x4012_0200_8401_0846,
+    0x8002_4212_0202_0200,
+    0x6104_0400_2084_1000,
+    0x0000_0002_0504_8804,
+    0x0808_0080_4110_2480,
+    0x2305_9040_0204_0440,
+    0x0810_4042_8202_0204,
+    0x0588_2001_0200_2100,
+];
+
+/// A single entry of the "fancy" magic bitboard scheme.
+///
+/// Every square owns a slice of the shared attack table starting at `offset`. The relevant
+/// occupancy (`mask`) is hashed into that slice, either by multiplying with `magic` and keeping
+/// the upper bits or, on CPUs with BMI2, with a single `pext` instruction.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "bmi2"), allow(dead_code))]
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    #[cfg(all(target_arch = "x86_64", target_feature = "bmi2"))]
+    #[inline]
+    fn index(&self, occupied: u64) -> usize {
+        // SAFETY: the `bmi2` target feature is enabled at compile time.
+        self.offset + unsafe { std::arch::x86_64::_pext_u64(occupied, self.mask) } as usize
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "bmi2")))]
+    #[inline]
+    fn index(&self, occupied: u64) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Lookup tables that are too large to be built at compile time.
+struct Tables {
+    rook_magics: [Magic; 64],
+    bishop_magics: [Magic; 64],
+    sliders: Vec<Bitboard>,
+    between: Vec<Bitboard>,
+    line: Vec<Bitboard>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Self {
+        let mut sliders: Vec<Bitboard> = Vec::new();
+        let rook_magics = init_magics(&ROOK_DIRECTIONS, &ROOK_MAGICS, &mut sliders);
+        let bishop_magics = init_magics(&BISHOP_DIRECTIONS, &BISHOP_MAGICS, &mut sliders);
+
+        let mut between: Vec<Bitboard> = vec![EMPTY; 64 * 64];
+        let mut line: Vec<Bitboard> = vec![EMPTY; 64 * 64];
+        for a in 0..64 {
+            for b in 0..64 {
+                let (a_bb, b_bb) = (1u64 << a, 1u64 << b);
+                for directions in [&ROOK_DIRECTIONS, &BISHOP_DIRECTIONS] {
+                    if slow_attacks(a, 0, directions) & b_bb != 0 {
+                        between[a * 64 + b] = Bitboard(
+                            slow_attacks(a, b_bb, directions) & slow_attacks(b, a_bb, directions),
+                        );
+                        line[a * 64 + b] = Bitboard(
+                            (slow_attacks(a, 0, directions) & slow_attacks(b, 0, directions))
+                                | a_bb
+                                | b_bb,
+                        );
+                    }
+                }
+            }
+        }
+
+        Tables {
+            rook_magics,
+            bishop_magics,
+            sliders,
+            between,
+            line,
+        }
+    }
+}
+
+/// Build the magic entries for one slider type and append its attacks to `table`.
+fn init_magics(
+    directions: &[(i8, i8); 4],
+    magics: &[u64; 64],
+    table: &mut Vec<Bitboard>,
+) -> [Magic; 64] {
+    let mut entries: [Magic; 64] = [Magic::default(); 64];
+    for sq in 0..64 {
+        let mask: u64 = relevant_occupancy(sq, directions);
+        let bits: u32 = mask.count_ones();
+        let entry = Magic {
+            mask,
+            magic: magics[sq],
+            shift: 64 - bits,
+            offset: table.len(),
+        };
+        table.resize(table.len() + (1 << bits), EMPTY);
+
+        // Walk every subset of the mask with the carry-rippler trick.
+        let mut subset: u64 = 0;
+        loop {
+            let index: usize = entry.index(subset);
+            let attacks: Bitboard = Bitboard(slow_attacks(sq, subset, directions));
+            // Occupancies may only share an index if they also share their attacks.
+            debug_assert!(
+                table[index] == EMPTY || table[index] == attacks,
+                "magic number of square {} has a destructive collision",
+                sq
+            );
+            table[index] = attacks;
+            subset = subset.wrapping_sub(mask) & mask;
+            if subset == 0 {
+                break;
+            }
+        }
+        entries[sq] = entry;
+    }
+    entries
+}
+
+/// Ray attacks of a slider, walking square by square until a blocker is hit.
+fn slow_attacks(sq: usize, occupied: u64, directions: &[(i8, i8); 4]) -> u64 {
+    let mut attacks: u64 = 0;
+    let (row, file) = ((sq / 8) as i8, (sq % 8) as i8);
+
+    for (dr, df) in directions {
+        let (mut r, mut f) = (row + dr, file + df);
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            let b: u64 = 1u64 << (r * 8 + f);
+            attacks |= b;
+            if occupied & b != 0 {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+    }
+    attacks
+}
+
+/// The squares whose occupancy can change the attacks of a slider, i.e., its rays without the edges.
+fn relevant_occupancy(sq: usize, directions: &[(i8, i8); 4]) -> u64 {
+    let mut mask: u64 = 0;
+    let (row, file) = ((sq / 8) as i8, (sq % 8) as i8);
+
+    for (dr, df) in directions {
+        let (mut r, mut f) = (row + dr, file + df);
+        while (0..8).contains(&(r + dr)) && (0..8).contains(&(f + df)) {
+            mask |= 1u64 << (r * 8 + f);
+            r += dr;
+            f += df;
+        }
+    }
+    mask
+}
+
+/// Get the squares attacked by a knight standing on `s`.
+pub fn knight_attacks(s: Square) -> Bitboard {
+    KNIGHT_ATTACKS[s.index()]
+}
+
+/// Get the squares attacked by a king standing on `s`.
+pub fn king_attacks(s: Square) -> Bitboard {
+    KING_ATTACKS[s.index()]
+}
+
+/// Get the squares attacked by a pawn of color `c` standing on `s`.
+pub fn pawn_attacks(c: Color, s: Square) -> Bitboard {
+    PAWN_ATTACKS[c.as_index()][s.index()]
+}
+
+/// Get the squares attacked by a bishop standing on `s`, given the `occupied` squares.
+///
+/// The attacks include the first blocker in every direction, regardless of its color.
+pub fn bishop_attacks(s: Square, occupied: Bitboard) -> Bitboard {
+    let t: &Tables = tables();
+    t.sliders[t.bishop_magics[s.index()].index(occupied.0)]
+}
+
+/// Get the squares attacked by a rook standing on `s`, given the `occupied` squares.
+///
+/// The attacks include the first blocker in every direction, regardless of its color.
+pub fn rook_attacks(s: Square, occupied: Bitboard) -> Bitboard {
+    let t: &Tables = tables();
+    t.sliders[t.rook_magics[s.index()].index(occupied.0)]
+}
+
+/// Get the squares attacked by a queen standing on `s`, given the `occupied` squares.
+pub fn queen_attacks(s: Square, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(s, occupied) | rook_attacks(s, occupied)
+}
+
+/// Get the squares strictly between `a` and `b` if they share a rank, file or diagonal.
+///
+/// Returns an empty bitboard if the squares are not aligned.
+pub(crate) fn between(a: Square, b: Square) -> Bitboard {
+    tables().between[a.index() * 64 + b.index()]
+}
+
+/// Get the whole line running through `a` and `b` from edge to edge.
+///
+/// Returns an empty bitboard if the squares are not aligned.
+pub(crate) fn line(a: Square, b: Square) -> Bitboard {
+    tables().line[a.index() * 64 + b.index()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{bb, sq};
+
+    #[test]
+    fn knight() {
+        assert_eq!(bb(&["b3", "c2"]), knight_attacks(sq("a1")));
+        assert_eq!(8, knight_attacks(sq("d4")).0.count_ones());
+        assert_eq!(bb(&["f7", "g6"]), knight_attacks(sq("h8")));
+    }
+
+    #[test]
+    fn king() {
+        assert_eq!(bb(&["g8", "g7", "h7"]), king_attacks(sq("h8")));
+        assert_eq!(8, king_attacks(sq("e5")).0.count_ones());
+    }
+
+    #[test]
+    fn pawn() {
+        assert_eq!(bb(&["d3", "f3"]), pawn_attacks(Color::White, sq("e2")));
+        assert_eq!(bb(&["b6"]), pawn_attacks(Color::White, sq("a5")));
+        assert_eq!(bb(&["g6"]), pawn_attacks(Color::Black, sq("h7")));
+        assert_eq!(bb(&["d3", "f3"]), pawn_attacks(Color::Black, sq("e4")));
+    }
+
+    #[test]
+    fn sliders() {
+        let occupied: Bitboard = bb(&["d6", "f4", "b2"]);
+
+        assert_eq!(
+            bb(&["d5", "d6", "d3", "d2", "d1", "a4", "b4", "c4", "e4", "f4"]),
+            rook_attacks(sq("d4"), occupied)
+        );
+        assert_eq!(
+            bb(&["c3", "b2", "e5", "f6", "g7", "h8", "c5", "b6", "a7", "e3", "f2", "g1"]),
+            bishop_attacks(sq("d4"), occupied)
+        );
+        assert_eq!(
+            rook_attacks(sq("d4"), occupied) | bishop_attacks(sq("d4"), occupied),
+            queen_attacks(sq("d4"), occupied)
+        );
+    }
+
+    #[test]
+    fn sliders_match_slow_attacks() {
+        // A cheap pseudo random generator is plenty for sampling occupancies.
+        let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..2000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let occupied: u64 = seed & (seed >> 3);
+
+            for s in 0..64 {
+                assert_eq!(
+                    slow_attacks(s, occupied, &ROOK_DIRECTIONS),
+                    rook_attacks(Square::from_index(s), Bitboard(occupied)).0
+                );
+                assert_eq!(
+                    slow_attacks(s, occupied, &BISHOP_DIRECTIONS),
+                    bishop_attacks(Square::from_index(s), Bitboard(occupied)).0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn between_and_line() {
+        assert_eq!(bb(&["b2", "c3"]), between(sq("a1"), sq("d4")));
+        assert_eq!(bb(&["e2", "e3"]), between(sq("e4"), sq("e1")));
+        assert_eq!(EMPTY, between(sq("e4"), sq("e5")));
+        assert_eq!(EMPTY, between(sq("a1"), sq("b3")));
+
+        assert_eq!(
+            bb(&["a1", "b2", "c3", "d4", "e5", "f6", "g7", "h8"]),
+            line(sq("c3"), sq("f6"))
+        );
+        assert_eq!(EMPTY, line(sq("a1"), sq("b3")));
+    }
+}
@@ -11,6 +11,63 @@ pub struct Bitboard(pub u64);
 pub const EMPTY: Bitboard = Bitboard(0u64);
 pub const FULL: Bitboard = Bitboard(u64::MAX);
 
+pub const FILE_A: Bitboard = Bitboard(0x0101_0101_0101_0101);
+pub const FILE_B: Bitboard = Bitboard(FILE_A.0 << 1);
+pub const FILE_C: Bitboard = Bitboard(FILE_A.0 << 2);
+pub const FILE_D: Bitboard = Bitboard(FILE_A.0 << 3);
+pub const FILE_E: Bitboard = Bitboard(FILE_A.0 << 4);
+pub const FILE_F: Bitboard = Bitboard(FILE_A.0 << 5);
+pub const FILE_G: Bitboard = Bitboard(FILE_A.0 << 6);
+pub const FILE_H: Bitboard = Bitboard(FILE_A.0 << 7);
+
+/// The files from a to h, indexed like [`File`](crate::square::File).
+pub const FILES: [Bitboard; 8] = [
+    FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H,
+];
+
+pub const RANK_8: Bitboard = Bitboard(0xFF);
+pub const RANK_7: Bitboard = Bitboard(RANK_8.0 << 8);
+pub const RANK_6: Bitboard = Bitboard(RANK_8.0 << 16);
+pub const RANK_5: Bitboard = Bitboard(RANK_8.0 << 24);
+pub const RANK_4: Bitboard = Bitboard(RANK_8.0 << 32);
+pub const RANK_3: Bitboard = Bitboard(RANK_8.0 << 40);
+pub const RANK_2: Bitboard = Bitboard(RANK_8.0 << 48);
+pub const RANK_1: Bitboard = Bitboard(RANK_8.0 << 56);
+
+/// The ranks from 1 to 8, i.e., `RANKS[0]` is the first rank.
+pub const RANKS: [Bitboard; 8] = [
+    RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8,
+];
+
+/// The diagonals running from the lower left to the upper right.
+///
+/// A square on file `f` and rank `r` (both counted from zero) lies on `DIAGONALS[7 + f - r]`,
+/// so that `DIAGONALS[7]` is the long diagonal from a1 to h8.
+pub const DIAGONALS: [Bitboard; 15] = diagonals(false);
+
+/// The diagonals running from the upper left to the lower right.
+///
+/// A square on file `f` and rank `r` (both counted from zero) lies on `ANTI_DIAGONALS[f + r]`,
+/// so that `ANTI_DIAGONALS[7]` is the long diagonal from a8 to h1.
+pub const ANTI_DIAGONALS: [Bitboard; 15] = diagonals(true);
+
+/// All light squares of the board, a8 and h1 being among them.
+pub const LIGHT_SQUARES: Bitboard = Bitboard(0xAA55_AA55_AA55_AA55);
+/// All dark squares of the board, a1 and h8 being among them.
+pub const DARK_SQUARES: Bitboard = Bitboard(!LIGHT_SQUARES.0);
+
+const fn diagonals(anti: bool) -> [Bitboard; 15] {
+    let mut masks: [Bitboard; 15] = [EMPTY; 15];
+    let mut i: usize = 0;
+    while i < 64 {
+        let (file, rank) = (i % 8, 7 - i / 8);
+        let d: usize = if anti { file + rank } else { 7 + file - rank };
+        masks[d].0 |= 1u64 << i;
+        i += 1;
+    }
+    masks
+}
+
 impl Bitboard {
     /// Create a new bitboard instance from a [`u64`].
     pub fn new(b: u64) -> Self {
@@ -21,6 +78,174 @@ impl Bitboard {
     pub fn from_square(s: Square) -> Self {
         Bitboard(1u64 << s.index())
     }
+
+    /// Get the number of set squares.
+    pub fn popcount(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Check whether no square is set.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check whether at least two squares are set.
+    pub fn more_than_one(&self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    /// Check whether the square `s` is set.
+    pub fn contains(&self, s: Square) -> bool {
+        self.0 & (1u64 << s.index()) != 0
+    }
+
+    /// Get the set square with the lowest index, i.e., the one closest to a8.
+    pub fn lsb(&self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Square(self.0.trailing_zeros() as u8))
+        }
+    }
+
+    /// Get the set square with the highest index, i.e., the one closest to h1.
+    pub fn msb(&self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(Square(63 - self.0.leading_zeros() as u8))
+        }
+    }
+
+    /// Remove the set square with the lowest index and return it.
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let s: Option<Square> = self.lsb();
+        self.0 &= self.0.wrapping_sub(1);
+        s
+    }
+
+    /// Iterate over the set squares from a8 to h1, leaving the bitboard unchanged.
+    pub fn squares(&self) -> Squares {
+        Squares(*self)
+    }
+
+    /// Iterate over every subset of the set squares, starting with the empty set and ending
+    /// with the bitboard itself.
+    ///
+    /// This uses the carry-rippler trick and yields `2^popcount` bitboards.
+    pub fn subsets(&self) -> Subsets {
+        Subsets {
+            set: *self,
+            next: Some(EMPTY),
+        }
+    }
+
+    /// Shift every square one rank up, towards the eighth rank.
+    pub fn north(&self) -> Self {
+        Bitboard(self.0 >> 8)
+    }
+
+    /// Shift every square one rank down, towards the first rank.
+    pub fn south(&self) -> Self {
+        Bitboard(self.0 << 8)
+    }
+
+    /// Shift every square one file to the right, dropping those on the h-file.
+    pub fn east(&self) -> Self {
+        Bitboard((self.0 & !FILE_H.0) << 1)
+    }
+
+    /// Shift every square one file to the left, dropping those on the a-file.
+    pub fn west(&self) -> Self {
+        Bitboard((self.0 & !FILE_A.0) >> 1)
+    }
+
+    /// Shift every square diagonally up and to the right.
+    pub fn north_east(&self) -> Self {
+        Bitboard((self.0 & !FILE_H.0) >> 7)
+    }
+
+    /// Shift every square diagonally up and to the left.
+    pub fn north_west(&self) -> Self {
+        Bitboard((self.0 & !FILE_A.0) >> 9)
+    }
+
+    /// Shift every square diagonally down and to the right.
+    pub fn south_east(&self) -> Self {
+        Bitboard((self.0 & !FILE_H.0) << 9)
+    }
+
+    /// Shift every square diagonally down and to the left.
+    pub fn south_west(&self) -> Self {
+        Bitboard((self.0 & !FILE_A.0) << 7)
+    }
+}
+
+/// Iterating over a bitboard yields its set squares from a8 to h1.
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Squares(self)
+    }
+}
+
+impl IntoIterator for &Bitboard {
+    type Item = Square;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Squares(*self)
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(EMPTY, |acc, s| acc | Bitboard::from_square(s))
+    }
+}
+
+/// An iterator over the set squares of a [`Bitboard`], see [`Bitboard::squares()`].
+#[derive(Clone, Debug)]
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n: usize = self.0.popcount() as usize;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+/// An iterator over all subsets of a [`Bitboard`], see [`Bitboard::subsets()`].
+#[derive(Clone, Debug)]
+pub struct Subsets {
+    set: Bitboard,
+    next: Option<Bitboard>,
+}
+
+impl Iterator for Subsets {
+    type Item = Bitboard;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let subset: Bitboard = self.next?;
+        let next: u64 = subset.0.wrapping_sub(self.set.0) & self.set.0;
+        self.next = if next == 0 {
+            None
+        } else {
+            Some(Bitboard(next))
+        };
+        Some(subset)
+    }
 }
 
 impl ops::BitAnd for Bitboard {
@@ -121,6 +346,7 @@ impl fmt::Display for Bitboard {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{bb, sq};
 
     #[test]
     fn op_and() {
@@ -175,6 +401,101 @@ mod tests {
         assert_eq!(b1, !b2);
     }
 
+    #[test]
+    fn masks() {
+        assert_eq!(
+            bb(&["a1", "a2", "a3", "a4", "a5", "a6", "a7", "a8"]),
+            FILE_A
+        );
+        assert_eq!(
+            bb(&["a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1"]),
+            RANKS[0]
+        );
+        assert_eq!(
+            bb(&["a8", "b8", "c8", "d8", "e8", "f8", "g8", "h8"]),
+            RANK_8
+        );
+        assert_eq!(
+            bb(&["a1", "b2", "c3", "d4", "e5", "f6", "g7", "h8"]),
+            DIAGONALS[7]
+        );
+        assert_eq!(
+            bb(&["a8", "b7", "c6", "d5", "e4", "f3", "g2", "h1"]),
+            ANTI_DIAGONALS[7]
+        );
+        assert_eq!(bb(&["h1"]), DIAGONALS[14]);
+        assert_eq!(bb(&["a2", "b1"]), ANTI_DIAGONALS[1]);
+        assert_eq!(FULL, FILES.iter().fold(EMPTY, |acc, f| acc | *f));
+        assert_eq!(FULL, DIAGONALS.iter().fold(EMPTY, |acc, d| acc | *d));
+        assert_eq!(32, LIGHT_SQUARES.popcount());
+        assert!(DARK_SQUARES.contains(sq("a1")) && LIGHT_SQUARES.contains(sq("h1")));
+    }
+
+    #[test]
+    fn queries() {
+        let b: Bitboard = bb(&["b7", "e4", "g1"]);
+        assert_eq!(3, b.popcount());
+        assert!(b.more_than_one());
+        assert!(!bb(&["e4"]).more_than_one());
+        assert!(!EMPTY.more_than_one());
+        assert!(b.contains(sq("e4")));
+        assert!(!b.contains(sq("e5")));
+        assert!(EMPTY.is_empty() && !b.is_empty());
+        assert_eq!(Some(sq("b7")), b.lsb());
+        assert_eq!(Some(sq("g1")), b.msb());
+        assert_eq!(None, EMPTY.lsb());
+        assert_eq!(None, EMPTY.msb());
+    }
+
+    #[test]
+    fn iteration() {
+        let mut b: Bitboard = bb(&["a8", "h1", "d4"]);
+        assert_eq!(3, b.squares().len());
+        assert_eq!(
+            vec![sq("a8"), sq("d4"), sq("h1")],
+            b.squares().collect::<Vec<Square>>()
+        );
+        assert_eq!(3, b.into_iter().count());
+        assert_eq!(3, (&b).into_iter().count());
+        assert_eq!(3, b.popcount());
+        let squares: Vec<Square> = b.into_iter().collect();
+        assert_eq!(b, squares.into_iter().collect::<Bitboard>());
+
+        assert_eq!(Some(sq("a8")), b.pop_lsb());
+        assert_eq!(bb(&["h1", "d4"]), b);
+        b = EMPTY;
+        assert_eq!(None, b.pop_lsb());
+    }
+
+    #[test]
+    fn subsets() {
+        let b: Bitboard = bb(&["a1", "c3", "h8"]);
+        let subsets: Vec<Bitboard> = b.subsets().collect();
+        assert_eq!(8, subsets.len());
+        assert_eq!(EMPTY, subsets[0]);
+        assert_eq!(b, subsets[7]);
+        assert!(subsets.iter().all(|s| *s & !b == EMPTY));
+
+        let mut unique = subsets.clone();
+        unique.sort_by_key(|s| s.0);
+        unique.dedup();
+        assert_eq!(8, unique.len());
+        assert_eq!(vec![EMPTY], EMPTY.subsets().collect::<Vec<Bitboard>>());
+    }
+
+    #[test]
+    fn shifts() {
+        let b: Bitboard = bb(&["a1", "h4", "e8"]);
+        assert_eq!(bb(&["a2", "h5"]), b.north());
+        assert_eq!(bb(&["h3", "e7"]), b.south());
+        assert_eq!(bb(&["b1", "f8"]), b.east());
+        assert_eq!(bb(&["g4", "d8"]), b.west());
+        assert_eq!(bb(&["b2"]), b.north_east());
+        assert_eq!(bb(&["g5"]), b.north_west());
+        assert_eq!(bb(&["f7"]), b.south_east());
+        assert_eq!(bb(&["g3", "d7"]), b.south_west());
+    }
+
     #[test]
     fn display() {
         let bb: Bitboard = Bitboard::new(4);
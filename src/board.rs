@@ -1,27 +1,46 @@
+use crate::attacks::pawn_attacks;
 use crate::bitboard::{Bitboard, EMPTY};
-use crate::castling_rights::{CastlingRights, NO_CASTLING_RIGHTS};
-use crate::color::{Color, NUM_COLORS};
+use crate::castling_rights::{corner_file, CastlingRights, CastlingSide, NO_CASTLING_RIGHTS};
+use crate::chess_move::{Move, MoveFlag};
+use crate::color::{Color, COLORS, NUM_COLORS};
 use crate::error::{ChessifyError, Result};
-use crate::piece::{Piece, NUM_PIECES};
-use crate::square::Square;
+use crate::fen::{
+    back_rank_king, castling_right_from_char, castling_right_possible, en_passant_square_possible,
+    split_fields, validate_position, FenErrorKind, FenField, FenWarning, FEN_FIELDS,
+};
+use crate::piece::{ColoredPiece, Piece, NUM_PIECES, PIECES};
+use crate::square::{File, Rank, Square};
+use crate::zobrist::Zobrist;
 use crate::CastlingStatus;
 
-use std::collections::HashMap;
+use std::error;
 use std::fmt;
 
 /// The standard starting position in chess.
 pub const DEFAULT_BOARD_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+/// The parts of the position that can not be recovered from a [`Move`] alone.
+#[derive(Clone, Copy, Debug)]
+struct UndoState {
+    captured: Option<Piece>,
+    castling_rights: CastlingRights,
+    en_passante_square: Option<Square>,
+    halfmove_clock: usize,
+    zobrist_key: u64,
+}
+
 /// Implementation of a chess board.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Board {
     bitboards: [Bitboard; NUM_PIECES * 2],
-    pieces: HashMap<usize, (Piece, Color)>,
+    pieces: [Option<ColoredPiece>; 64],
     side_to_move: Color,
     castling_rights: CastlingRights,
     en_passante_square: Option<Square>,
     halfmove_clock: usize,
     fullmove_number: usize,
+    zobrist_key: u64,
+    history: Vec<UndoState>,
 }
 
 impl Board {
@@ -30,11 +49,44 @@ impl Board {
         &self.bitboards
     }
 
-    /// Get the current mapping of what pieces exist on what squares.
-    pub fn pieces(&self) -> &HashMap<usize, (Piece, Color)> {
+    /// Get the bitboard of a specific piece type of a specific color.
+    pub fn piece_bitboard(&self, p: Piece, c: Color) -> Bitboard {
+        self.bitboards[c.as_index() * NUM_PIECES + p.as_index()]
+    }
+
+    /// Get the bitboard of all squares occupied by pieces of a specific color.
+    pub fn color_bitboard(&self, c: Color) -> Bitboard {
+        let offset: usize = c.as_index() * NUM_PIECES;
+        self.bitboards[offset..offset + NUM_PIECES]
+            .iter()
+            .fold(EMPTY, |acc, bb| acc | *bb)
+    }
+
+    /// Get the bitboard of all occupied squares.
+    pub fn occupied(&self) -> Bitboard {
+        self.bitboards.iter().fold(EMPTY, |acc, bb| acc | *bb)
+    }
+
+    /// Get the square of the king of a specific color, if there is one on the board.
+    pub fn king_square(&self, c: Color) -> Option<Square> {
+        self.piece_bitboard(Piece::King, c).lsb()
+    }
+
+    /// Get the current mapping of what pieces exist on what squares, indexed by square.
+    pub fn pieces(&self) -> &[Option<ColoredPiece>; 64] {
         &self.pieces
     }
 
+    /// Get the piece standing on a square, if any.
+    pub fn piece_at(&self, s: Square) -> Option<Piece> {
+        self.pieces[s.index()].map(|cp| cp.piece)
+    }
+
+    /// Get the color of the piece standing on a square, if any.
+    pub fn color_at(&self, s: Square) -> Option<Color> {
+        self.pieces[s.index()].map(|cp| cp.color)
+    }
+
     /// Get which players turn it is to make a move.
     pub fn side_to_move(&self) -> Color {
         self.side_to_move
@@ -55,16 +107,57 @@ impl Board {
         self.en_passante_square
     }
 
+    /// Get the number of halfmoves since the last capture or pawn advance.
+    pub fn halfmove_clock(&self) -> usize {
+        self.halfmove_clock
+    }
+
+    /// Get the number of the full move, which is incremented after black has moved.
+    pub fn fullmove_number(&self) -> usize {
+        self.fullmove_number
+    }
+
+    /// Get the 64 bit Zobrist key of the position.
+    ///
+    /// The key covers the piece placement, the side to move, the castling rights and the en
+    /// passant file, and it is kept up to date by [`Board::make_move()`]. It follows the
+    /// Polyglot scheme, so the en passant file only counts if a pawn of the side to move stands
+    /// ready to capture.
+    pub fn zobrist_key(&self) -> u64 {
+        self.zobrist_key
+    }
+
+    /// Get how many times the current position has occurred in the game, including now.
+    ///
+    /// Only positions reached through [`Board::make_move()`] since the last capture or pawn
+    /// advance are considered, as no earlier position can repeat.
+    pub fn repetition_count(&self) -> usize {
+        // Every entry holds the key before a move, so the position with the same side to move
+        // sits two entries back.
+        let reversible: usize = self.halfmove_clock.min(self.history.len());
+        1 + self
+            .history
+            .iter()
+            .rev()
+            .take(reversible)
+            .skip(1)
+            .step_by(2)
+            .filter(|state| state.zobrist_key == self.zobrist_key)
+            .count()
+    }
+
     /// Create a new [`Board`] that is completely empty.
     pub fn empty() -> Self {
         Board {
             bitboards: [EMPTY; NUM_PIECES * NUM_COLORS],
-            pieces: HashMap::new(),
+            pieces: [None; 64],
             side_to_move: Color::White,
             castling_rights: NO_CASTLING_RIGHTS,
             en_passante_square: None,
             halfmove_clock: 0,
             fullmove_number: 0,
+            zobrist_key: Zobrist::white_to_move(),
+            history: Vec::new(),
         }
     }
 
@@ -94,10 +187,291 @@ impl Board {
     /// # Errors
     /// Iff the user provided an invalid FEN string.
     pub fn try_from_fen(fen: &str) -> Result<Self> {
-        Ok(BoardBuilder::try_from_fen(fen)?.try_build()?)
+        BoardBuilder::try_from_fen(fen)?.try_build()
+    }
+
+    /// Try and create a new [`Board`] from a Chess960 FEN string.
+    ///
+    /// # Errors
+    /// See [`BoardBuilder::try_from_fen_chess960()`].
+    pub fn try_from_fen_chess960(fen: &str) -> Result<Self> {
+        BoardBuilder::try_from_fen_chess960(fen)?.try_build()
+    }
+
+    /// Try and create a new [`Board`] from a FEN string, rejecting positions that can not occur in a game.
+    ///
+    /// # Errors
+    /// See [`BoardBuilder::try_from_fen_strict()`] for the checks that are performed.
+    pub fn try_from_fen_strict(fen: &str) -> Result<Self> {
+        BoardBuilder::try_from_fen_strict(fen)?.try_build()
+    }
+
+    /// Try and create a new [`Board`] from a FEN string, repairing what can be repaired.
+    ///
+    /// # Errors
+    /// See [`BoardBuilder::try_from_fen_lenient()`] for what is repaired and what is rejected.
+    pub fn try_from_fen_lenient(fen: &str) -> Result<(Self, Vec<FenWarning>)> {
+        let (builder, warnings) = BoardBuilder::try_from_fen_lenient(fen)?;
+        Ok((builder.try_build()?, warnings))
+    }
+
+    /// Serialize the position into a Forsyth-Edwards-Notation (FEN) string.
+    ///
+    /// The result can be parsed again with [`Board::from_fen()`] and yields an equal board.
+    /// Castling rights are written as `KQkq` if king and rook start on their squares of standard
+    /// chess, Chess960 rights as the file letter of the rook as in Shredder-FEN.
+    pub fn to_fen(&self) -> String {
+        let mut fen: String = String::with_capacity(90);
+
+        for rank in 0..8 {
+            let mut empty: u8 = 0;
+            for file in 0..8 {
+                match self.pieces[rank * 8 + file] {
+                    Some(cp) => {
+                        if empty > 0 {
+                            fen.push((b'0' + empty) as char);
+                            empty = 0;
+                        }
+                        fen.push_str(&cp.piece.to_string(cp.color));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push((b'0' + empty) as char);
+            }
+            if rank < 7 {
+                fen.push('/');
+            }
+        }
+
+        let en_passante_square: String = match self.en_passante_square {
+            Some(s) => s.to_string(),
+            None => "-".to_string(),
+        };
+
+        let castling_rights: String = self.castling_rights.letters(|c, side, file| {
+            let standard: bool = back_rank_king(&self.bitboards, c) == Some(File(4));
+            (!standard || file != corner_file(side)).then_some(file)
+        });
+
+        format!(
+            "{} {} {} {} {} {}",
+            fen,
+            self.side_to_move.to_string().to_lowercase(),
+            castling_rights,
+            en_passante_square,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// Play a move on the board, updating every part of the position.
+    ///
+    /// The move is expected to be legal in the current position, e.g., taken from
+    /// [`Board::legal_moves()`]. Playing anything else leaves the board in an unspecified state.
+    /// Use [`Board::unmake_move()`] with the same move to restore the previous position.
+    pub fn make_move(&mut self, m: Move) {
+        let us: Color = self.side_to_move;
+        let (from, to) = (m.from(), m.to());
+        let piece: Piece = self.piece_at(from).expect("no piece on the square to move");
+        let zobrist_key: u64 = self.zobrist_key;
+        self.zobrist_key ^= self.en_passant_key() ^ Zobrist::castling(self.castling_rights);
+
+        let captured: Option<Piece> = match m.flag() {
+            MoveFlag::EnPassant => Some(self.remove_piece(square_behind(to, us)).0),
+            _ if m.is_capture() => Some(self.remove_piece(to).0),
+            _ => None,
+        };
+
+        self.history.push(UndoState {
+            captured,
+            castling_rights: self.castling_rights,
+            en_passante_square: self.en_passante_square,
+            halfmove_clock: self.halfmove_clock,
+            zobrist_key,
+        });
+
+        if m.is_castle() {
+            // In Chess960 king and rook may end up on each other's squares, so both leave first.
+            let (rook_from, rook_to) = self.castling_rook_squares(m);
+            self.remove_piece(from);
+            self.remove_piece(rook_from);
+            self.put_piece(to, Piece::King, us);
+            self.put_piece(rook_to, Piece::Rook, us);
+        } else {
+            self.remove_piece(from);
+            self.put_piece(to, m.promotion().unwrap_or(piece), us);
+        }
+
+        self.en_passante_square = match m.flag() {
+            MoveFlag::DoublePawnPush => Some(square_behind(to, us)),
+            _ => None,
+        };
+
+        // Moving the king or a castling rook, or capturing the latter, forfeits the rights.
+        if piece == Piece::King {
+            self.castling_rights.remove_color(us);
+        }
+        self.castling_rights.remove_rook(from);
+        self.castling_rights.remove_rook(to);
+
+        if piece == Piece::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if us == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = !us;
+
+        self.zobrist_key ^= Zobrist::white_to_move()
+            ^ self.en_passant_key()
+            ^ Zobrist::castling(self.castling_rights);
+    }
+
+    /// Take back a move previously played with [`Board::make_move()`].
+    ///
+    /// # Panics
+    /// If no move has been played on this board.
+    pub fn unmake_move(&mut self, m: Move) {
+        let state: UndoState = self
+            .history
+            .pop()
+            .expect("unmake_move called without a matching make_move");
+
+        let us: Color = !self.side_to_move;
+        let (from, to) = (m.from(), m.to());
+
+        self.side_to_move = us;
+        if us == Color::Black {
+            self.fullmove_number -= 1;
+        }
+
+        if m.is_castle() {
+            // The rook squares depend on the castling rights from before the move.
+            self.castling_rights = state.castling_rights;
+            let (rook_from, rook_to) = self.castling_rook_squares(m);
+            self.remove_piece(to);
+            self.remove_piece(rook_to);
+            self.put_piece(from, Piece::King, us);
+            self.put_piece(rook_from, Piece::Rook, us);
+        } else {
+            let (piece, _) = self.remove_piece(to);
+            self.put_piece(from, if m.is_promotion() { Piece::Pawn } else { piece }, us);
+        }
+
+        if let Some(captured) = state.captured {
+            let square: Square = if m.is_en_passant() {
+                square_behind(to, us)
+            } else {
+                to
+            };
+            self.put_piece(square, captured, !us);
+        }
+
+        self.castling_rights = state.castling_rights;
+        self.en_passante_square = state.en_passante_square;
+        self.halfmove_clock = state.halfmove_clock;
+        self.zobrist_key = state.zobrist_key;
+    }
+
+    /// The origin and destination of the rook for a castling move.
+    ///
+    /// The origin is taken from the current castling rights, so that Chess960 rooks are found.
+    pub(crate) fn castling_rook_squares(&self, m: Move) -> (Square, Square) {
+        let back_rank: u8 = m.from().0 / 8 * 8;
+        let c: Color = if back_rank == 0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        let (side, rook_to) = match m.flag() {
+            MoveFlag::KingCastle => (CastlingSide::Kingside, 5),
+            _ => (CastlingSide::Queenside, 3),
+        };
+        let rook_from: File = self
+            .castling_rights
+            .rook_file(c, side)
+            .unwrap_or(corner_file(side));
+        (Square(back_rank + rook_from.0), Square(back_rank + rook_to))
+    }
+
+    fn put_piece(&mut self, s: Square, p: Piece, c: Color) {
+        self.bitboards[c.as_index() * NUM_PIECES + p.as_index()] |= Bitboard::from_square(s);
+        self.pieces[s.index()] = Some(ColoredPiece::new(p, c));
+        self.zobrist_key ^= Zobrist::piece(p, c, s);
+    }
+
+    fn remove_piece(&mut self, s: Square) -> (Piece, Color) {
+        let ColoredPiece { piece: p, color: c } = self.pieces[s.index()]
+            .take()
+            .expect("no piece on the square to remove");
+        self.bitboards[c.as_index() * NUM_PIECES + p.as_index()] ^= Bitboard::from_square(s);
+        self.zobrist_key ^= Zobrist::piece(p, c, s);
+        (p, c)
+    }
+
+    /// The part of the Zobrist key contributed by the en passant square.
+    ///
+    /// Polyglot only hashes the en passant file if a pawn of the side to move could capture.
+    fn en_passant_key(&self) -> u64 {
+        match self.en_passante_square {
+            Some(s) => {
+                let us: Color = self.side_to_move;
+                if pawn_attacks(!us, s) & self.piece_bitboard(Piece::Pawn, us) != EMPTY {
+                    Zobrist::en_passant(s.file())
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        }
+    }
+
+    /// Compute the Zobrist key of the position from scratch.
+    fn compute_zobrist_key(&self) -> u64 {
+        let mut key: u64 = self.en_passant_key() ^ Zobrist::castling(self.castling_rights);
+        if self.side_to_move == Color::White {
+            key ^= Zobrist::white_to_move();
+        }
+        for (s, cp) in self.pieces.iter().enumerate() {
+            if let Some(cp) = cp {
+                key ^= Zobrist::piece(cp.piece, cp.color, Square::from_index(s));
+            }
+        }
+        key
+    }
+}
+
+/// The square directly behind `s` as seen from the side of color `c`.
+///
+/// This is both the square a pawn skipped with its double push and the square of the pawn
+/// captured en passant.
+fn square_behind(s: Square, c: Color) -> Square {
+    match c {
+        Color::White => Square(s.0 + 8),
+        Color::Black => Square(s.0 - 8),
+    }
+}
+
+/// Two boards are equal if they describe the same position, regardless of how they got there.
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.bitboards == other.bitboards
+            && self.pieces == other.pieces
+            && self.side_to_move == other.side_to_move
+            && self.castling_rights == other.castling_rights
+            && self.en_passante_square == other.en_passante_square
+            && self.halfmove_clock == other.halfmove_clock
+            && self.fullmove_number == other.fullmove_number
     }
 }
 
+impl Eq for Board {}
+
 impl Default for Board {
     fn default() -> Self {
         BoardBuilder::from_fen(DEFAULT_BOARD_FEN).build()
@@ -114,8 +488,8 @@ impl fmt::Display for Board {
                     write!(f, " {} |", 8 - rank)?;
                 }
 
-                if let Some((piece, color)) = self.pieces.get(&(rank * 8 + field)) {
-                    write!(f, " {} ", piece.to_string(*color))?;
+                if let Some(cp) = self.pieces[rank * 8 + field] {
+                    write!(f, " {} ", cp)?;
                 } else {
                     write!(f, " . ")?;
                 }
@@ -128,11 +502,21 @@ impl fmt::Display for Board {
     }
 }
 
+/// How thoroughly a FEN string is checked while parsing it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FenMode {
+    /// Only reject FEN strings that can not be read at all.
+    Standard,
+    /// Also reject positions that can not occur in a game.
+    Strict,
+    /// Like strict, but repair what can be repaired and report it.
+    Lenient,
+}
+
 /// A helper struct for building an instance of a [`Board`] struct.
 #[derive(Debug, Default)]
 pub struct BoardBuilder {
     bitboards: Option<[Bitboard; NUM_PIECES * NUM_COLORS]>,
-    pieces: HashMap<usize, (Piece, Color)>,
     side_to_move: Option<Color>,
     castling_rights: Option<CastlingRights>,
     en_passante_square: Option<Square>,
@@ -175,16 +559,29 @@ impl BoardBuilder {
             .castling_rights
             .ok_or_else(|| Box::new(ChessifyError::BoardSetup("".to_string())))?;
 
+        // The mailbox is derived from the bitboards so that the two can not disagree.
+        let mut pieces: [Option<ColoredPiece>; 64] = [None; 64];
+        for (i, bb) in bitboards.iter().enumerate() {
+            let cp: ColoredPiece =
+                ColoredPiece::new(PIECES[i % NUM_PIECES], COLORS[i / NUM_PIECES]);
+            for s in *bb {
+                pieces[s.index()] = Some(cp);
+            }
+        }
 
-        Ok(Board {
+        let mut board: Board = Board {
             bitboards,
-            pieces: self.pieces,
+            pieces,
             side_to_move,
             castling_rights,
             en_passante_square: self.en_passante_square,
             halfmove_clock: self.halfmove_clock,
             fullmove_number: self.fullmove_number,
-        })
+            zobrist_key: 0,
+            history: Vec::new(),
+        };
+        board.zobrist_key = board.compute_zobrist_key();
+        Ok(board)
     }
 
     /// Set up a board state from a provided FEN string.
@@ -217,142 +614,876 @@ impl BoardBuilder {
     ///
     /// Below you can see the FEN for the starting position:
     /// rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
+    ///
+    /// The halfmove clock and fullmove number may be left out together, in which case they
+    /// default to `0 1`.
+    ///
+    /// `KQkq` castling rights belong to a king on the e-file and a rook in the corner and are
+    /// dropped otherwise. For Chess960 the castling field may instead name the file of the
+    /// castling rook as in Shredder-FEN (`HAha`), see [`BoardBuilder::try_from_fen_chess960()`]
+    /// for X-FEN.
     pub fn try_from_fen(fen: &str) -> Result<BoardBuilder> {
-        let parts: Vec<&str> = fen.split_whitespace().collect();
-        if parts.len() < 4 {
-            return Err(Box::new(ChessifyError::InvalidFen(fen.to_string())));
+        Ok(BoardBuilder::parse_fen(fen, FenMode::Standard, false)?.0)
+    }
+
+    /// Try to set up a Chess960 board state from a provided FEN string.
+    ///
+    /// # Errors
+    /// If the provided FEN string was invalid, see [`BoardBuilder::try_from_fen()`].
+    ///
+    /// # Details
+    /// Unlike in standard chess, `KQkq` castling rights refer to the outermost rook on that side
+    /// of the king as in X-FEN. The file of an inner rook has to be given instead, as may be the
+    /// file of every rook as in Shredder-FEN.
+    pub fn try_from_fen_chess960(fen: &str) -> Result<BoardBuilder> {
+        Ok(BoardBuilder::parse_fen(fen, FenMode::Standard, true)?.0)
+    }
+
+    /// Try to set up a board state from a provided FEN string, rejecting any position that
+    /// can not occur in a game.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidFenField`] error naming the field, the character offset
+    /// and the reason if the FEN string fails any of the checks of [`BoardBuilder::try_from_fen()`]
+    /// or if
+    /// - it does not consist of exactly six fields,
+    /// - a color does not have exactly one king, or more than 8 pawns or 16 pieces,
+    /// - a pawn stands on the first or eighth rank,
+    /// - the side not to move is in check,
+    /// - a castling right is listed twice or without king and rook on their back rank squares,
+    ///   or as `KQkq` without the king on the e-file,
+    /// - the en passant square does not follow a double pawn push,
+    /// - the fullmove number is zero.
+    pub fn try_from_fen_strict(fen: &str) -> Result<BoardBuilder> {
+        Ok(BoardBuilder::parse_fen(fen, FenMode::Strict, false)?.0)
+    }
+
+    /// Try to set up a board state from a provided FEN string, repairing what can be repaired.
+    ///
+    /// Impossible castling rights and en passant squares are dropped, missing clocks default to
+    /// `0 1` and a fullmove number of zero becomes one. Every repair is reported as a
+    /// [`FenWarning`].
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidFenField`] error for everything that can not be repaired,
+    /// i.e., malformed fields, missing or extra kings, pawns on the back ranks, too many pieces
+    /// and the side not to move being in check.
+    pub fn try_from_fen_lenient(fen: &str) -> Result<(BoardBuilder, Vec<FenWarning>)> {
+        BoardBuilder::parse_fen(fen, FenMode::Lenient, false)
+    }
+
+    fn parse_fen(
+        fen: &str,
+        mode: FenMode,
+        chess960: bool,
+    ) -> Result<(BoardBuilder, Vec<FenWarning>)> {
+        let error = |field: FenField, offset: usize, kind: FenErrorKind| -> Box<dyn error::Error> {
+            Box::new(ChessifyError::InvalidFenField {
+                field,
+                offset,
+                kind,
+            })
+        };
+        let mut warnings: Vec<FenWarning> = Vec::new();
+
+        let parts: Vec<(usize, &str)> = split_fields(fen);
+        let expected: &'static [usize] = match mode {
+            FenMode::Standard => &[4, 6],
+            FenMode::Strict => &[6],
+            FenMode::Lenient => &[4, 5, 6],
+        };
+        let field_count_ok: bool = expected.contains(&parts.len());
+        if parts.len() != 6 {
+            let field: FenField = FEN_FIELDS[parts.len().min(5)];
+            let offset: usize = parts.get(6).map_or(fen.chars().count(), |(o, _)| *o);
+            let kind: FenErrorKind = FenErrorKind::FieldCount {
+                found: parts.len(),
+                expected,
+            };
+            if !field_count_ok {
+                return Err(error(field, offset, kind));
+            }
+            if mode == FenMode::Lenient {
+                warnings.push(FenWarning {
+                    field,
+                    offset,
+                    kind,
+                });
+            }
         }
 
         // Initialize board state as empty.
         let mut bitboards: [Bitboard; NUM_PIECES * NUM_COLORS] = [EMPTY; NUM_PIECES * NUM_COLORS];
-        let mut pieces: HashMap<usize, (Piece, Color)> = HashMap::new();
+        // The character offset of every piece, to point errors about a piece at its origin.
+        let mut piece_offsets: [usize; 64] = [0; 64];
 
         // In reality we are actually going from the 8th rank to the 1st rank,
         // but we calculate this counter backwards because its easier with indices...
         let mut rank: usize = 0;
         let mut file: usize = 0;
 
-        let piece_placement_str: &str = parts[0];
-        let active_color_str: &str = parts[1];
-        let castling_rights_str: &str = parts[2];
-        let en_passant_square_str: &str = parts[3];
+        let (placement_offset, piece_placement_str) = parts[0];
+        let rank_count: usize = piece_placement_str.split('/').count();
 
-        for c in piece_placement_str.chars() {
+        for (i, c) in piece_placement_str.chars().enumerate() {
+            let offset: usize = placement_offset + i;
             if c == '/' {
+                if file < 8 {
+                    return Err(error(
+                        FenField::PiecePlacement,
+                        offset,
+                        FenErrorKind::RankTooShort,
+                    ));
+                }
+                if rank == 7 {
+                    return Err(error(
+                        FenField::PiecePlacement,
+                        offset,
+                        FenErrorKind::RankCount(rank_count),
+                    ));
+                }
                 rank += 1;
                 file = 0;
                 continue;
             }
 
-            let s: Square = Square::from_index(rank * 8 + file);
-            let bb_idx: usize;
-
-            let piece: Piece;
-            let color: Color;
-
-            match c {
+            let bb_idx: usize = match c {
                 '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' => {
                     // We need to subtract 48 here because the char '1' byte value is 49.
                     // See the ASCII table for more details: https://www.ascii-code.com
                     file += (c as usize) - 48;
+                    if file > 8 {
+                        return Err(error(
+                            FenField::PiecePlacement,
+                            offset,
+                            FenErrorKind::RankTooLong,
+                        ));
+                    }
                     continue;
                 }
-                'P' => {
-                    bb_idx = 0;
-                    piece = Piece::Pawn;
-                    color = Color::White;
-                }
-                'N' => {
-                    bb_idx = 1;
-                    piece = Piece::Knight;
-                    color = Color::White;
-                }
-                'B' => {
-                    bb_idx = 2;
-                    piece = Piece::Bishop;
-                    color = Color::White;
-                }
-                'R' => {
-                    bb_idx = 3;
-                    piece = Piece::Rook;
-                    color = Color::White;
-                }
-                'Q' => {
-                    bb_idx = 4;
-                    piece = Piece::Queen;
-                    color = Color::White;
-                }
-                'K' => {
-                    bb_idx = 5;
-                    piece = Piece::King;
-                    color = Color::White;
-                }
-                'p' => {
-                    bb_idx = 6;
-                    piece = Piece::Pawn;
-                    color = Color::Black;
-                }
-                'n' => {
-                    bb_idx = 7;
-                    piece = Piece::Knight;
-                    color = Color::Black;
-                }
-                'b' => {
-                    bb_idx = 8;
-                    piece = Piece::Bishop;
-                    color = Color::Black;
-                }
-                'r' => {
-                    bb_idx = 9;
-                    piece = Piece::Rook;
-                    color = Color::Black;
-                }
-                'q' => {
-                    bb_idx = 10;
-                    piece = Piece::Queen;
-                    color = Color::Black;
-                }
-                'k' => {
-                    bb_idx = 11;
-                    piece = Piece::King;
-                    color = Color::Black;
-                }
+                'P' => 0,
+                'N' => 1,
+                'B' => 2,
+                'R' => 3,
+                'Q' => 4,
+                'K' => 5,
+                'p' => 6,
+                'n' => 7,
+                'b' => 8,
+                'r' => 9,
+                'q' => 10,
+                'k' => 11,
                 _ => {
-                    return Err(Box::new(ChessifyError::InvalidFen(fen.to_string())));
+                    return Err(error(
+                        FenField::PiecePlacement,
+                        offset,
+                        FenErrorKind::UnexpectedCharacter(c),
+                    ));
                 }
+            };
+
+            if file >= 8 {
+                return Err(error(
+                    FenField::PiecePlacement,
+                    offset,
+                    FenErrorKind::RankTooLong,
+                ));
             }
 
+            let s: Square = Square::from_index(rank * 8 + file);
             bitboards[bb_idx] |= Bitboard::from_square(s);
-            pieces.insert(rank * 8 + file, (piece, color));
+            piece_offsets[rank * 8 + file] = offset;
             file += 1;
         }
 
-        let side_to_move: Color = Color::try_from_str(active_color_str)?;
+        let placement_end: usize = placement_offset + piece_placement_str.chars().count();
+        if rank < 7 {
+            return Err(error(
+                FenField::PiecePlacement,
+                placement_end,
+                FenErrorKind::RankCount(rank_count),
+            ));
+        }
+        if file < 8 {
+            return Err(error(
+                FenField::PiecePlacement,
+                placement_end,
+                FenErrorKind::RankTooShort,
+            ));
+        }
+
+        let (color_offset, active_color_str) = parts[1];
+        let side_to_move: Color = match active_color_str {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => {
+                // Only strict mode insists on a single lowercase letter.
+                let unexpected = active_color_str
+                    .chars()
+                    .enumerate()
+                    .find(|(i, c)| match mode {
+                        FenMode::Strict => *i > 0 || !matches!(c, 'w' | 'b'),
+                        _ => *i == 0 && !matches!(c, 'w' | 'W' | 'b' | 'B'),
+                    });
+                match unexpected {
+                    Some((i, c)) => {
+                        return Err(error(
+                            FenField::ActiveColor,
+                            color_offset + i,
+                            FenErrorKind::UnexpectedCharacter(c),
+                        ));
+                    }
+                    None => Color::try_from_str(active_color_str)?,
+                }
+            }
+        };
+
+        if mode != FenMode::Standard {
+            if let Err((kind, square)) = validate_position(&bitboards, side_to_move) {
+                return Err(match (kind, square) {
+                    (FenErrorKind::OpponentInCheck, _) => {
+                        error(FenField::ActiveColor, color_offset, kind)
+                    }
+                    (_, Some(s)) => error(FenField::PiecePlacement, piece_offsets[s.index()], kind),
+                    (_, None) => error(FenField::PiecePlacement, placement_offset, kind),
+                });
+            }
+        }
+
+        let (castling_offset, castling_rights_str) = parts[2];
+        let mut castling_rights: CastlingRights = NO_CASTLING_RIGHTS;
+        if castling_rights_str != "-" {
+            for (i, c) in castling_rights_str.chars().enumerate() {
+                let offset: usize = castling_offset + i;
+                let (color, side, rook_file) = castling_right_from_char(&bitboards, c, chess960)
+                    .ok_or_else(|| {
+                        error(
+                            FenField::CastlingRights,
+                            offset,
+                            FenErrorKind::UnexpectedCharacter(c),
+                        )
+                    })?;
 
-        let castling_rights: CastlingRights = CastlingRights::try_from(castling_rights_str)?;
+                // Outside of Chess960 only file letters may castle with a king off the e-file.
+                let standard_right: bool = chess960
+                    || !matches!(c, 'K' | 'Q' | 'k' | 'q')
+                    || back_rank_king(&bitboards, color) == Some(File(4));
+                let problem: Option<FenErrorKind> = if castling_rights.has(color, side) {
+                    Some(FenErrorKind::DuplicateCastlingRight(c))
+                } else if !standard_right
+                    || (mode != FenMode::Standard
+                        && !castling_right_possible(&bitboards, color, side, rook_file))
+                {
+                    Some(FenErrorKind::ImpossibleCastlingRight(c))
+                } else {
+                    None
+                };
+
+                match (problem, mode) {
+                    (Some(kind), FenMode::Strict) => {
+                        return Err(error(FenField::CastlingRights, offset, kind));
+                    }
+                    (Some(kind), FenMode::Lenient) => warnings.push(FenWarning {
+                        field: FenField::CastlingRights,
+                        offset,
+                        kind,
+                    }),
+                    (Some(FenErrorKind::ImpossibleCastlingRight(_)), FenMode::Standard) => {}
+                    _ => castling_rights.insert(color, side, rook_file),
+                }
+            }
+        }
 
-        let en_passante_square: Option<Square> = match en_passant_square_str {
+        let (en_passant_offset, en_passant_square_str) = parts[3];
+        let mut en_passante_square: Option<Square> = match en_passant_square_str {
             "-" => None,
-            _ => Some(Square::try_from(en_passant_square_str)?),
+            _ => {
+                let unexpected = |i: usize| {
+                    let c: char = en_passant_square_str.chars().nth(i).unwrap_or(' ');
+                    error(
+                        FenField::EnPassantSquare,
+                        en_passant_offset + i,
+                        FenErrorKind::UnexpectedCharacter(c),
+                    )
+                };
+                if en_passant_square_str.chars().count() != 2 {
+                    return Err(unexpected(2.min(en_passant_square_str.chars().count())));
+                }
+                let file: File =
+                    File::try_from(en_passant_square_str.chars().next().unwrap_or(' '))
+                        .map_err(|_| unexpected(0))?;
+                let rank: Rank =
+                    Rank::try_from(en_passant_square_str.chars().nth(1).unwrap_or(' '))
+                        .map_err(|_| unexpected(1))?;
+                Some(Square(rank.0 * 8 + file.0))
+            }
         };
 
+        if let Some(s) = en_passante_square {
+            if mode != FenMode::Standard && !en_passant_square_possible(&bitboards, side_to_move, s)
+            {
+                let kind: FenErrorKind = FenErrorKind::ImpossibleEnPassantSquare;
+                if mode == FenMode::Strict {
+                    return Err(error(FenField::EnPassantSquare, en_passant_offset, kind));
+                }
+                warnings.push(FenWarning {
+                    field: FenField::EnPassantSquare,
+                    offset: en_passant_offset,
+                    kind,
+                });
+                en_passante_square = None;
+            }
+        }
 
-        let mut halfmove_clock: usize = 0;
-        let mut fullmove_number: usize = 0;
+        let mut clocks: [usize; 2] = [0, 1];
+        for (i, field) in [(4, FenField::HalfmoveClock), (5, FenField::FullmoveNumber)] {
+            if let Some((offset, clock_str)) = parts.get(i) {
+                clocks[i - 4] = clock_str
+                    .parse()
+                    .map_err(|_| error(field, *offset, FenErrorKind::InvalidNumber))?;
+            }
+        }
+        let [halfmove_clock, mut fullmove_number] = clocks;
 
-        if parts.len() == 6 {
-            halfmove_clock = parts[4].parse()?;
-            fullmove_number = parts[5].parse()?;
+        if fullmove_number == 0 && mode != FenMode::Standard {
+            let offset: usize = parts[5].0;
+            let kind: FenErrorKind = FenErrorKind::InvalidNumber;
+            if mode == FenMode::Strict {
+                return Err(error(FenField::FullmoveNumber, offset, kind));
+            }
+            warnings.push(FenWarning {
+                field: FenField::FullmoveNumber,
+                offset,
+                kind,
+            });
+            fullmove_number = 1;
         }
 
-        Ok(BoardBuilder {
+        let builder: BoardBuilder = BoardBuilder {
             bitboards: Some(bitboards),
-            pieces,
             side_to_move: Some(side_to_move),
             castling_rights: Some(castling_rights),
             en_passante_square,
             halfmove_clock,
             fullmove_number,
-        })
+        };
+        Ok((builder, warnings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Snapshot = (
+        [Bitboard; NUM_PIECES * 2],
+        [Option<ColoredPiece>; 64],
+        Color,
+        CastlingRights,
+        Option<Square>,
+        usize,
+        usize,
+    );
+
+    fn snapshot(b: &Board) -> Snapshot {
+        (
+            *b.bitboards(),
+            *b.pieces(),
+            b.side_to_move(),
+            b.castling_rights(),
+            b.en_passante_square(),
+            b.halfmove_clock(),
+            b.fullmove_number(),
+        )
+    }
+
+    fn find(b: &Board, uci: &str) -> Move {
+        b.legal_moves()
+            .into_iter()
+            .find(|m| m.to_string() == uci)
+            .unwrap()
+    }
+
+    #[test]
+    fn make_unmake_restores_position() {
+        let fens: [&str; 5] = [
+            DEFAULT_BOARD_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+        ];
+
+        for fen in fens {
+            let mut b = Board::from_fen(fen);
+            let before = snapshot(&b);
+            for m in b.legal_moves() {
+                b.make_move(m);
+                for reply in b.legal_moves() {
+                    b.make_move(reply);
+                    b.unmake_move(reply);
+                }
+                b.unmake_move(m);
+                assert_eq!(before, snapshot(&b), "{} {}", fen, m);
+            }
+        }
+    }
+
+    #[test]
+    fn make_move_updates_state() {
+        let mut b = Board::default();
+        b.make_move(find(&b, "e2e4"));
+
+        assert_eq!(Color::Black, b.side_to_move());
+        assert_eq!(Some(Square::from_str("e3")), b.en_passante_square());
+        assert_eq!(0, b.halfmove_clock());
+        assert_eq!(1, b.fullmove_number());
+
+        b.make_move(find(&b, "g8f6"));
+        assert_eq!(None, b.en_passante_square());
+        assert_eq!(1, b.halfmove_clock());
+        assert_eq!(2, b.fullmove_number());
+
+        b.make_move(find(&b, "e1e2"));
+        assert_eq!(CastlingRights::from_str("kq"), b.castling_rights());
+    }
+
+    #[test]
+    fn make_move_special_moves() {
+        let mut b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        b.make_move(find(&b, "e1g1"));
+        assert_eq!(Some(Piece::Rook), b.piece_at(Square::from_str("f1")));
+        assert_eq!(Some(Color::White), b.color_at(Square::from_str("f1")));
+        assert_eq!(None, b.piece_at(Square::from_str("h1")));
+        assert_eq!(CastlingRights::from_str("kq"), b.castling_rights());
+
+        // Capturing the rook on h1 removes the white kingside right.
+        let mut b = Board::from_fen("r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 0 1");
+        b.make_move(find(&b, "g2h1"));
+        assert_eq!(CastlingRights::from_str("Qkq"), b.castling_rights());
+
+        let mut b =
+            Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3");
+        b.make_move(find(&b, "e5f6"));
+        assert_eq!(None, b.color_at(Square::from_str("f5")));
+        assert_eq!(
+            EMPTY,
+            b.piece_bitboard(Piece::Pawn, Color::Black)
+                & Bitboard::from_square(Square::from_str("f5"))
+        );
+
+        let mut b = Board::from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1");
+        b.make_move(find(&b, "a7b8n"));
+        assert_eq!(
+            Some(ColoredPiece::new(Piece::Knight, Color::White)),
+            b.pieces()[Square::from_str("b8").index()]
+        );
+        assert_eq!(EMPTY, b.piece_bitboard(Piece::Pawn, Color::White));
+    }
+
+    #[test]
+    fn chess960_castling() {
+        // The king stays on g1 and the rook jumps over it from h1 to f1.
+        let mut b = Board::from_fen("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1");
+        let short = find(&b, "g1g1");
+        assert_eq!(MoveFlag::KingCastle, short.flag());
+        b.make_move(short);
+        assert_eq!("1r4kr/8/8/8/8/8/8/1R3RK1 b hb - 1 1", b.to_fen());
+        b.unmake_move(short);
+        assert_eq!("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", b.to_fen());
+
+        // King and rook swap places, or the rook stays where it is.
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/8/5KR1 w G - 0 1");
+        b.make_move(find(&b, "f1g1"));
+        assert_eq!("4k3/8/8/8/8/8/8/5RK1 b - - 1 1", b.to_fen());
+
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/8/3RK3 w D - 0 1");
+        let long = find(&b, "e1c1");
+        assert_eq!(MoveFlag::QueenCastle, long.flag());
+        b.make_move(long);
+        assert_eq!("4k3/8/8/8/8/8/8/2KR4 b - - 1 1", b.to_fen());
+
+        // The king may travel the whole way, but every square on it has to be safe.
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/1KR5 w C - 0 1");
+        assert_eq!(MoveFlag::KingCastle, find(&b, "b1g1").flag());
+        let b = Board::from_fen("4k3/8/8/8/8/8/3r4/1KR5 w C - 0 1");
+        assert!(b.legal_moves().iter().all(|m| !m.is_castle()));
+
+        // A rook on the back rank that the castling rook shields still counts.
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/rR4K1 w B - 0 1");
+        assert!(b.legal_moves().iter().all(|m| !m.is_castle()));
+
+        // Moving the castling rook only forfeits its own right.
+        let mut b = Board::from_fen("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1");
+        b.make_move(find(&b, "b1b2"));
+        assert_eq!("Kkb", b.castling_rights().to_string());
+    }
+
+    #[test]
+    fn chess960_fen() {
+        // Shredder-FEN and X-FEN describe the same rights.
+        let shredder = Board::from_fen("rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1");
+        let x_fen = Board::try_from_fen_chess960("rk2r3/8/8/8/8/8/8/RK2R3 w KQkq - 0 1").unwrap();
+        assert_eq!(shredder, x_fen);
+        assert_eq!("EAea", shredder.castling_rights().to_shredder_string());
+        assert_eq!("rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1", shredder.to_fen());
+
+        // An inner rook has to be named by its file in X-FEN.
+        let fen: &str = "1k1r3r/8/8/8/8/8/8/1K1R3R w Dd - 0 1";
+        let b = Board::from_fen(fen);
+        assert_eq!(
+            Some(File(3)),
+            b.castling_rights()
+                .rook_file(Color::White, CastlingSide::Kingside)
+        );
+        assert_eq!(fen, b.to_fen());
+        assert_eq!(
+            Some(File(7)),
+            Board::try_from_fen_chess960("1k1r3r/8/8/8/8/8/8/1K1R3R w K - 0 1")
+                .unwrap()
+                .castling_rights()
+                .rook_file(Color::White, CastlingSide::Kingside)
+        );
+
+        assert!(BoardBuilder::try_from_fen_strict("1k1r3r/8/8/8/8/8/8/1K1R3R w DA - 0 1").is_err());
+        assert!(BoardBuilder::try_from_fen_strict("1k1r3r/8/8/8/8/8/8/1K1R3R w HK - 0 1").is_err());
+        assert!(BoardBuilder::try_from_fen("1k1r3r/8/8/8/8/8/8/1K1R3R w I - 0 1").is_err());
+
+        // Outside of Chess960 `KQkq` need the king on the e-file.
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/R5K1 w Q - 0 1");
+        assert_eq!(NO_CASTLING_RIGHTS, b.castling_rights());
+        assert!(b.legal_moves().iter().all(|m| !m.is_castle()));
+        assert!(Board::try_from_fen_strict("4k3/8/8/8/8/8/8/R5K1 w Q - 0 1").is_err());
+        let b = Board::try_from_fen_chess960("4k3/8/8/8/8/8/8/R5K1 w Q - 0 1").unwrap();
+        assert_eq!(MoveFlag::QueenCastle, find(&b, "g1c1").flag());
+    }
+
+    #[test]
+    fn to_fen() {
+        let fens: [&str; 4] = [
+            DEFAULT_BOARD_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+
+        for fen in fens {
+            assert_eq!(fen, Board::from_fen(fen).to_fen());
+        }
+
+        let mut b = Board::default();
+        b.make_move(find(&b, "e2e4"));
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            b.to_fen()
+        );
+    }
+
+    #[test]
+    fn fen_round_trip_random_games() {
+        // A small xorshift generator keeps the games reproducible without extra dependencies.
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let starts: [&str; 4] = [
+            DEFAULT_BOARD_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "1r4kr/pppppppp/8/8/8/8/PPPPPPPP/1R4KR w HBhb - 0 1",
+        ];
+
+        for _ in 0..100 {
+            let mut b = Board::from_fen(starts[(next() % 4) as usize]);
+            for _ in 0..120 {
+                let moves = b.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                b.make_move(moves[(next() % moves.len() as u64) as usize]);
+
+                let fen: String = b.to_fen();
+                assert_eq!(b, Board::from_fen(&fen), "{}", fen);
+                assert_eq!(fen, Board::from_fen(&fen).to_fen());
+            }
+        }
+    }
+
+    #[test]
+    fn eq_ignores_history() {
+        let mut b = Board::default();
+        let m = find(&b, "g1f3");
+        b.make_move(m);
+        assert_ne!(Board::default(), b);
+
+        b.unmake_move(m);
+        assert_eq!(Board::default(), b);
+    }
+
+    fn fen_error(result: Result<Board>) -> (FenField, usize, FenErrorKind) {
+        match *result.unwrap_err().downcast::<ChessifyError>().unwrap() {
+            ChessifyError::InvalidFenField {
+                field,
+                offset,
+                kind,
+            } => (field, offset, kind),
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn fen_syntax_errors() {
+        assert_eq!(
+            (FenField::PiecePlacement, 8, FenErrorKind::RankTooLong),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            ))
+        );
+        assert_eq!(
+            (FenField::PiecePlacement, 11, FenErrorKind::RankTooLong),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnr/p7p/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            ))
+        );
+        assert_eq!(
+            (FenField::PiecePlacement, 7, FenErrorKind::RankTooShort),
+            fen_error(Board::try_from_fen(
+                "rnbqkbn/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            ))
+        );
+        assert_eq!(
+            (FenField::PiecePlacement, 43, FenErrorKind::RankCount(9)),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w KQkq - 0 1"
+            ))
+        );
+        assert_eq!(
+            (
+                FenField::PiecePlacement,
+                9,
+                FenErrorKind::UnexpectedCharacter('x')
+            ),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnr/xppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            ))
+        );
+        assert_eq!(
+            (
+                FenField::FullmoveNumber,
+                54,
+                FenErrorKind::FieldCount {
+                    found: 5,
+                    expected: &[4, 6]
+                }
+            ),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"
+            ))
+        );
+        assert_eq!(
+            (
+                FenField::CastlingRights,
+                48,
+                FenErrorKind::UnexpectedCharacter('x')
+            ),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1"
+            ))
+        );
+        assert_eq!(
+            (FenField::HalfmoveClock, 53, FenErrorKind::InvalidNumber),
+            fen_error(Board::try_from_fen(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - a 1"
+            ))
+        );
+
+        // Four fields are fine and default the clocks.
+        let b = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+        assert_eq!((0, 1), (b.halfmove_clock(), b.fullmove_number()));
+    }
+
+    #[test]
+    fn fen_strict_errors() {
+        let cases: [(&str, (FenField, usize, FenErrorKind)); 9] = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+                (
+                    FenField::HalfmoveClock,
+                    52,
+                    FenErrorKind::FieldCount {
+                        found: 4,
+                        expected: &[6],
+                    },
+                ),
+            ),
+            (
+                "rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1",
+                (
+                    FenField::PiecePlacement,
+                    0,
+                    FenErrorKind::KingCount(Color::Black, 0),
+                ),
+            ),
+            (
+                "rnbqkbnP/pppppppp/8/8/8/8/PPPPPPP1/RNBQKBNR w KQq - 0 1",
+                (FenField::PiecePlacement, 7, FenErrorKind::PawnOnBackRank),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1",
+                (FenField::ActiveColor, 22, FenErrorKind::OpponentInCheck),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 W - - 0 1",
+                (
+                    FenField::ActiveColor,
+                    20,
+                    FenErrorKind::UnexpectedCharacter('W'),
+                ),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K2R w KK - 0 1",
+                (
+                    FenField::CastlingRights,
+                    24,
+                    FenErrorKind::DuplicateCastlingRight('K'),
+                ),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K2R w Q - 0 1",
+                (
+                    FenField::CastlingRights,
+                    23,
+                    FenErrorKind::ImpossibleCastlingRight('Q'),
+                ),
+            ),
+            (
+                "4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1",
+                (
+                    FenField::EnPassantSquare,
+                    26,
+                    FenErrorKind::ImpossibleEnPassantSquare,
+                ),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+                (FenField::FullmoveNumber, 28, FenErrorKind::InvalidNumber),
+            ),
+        ];
+
+        for (fen, expected) in cases {
+            assert!(Board::try_from_fen(fen).is_ok(), "{}", fen);
+            assert_eq!(
+                expected,
+                fen_error(Board::try_from_fen_strict(fen)),
+                "{}",
+                fen
+            );
+        }
+
+        assert!(Board::try_from_fen_strict(DEFAULT_BOARD_FEN).is_ok());
+        assert!(Board::try_from_fen_strict("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+    }
+
+    #[test]
+    fn fen_lenient_repairs() {
+        let (b, warnings) =
+            Board::try_from_fen_lenient("4k2r/8/8/8/4P3/8/8/4K3 w KQk e3 0").unwrap();
+
+        assert_eq!("4k2r/8/8/8/4P3/8/8/4K3 w k - 0 1", b.to_fen());
+        assert_eq!(
+            vec![
+                FenErrorKind::FieldCount {
+                    found: 5,
+                    expected: &[4, 5, 6],
+                },
+                FenErrorKind::ImpossibleCastlingRight('K'),
+                FenErrorKind::ImpossibleCastlingRight('Q'),
+                FenErrorKind::ImpossibleEnPassantSquare,
+            ],
+            warnings
+                .iter()
+                .map(|w| w.kind)
+                .collect::<Vec<FenErrorKind>>()
+        );
+        assert_eq!(
+            FenWarning {
+                field: FenField::CastlingRights,
+                offset: 25,
+                kind: FenErrorKind::ImpossibleCastlingRight('K'),
+            },
+            warnings[1]
+        );
+
+        let (_, warnings) = Board::try_from_fen_lenient(DEFAULT_BOARD_FEN).unwrap();
+        assert!(warnings.is_empty());
+
+        // Missing kings can not be repaired.
+        assert_eq!(
+            (
+                FenField::PiecePlacement,
+                0,
+                FenErrorKind::KingCount(Color::White, 0)
+            ),
+            fen_error(Board::try_from_fen_lenient("4k3/8/8/8/8/8/8/8 w - - 0 1").map(|(b, _)| b))
+        );
+    }
+
+    #[test]
+    fn zobrist_key_matches_polyglot() {
+        // Test vectors from the Polyglot book format specification.
+        let lines: [(&str, u64); 9] = [
+            ("", 0x463B_9618_1691_FC9C),
+            ("e2e4", 0x823C_9B50_FD11_4196),
+            ("e2e4 d7d5", 0x0756_B944_61C5_0FB0),
+            ("e2e4 d7d5 e4e5", 0x662F_AFB9_65DB_29D4),
+            ("e2e4 d7d5 e4e5 f7f5", 0x22A4_8B5A_8E47_FF78),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652A_607C_A3F2_42C1),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00FD_D303_C946_BDD9),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3C81_23EA_7B06_7637),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5C3F_9B82_9B27_9560),
+        ];
+
+        for (moves, key) in lines {
+            let mut b = Board::default();
+            for uci in moves.split_whitespace() {
+                b.make_move(find(&b, uci));
+            }
+            assert_eq!(key, b.zobrist_key(), "{}", moves);
+            assert_eq!(key, Board::from_fen(&b.to_fen()).zobrist_key(), "{}", moves);
+        }
+    }
+
+    #[test]
+    fn zobrist_key_incremental() {
+        let mut b =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let root: u64 = b.zobrist_key();
+
+        for m in b.legal_moves() {
+            b.make_move(m);
+            assert_eq!(b.compute_zobrist_key(), b.zobrist_key(), "{}", m);
+            for reply in b.legal_moves() {
+                b.make_move(reply);
+                assert_eq!(b.compute_zobrist_key(), b.zobrist_key(), "{} {}", m, reply);
+                b.unmake_move(reply);
+            }
+            b.unmake_move(m);
+        }
+        assert_eq!(root, b.zobrist_key());
+    }
+
+    #[test]
+    #[should_panic]
+    fn unmake_without_make() {
+        let mut b = Board::default();
+        b.unmake_move(Move::new(
+            Square::from_str("e2"),
+            Square::from_str("e4"),
+            MoveFlag::DoublePawnPush,
+        ));
     }
 }
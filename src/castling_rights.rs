@@ -1,5 +1,8 @@
 use crate::color::Color;
 use crate::error::{ChessifyError, Result};
+use crate::square::{File, Square};
+
+use std::fmt;
 
 /// Exhaustive enum of the castling availability status for a color.
 #[repr(u8)]
@@ -12,16 +15,23 @@ pub enum CastlingStatus {
 }
 
 impl CastlingStatus {
-    ///
+    /// Get the [`usize`] index of the castling status.
     pub fn to_index(&self) -> usize {
         *self as usize
     }
 
+    /// Create a new [`CastlingStatus`] from an unsigned char ([`u8`]).
+    ///
+    /// # Panics
+    /// If the value is larger than 3.
     pub fn from_u8(b: u8) -> Self {
         CastlingStatus::try_from_u8(b).unwrap()
     }
 
+    /// Try and create a new [`CastlingStatus`] from an unsigned char ([`u8`]).
     ///
+    /// # Errors
+    /// Returns a [`ChessifyError::UnknownCastlingRights`] error if the value is larger than 3.
     pub fn try_from_u8(b: u8) -> Result<Self> {
         match b {
             0 => Ok(CastlingStatus::NotAvailable),
@@ -33,39 +43,183 @@ impl CastlingStatus {
     }
 }
 
+/// Exhaustive enum of the two sides of the board a king can castle to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CastlingSide {
+    /// Towards the h-file, the king ends up on g1 or g8.
+    Kingside,
+    /// Towards the a-file, the king ends up on c1 or c8.
+    Queenside,
+}
+
+/// Castling rights of both colors.
 ///
+/// The lowest four bits are a mask of the available rights (`KQkq` from high to low bit). For
+/// Chess960 the origin file of each castling rook is stored in three more bits per right, counted
+/// from the corner of the board, so that rooks on the a- and h-files leave them zero.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, PartialOrd)]
-pub struct CastlingRights(pub u8);
+pub struct CastlingRights(pub u16);
 
-pub const NO_CASTLING_RIGHTS: CastlingRights = CastlingRights(0u8);
-pub const FULL_CASTLING_RIGHTS: CastlingRights = CastlingRights(15u8);
+pub const NO_CASTLING_RIGHTS: CastlingRights = CastlingRights(0u16);
+pub const FULL_CASTLING_RIGHTS: CastlingRights = CastlingRights(15u16);
+
+/// The file of the corner rook on `side` in standard chess.
+pub(crate) fn corner_file(side: CastlingSide) -> File {
+    match side {
+        CastlingSide::Kingside => File(7),
+        CastlingSide::Queenside => File(0),
+    }
+}
+
+/// The bit of the mask that stands for the right of color `c` to castle to `side`.
+fn right_bit(c: Color, side: CastlingSide) -> u16 {
+    match (c, side) {
+        (Color::White, CastlingSide::Kingside) => 3,
+        (Color::White, CastlingSide::Queenside) => 2,
+        (Color::Black, CastlingSide::Kingside) => 1,
+        (Color::Black, CastlingSide::Queenside) => 0,
+    }
+}
 
 impl CastlingRights {
+    /// Create new [`CastlingRights`] from the castling field of a FEN string.
     ///
+    /// # Panics
+    /// If the string contains anything but the characters ('K', 'Q', 'k', 'q').
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         CastlingRights::try_from(s).unwrap()
     }
 
+    /// Check whether color `c` may still castle to `side`.
+    pub fn has(&self, c: Color, side: CastlingSide) -> bool {
+        self.0 & (1 << right_bit(c, side)) != 0
+    }
+
+    /// Get the file the castling rook of color `c` starts on, if it may still castle to `side`.
+    pub fn rook_file(&self, c: Color, side: CastlingSide) -> Option<File> {
+        if !self.has(c, side) {
+            return None;
+        }
+        let bit: u16 = right_bit(c, side);
+        let offset: u8 = ((self.0 >> (4 + 3 * bit)) & 7) as u8;
+        Some(match side {
+            CastlingSide::Kingside => File(7 - offset),
+            CastlingSide::Queenside => File(offset),
+        })
+    }
+
+    /// Grant color `c` the right to castle to `side` with the rook starting on `rook_file`.
+    pub fn insert(&mut self, c: Color, side: CastlingSide, rook_file: File) {
+        self.remove(c, side);
+        let bit: u16 = right_bit(c, side);
+        let offset: u16 = match side {
+            CastlingSide::Kingside => 7 - rook_file.0 as u16,
+            CastlingSide::Queenside => rook_file.0 as u16,
+        };
+        self.0 |= (1 << bit) | (offset << (4 + 3 * bit));
+    }
+
+    /// Take away the right of color `c` to castle to `side`.
+    pub fn remove(&mut self, c: Color, side: CastlingSide) {
+        let bit: u16 = right_bit(c, side);
+        self.0 &= !((1 << bit) | (7 << (4 + 3 * bit)));
+    }
+
+    /// Take away both castling rights of color `c`, e.g. after its king moved.
+    pub fn remove_color(&mut self, c: Color) {
+        self.remove(c, CastlingSide::Kingside);
+        self.remove(c, CastlingSide::Queenside);
+    }
+
+    /// Take away every castling right whose rook starts on `s`, e.g. after it moved or was captured.
+    pub fn remove_rook(&mut self, s: Square) {
+        let c: Color = match s.0 / 8 {
+            0 => Color::Black,
+            7 => Color::White,
+            _ => return,
+        };
+        for side in [CastlingSide::Kingside, CastlingSide::Queenside] {
+            if self.rook_file(c, side) == Some(s.file()) {
+                self.remove(c, side);
+            }
+        }
+    }
+
+    /// Check whether every castling rook starts in a corner of the board as in standard chess.
+    pub fn is_standard(&self) -> bool {
+        self.0 >> 4 == 0
+    }
+
+    /// Format the rights as the castling field of a Shredder-FEN string, e.g. `HAha`.
     ///
+    /// Every right is written as the file letter of its rook, upper case for white.
+    pub fn to_shredder_string(&self) -> String {
+        self.letters(|_, _, file| Some(file))
+    }
+
+    /// Write one character per right, calling `file_letter` to decide whether a right is written
+    /// as the file of its rook instead of `K`, `Q`, `k` or `q`. No rights at all are written as `-`.
+    pub(crate) fn letters<F>(&self, file_letter: F) -> String
+    where
+        F: Fn(Color, CastlingSide, File) -> Option<File>,
+    {
+        if self.0 & 15 == 0 {
+            return "-".to_string();
+        }
+
+        let mut s: String = String::with_capacity(4);
+        for (c, side, letter) in [
+            (Color::White, CastlingSide::Kingside, 'K'),
+            (Color::White, CastlingSide::Queenside, 'Q'),
+            (Color::Black, CastlingSide::Kingside, 'k'),
+            (Color::Black, CastlingSide::Queenside, 'q'),
+        ] {
+            if let Some(file) = self.rook_file(c, side) {
+                match (file_letter(c, side, file), c) {
+                    (None, _) => s.push(letter),
+                    (Some(f), Color::White) => s.push_str(&f.to_string().to_uppercase()),
+                    (Some(f), Color::Black) => s.push_str(&f.to_string()),
+                }
+            }
+        }
+        s
+    }
+
+    /// Get the [`CastlingStatus`] for a specific color.
     pub fn for_color(&self, c: Color) -> CastlingStatus {
         match c {
-            Color::White => CastlingStatus::from_u8((self.0 & 12) >> 2),
-            Color::Black => CastlingStatus::from_u8(self.0 & 3),
+            Color::White => CastlingStatus::from_u8(((self.0 & 12) >> 2) as u8),
+            Color::Black => CastlingStatus::from_u8((self.0 & 3) as u8),
         }
     }
 }
 
+impl fmt::Display for CastlingRights {
+    /// Formats the rights as `KQkq`, falling back to the file letter of the rook for every right
+    /// whose rook does not start in a corner of the board.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s: String = self.letters(|_, side, file| (file != corner_file(side)).then_some(file));
+        write!(f, "{}", s)
+    }
+}
+
 impl TryFrom<&str> for CastlingRights {
     type Error = ChessifyError;
 
     fn try_from(s: &str) -> std::result::Result<Self, Self::Error> {
-        let mut b: u8 = 0;
+        // A single dash is how FEN marks that neither side may castle.
+        if s == "-" {
+            return Ok(NO_CASTLING_RIGHTS);
+        }
+
+        let mut b: u16 = 0;
         for c in s.chars() {
             match c {
-                'K' => b |= 1u8 << 3,
-                'Q' => b |= 1u8 << 2,
-                'k' => b |= 1u8 << 1,
-                'q' => b |= 1u8 << 0,
+                'K' => b |= 1u16 << 3,
+                'Q' => b |= 1u16 << 2,
+                'k' => b |= 1u16 << 1,
+                'q' => b |= 1u16 << 0,
                 _ => return Err(ChessifyError::InvalidFen(s.to_string())),
             }
         };
@@ -98,6 +252,7 @@ mod tests {
     #[test]
     fn try_from_str_ok() {
         assert_eq!(CastlingRights(9), CastlingRights::try_from("Kq").unwrap());
+        assert_eq!(NO_CASTLING_RIGHTS, CastlingRights::try_from("-").unwrap());
     }
 
     #[test]
@@ -106,6 +261,14 @@ mod tests {
         CastlingRights::try_from("KQb").unwrap();
     }
 
+    #[test]
+    fn display() {
+        assert_eq!("KQkq", FULL_CASTLING_RIGHTS.to_string());
+        assert_eq!("-", NO_CASTLING_RIGHTS.to_string());
+        assert_eq!("Kq", CastlingRights::from_str("qK").to_string());
+        assert_eq!("Qk", CastlingRights(6).to_string());
+    }
+
     #[test]
     fn for_color() {
         let cr1 = CastlingRights::from_str("KQkq");
@@ -129,4 +292,41 @@ mod tests {
         assert_eq!(CastlingStatus::Queenside, wr3);
         assert_eq!(CastlingStatus::Queenside, br3);
     }
+
+    #[test]
+    fn rook_files() {
+        let mut cr = FULL_CASTLING_RIGHTS;
+        assert!(cr.is_standard());
+        assert_eq!(
+            Some(File(7)),
+            cr.rook_file(Color::White, CastlingSide::Kingside)
+        );
+        assert_eq!(
+            Some(File(0)),
+            cr.rook_file(Color::Black, CastlingSide::Queenside)
+        );
+
+        cr.insert(Color::White, CastlingSide::Kingside, File(6));
+        cr.insert(Color::Black, CastlingSide::Queenside, File(1));
+        assert!(!cr.is_standard());
+        assert_eq!(
+            Some(File(6)),
+            cr.rook_file(Color::White, CastlingSide::Kingside)
+        );
+        assert_eq!(
+            Some(File(1)),
+            cr.rook_file(Color::Black, CastlingSide::Queenside)
+        );
+        assert_eq!("GQkb", cr.to_string());
+        assert_eq!("GAhb", cr.to_shredder_string());
+
+        cr.remove_rook(Square::from_str("g1"));
+        cr.remove_rook(Square::from_str("a8"));
+        assert_eq!("Qkb", cr.to_string());
+
+        cr.remove_color(Color::Black);
+        assert_eq!(CastlingRights::from_str("Q"), cr);
+        assert_eq!(None, cr.rook_file(Color::Black, CastlingSide::Kingside));
+        assert_eq!("-", NO_CASTLING_RIGHTS.to_shredder_string());
+    }
 }
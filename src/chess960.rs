@@ -0,0 +1,160 @@
+use crate::board::Board;
+use crate::color::Color;
+use crate::error::{ChessifyError, Result};
+use crate::piece::Piece;
+
+/// The placements of the two knights on the five squares left after bishops and queen.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// The number of possible Chess960 starting positions.
+const NUM_POSITIONS: u16 = 960;
+
+/// Get the back rank pieces of the Chess960 starting position with Scharnagl number `n`.
+fn back_rank(n: u16) -> [Piece; 8] {
+    let mut rank: [Option<Piece>; 8] = [None; 8];
+    let n: usize = n as usize;
+
+    rank[2 * (n % 4) + 1] = Some(Piece::Bishop);
+    rank[2 * (n / 4 % 4)] = Some(Piece::Bishop);
+
+    // The remaining pieces fill the empty squares from left to right.
+    let mut place = |i: usize, p: Piece| {
+        let s: usize = (0..8).filter(|s| rank[*s].is_none()).nth(i).unwrap();
+        rank[s] = Some(p);
+    };
+    place(n / 16 % 6, Piece::Queen);
+    let (first, second) = KNIGHT_PLACEMENTS[n / 96];
+    // Placing the second knight first keeps the index of the first one valid.
+    place(second, Piece::Knight);
+    place(first, Piece::Knight);
+    for p in [Piece::Rook, Piece::King, Piece::Rook] {
+        place(0, p);
+    }
+
+    rank.map(|p| p.unwrap())
+}
+
+impl Board {
+    /// Create a new [`Board`] with the Chess960 starting position of the given Scharnagl number.
+    ///
+    /// # Panics
+    /// If the number is not below 960.
+    pub fn from_scharnagl(n: u16) -> Self {
+        Board::try_from_scharnagl(n).unwrap()
+    }
+
+    /// Try and create a new [`Board`] with the Chess960 starting position of the given
+    /// Scharnagl number.
+    ///
+    /// The numbers go from 0 to 959, 518 being the standard starting position. Castling rights
+    /// are granted for both rooks of each side.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidScharnaglNumber`] error if the number is not below 960.
+    pub fn try_from_scharnagl(n: u16) -> Result<Self> {
+        if n >= NUM_POSITIONS {
+            return Err(Box::new(ChessifyError::InvalidScharnaglNumber(n)));
+        }
+
+        let pieces: String = back_rank(n)
+            .iter()
+            .map(|p| p.to_string(Color::Black))
+            .collect();
+        let rooks: String = (0..8)
+            .filter(|i| back_rank(n)[*i] == Piece::Rook)
+            .rev()
+            .map(|i| (b'a' + i as u8) as char)
+            .collect();
+
+        Board::try_from_fen(&format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+            pieces,
+            pieces.to_uppercase(),
+            rooks.to_uppercase(),
+            rooks
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::castling_rights::CastlingSide;
+    use crate::square::File;
+
+    fn rank_str(n: u16) -> String {
+        back_rank(n)
+            .iter()
+            .map(|p| p.to_string(Color::White))
+            .collect()
+    }
+
+    #[test]
+    fn known_positions() {
+        assert_eq!("BBQNNRKR", rank_str(0));
+        assert_eq!("RNBQKBNR", rank_str(518));
+        assert_eq!("RKRNNQBB", rank_str(959));
+        assert_eq!(Board::default(), Board::from_scharnagl(518));
+    }
+
+    #[test]
+    fn all_positions_are_valid() {
+        let mut ranks: Vec<String> = (0..NUM_POSITIONS).map(rank_str).collect();
+        for (n, rank) in ranks.iter().enumerate() {
+            let bishops: Vec<usize> = rank.match_indices('B').map(|(i, _)| i).collect();
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", rank);
+            let king: usize = rank.find('K').unwrap();
+            assert!(rank.find('R').unwrap() < king && king < rank.rfind('R').unwrap());
+            assert!(Board::try_from_fen_strict(&Board::from_scharnagl(n as u16).to_fen()).is_ok());
+        }
+        ranks.sort();
+        ranks.dedup();
+        assert_eq!(NUM_POSITIONS as usize, ranks.len());
+    }
+
+    #[test]
+    fn castling_rights() {
+        let b = Board::from_scharnagl(0);
+        assert_eq!(
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1",
+            b.to_fen()
+        );
+        assert_eq!(
+            b,
+            Board::try_from_fen_chess960(
+                "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+            )
+            .unwrap()
+        );
+        let rights = b.castling_rights();
+        assert_eq!(
+            Some(File(7)),
+            rights.rook_file(Color::White, CastlingSide::Kingside)
+        );
+        assert_eq!(
+            Some(File(5)),
+            rights.rook_file(Color::Black, CastlingSide::Queenside)
+        );
+        assert_eq!("HFhf", rights.to_shredder_string());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let err = Board::try_from_scharnagl(960).unwrap_err();
+        assert_eq!(
+            ChessifyError::InvalidScharnaglNumber(960),
+            *err.downcast::<ChessifyError>().unwrap()
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use crate::color::Color;
+use crate::error::{ChessifyError, Result};
+use crate::piece::Piece;
+use crate::square::Square;
+
+use std::fmt;
+
+/// Exhaustive enum of the different kinds of moves that can be played.
+///
+/// The discriminants are laid out so that bit 2 marks a capture and bit 3 marks a promotion,
+/// which lets a [`Move`] store its kind in four bits.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd)]
+pub enum MoveFlag {
+    Quiet = 0,
+    DoublePawnPush = 1,
+    KingCastle = 2,
+    QueenCastle = 3,
+    Capture = 4,
+    EnPassant = 5,
+    KnightPromotion = 8,
+    BishopPromotion = 9,
+    RookPromotion = 10,
+    QueenPromotion = 11,
+    KnightPromotionCapture = 12,
+    BishopPromotionCapture = 13,
+    RookPromotionCapture = 14,
+    QueenPromotionCapture = 15,
+}
+
+impl MoveFlag {
+    /// Create a new [`MoveFlag`] from an unsigned char ([`u8`]).
+    ///
+    /// # Panics
+    /// If the value does not correspond to any flag.
+    pub fn from_u8(b: u8) -> Self {
+        MoveFlag::try_from_u8(b).unwrap()
+    }
+
+    /// Try and create a new [`MoveFlag`] from an unsigned char ([`u8`]).
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::UnknownMoveFlag`] error if the value does not correspond to any flag.
+    pub fn try_from_u8(b: u8) -> Result<Self> {
+        match b {
+            0 => Ok(MoveFlag::Quiet),
+            1 => Ok(MoveFlag::DoublePawnPush),
+            2 => Ok(MoveFlag::KingCastle),
+            3 => Ok(MoveFlag::QueenCastle),
+            4 => Ok(MoveFlag::Capture),
+            5 => Ok(MoveFlag::EnPassant),
+            8 => Ok(MoveFlag::KnightPromotion),
+            9 => Ok(MoveFlag::BishopPromotion),
+            10 => Ok(MoveFlag::RookPromotion),
+            11 => Ok(MoveFlag::QueenPromotion),
+            12 => Ok(MoveFlag::KnightPromotionCapture),
+            13 => Ok(MoveFlag::BishopPromotionCapture),
+            14 => Ok(MoveFlag::RookPromotionCapture),
+            15 => Ok(MoveFlag::QueenPromotionCapture),
+            _ => Err(Box::new(ChessifyError::UnknownMoveFlag(b.to_string()))),
+        }
+    }
+
+    /// Get the promotion flag for the given piece, optionally combined with a capture.
+    ///
+    /// # Panics
+    /// If the piece is a pawn or a king since those can not be promoted to.
+    pub fn promotion(piece: Piece, capture: bool) -> Self {
+        let base: u8 = match piece {
+            Piece::Knight => 8,
+            Piece::Bishop => 9,
+            Piece::Rook => 10,
+            Piece::Queen => 11,
+            _ => panic!("can not promote to {:?}", piece),
+        };
+        MoveFlag::from_u8(if capture { base | 4 } else { base })
+    }
+}
+
+/// A compact chess move stored in a single [`u16`].
+///
+/// The lower six bits hold the origin square, the next six bits hold the destination square
+/// and the upper four bits hold the [`MoveFlag`]. For castling moves the destination is the
+/// square the king ends up on.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Move(pub u16);
+
+/// The null move, which does not describe any real move on the board.
+pub const NULL_MOVE: Move = Move(0);
+
+impl Move {
+    /// Create a new [`Move`] from an origin square, a destination square and a flag.
+    pub fn new(from: Square, to: Square, flag: MoveFlag) -> Self {
+        Move((from.0 as u16) | ((to.0 as u16) << 6) | ((flag as u16) << 12))
+    }
+
+    /// Get the square the moving piece starts on.
+    pub fn from(&self) -> Square {
+        Square((self.0 & 63) as u8)
+    }
+
+    /// Get the square the moving piece ends up on.
+    pub fn to(&self) -> Square {
+        Square(((self.0 >> 6) & 63) as u8)
+    }
+
+    /// Get the [`MoveFlag`] describing what kind of move this is.
+    pub fn flag(&self) -> MoveFlag {
+        MoveFlag::from_u8((self.0 >> 12) as u8)
+    }
+
+    /// Get the piece a pawn is promoted to, if this is a promotion.
+    pub fn promotion(&self) -> Option<Piece> {
+        if !self.is_promotion() {
+            return None;
+        }
+
+        match (self.0 >> 12) & 3 {
+            0 => Some(Piece::Knight),
+            1 => Some(Piece::Bishop),
+            2 => Some(Piece::Rook),
+            _ => Some(Piece::Queen),
+        }
+    }
+
+    /// Check whether the move captures a piece, including en passant captures.
+    pub fn is_capture(&self) -> bool {
+        (self.0 >> 12) & 4 != 0
+    }
+
+    /// Check whether the move promotes a pawn.
+    pub fn is_promotion(&self) -> bool {
+        (self.0 >> 12) & 8 != 0
+    }
+
+    /// Check whether the move is an en passant capture.
+    pub fn is_en_passant(&self) -> bool {
+        self.flag() == MoveFlag::EnPassant
+    }
+
+    /// Check whether the move castles either king- or queenside.
+    pub fn is_castle(&self) -> bool {
+        matches!(self.flag(), MoveFlag::KingCastle | MoveFlag::QueenCastle)
+    }
+
+    /// Check whether the move is a pawn advancing two squares from its starting rank.
+    pub fn is_double_pawn_push(&self) -> bool {
+        self.flag() == MoveFlag::DoublePawnPush
+    }
+
+    /// Check whether the move is the [`NULL_MOVE`].
+    pub fn is_null(&self) -> bool {
+        *self == NULL_MOVE
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_null() {
+            return write!(f, "0000");
+        }
+
+        write!(f, "{}{}", self.from(), self.to())?;
+        if let Some(piece) = self.promotion() {
+            write!(f, "{}", piece.to_string(Color::Black))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_and_accessors() {
+        let e2 = Square::from_str("e2");
+        let e4 = Square::from_str("e4");
+        let m = Move::new(e2, e4, MoveFlag::DoublePawnPush);
+
+        assert_eq!(e2, m.from());
+        assert_eq!(e4, m.to());
+        assert_eq!(MoveFlag::DoublePawnPush, m.flag());
+        assert!(m.is_double_pawn_push());
+        assert!(!m.is_capture());
+        assert!(!m.is_promotion());
+        assert_eq!(None, m.promotion());
+    }
+
+    #[test]
+    fn promotions() {
+        let b7 = Square::from_str("b7");
+        let a8 = Square::from_str("a8");
+
+        let m1 = Move::new(b7, a8, MoveFlag::promotion(Piece::Knight, true));
+        assert_eq!(MoveFlag::KnightPromotionCapture, m1.flag());
+        assert_eq!(Some(Piece::Knight), m1.promotion());
+        assert!(m1.is_capture());
+        assert!(m1.is_promotion());
+
+        let m2 = Move::new(
+            b7,
+            Square::from_str("b8"),
+            MoveFlag::promotion(Piece::Queen, false),
+        );
+        assert_eq!(MoveFlag::QueenPromotion, m2.flag());
+        assert_eq!(Some(Piece::Queen), m2.promotion());
+        assert!(!m2.is_capture());
+    }
+
+    #[test]
+    #[should_panic]
+    fn promotion_to_king() {
+        MoveFlag::promotion(Piece::King, false);
+    }
+
+    #[test]
+    fn special_moves() {
+        let castle = Move::new(
+            Square::from_str("e1"),
+            Square::from_str("g1"),
+            MoveFlag::KingCastle,
+        );
+        assert!(castle.is_castle());
+        assert!(!castle.is_capture());
+
+        let ep = Move::new(
+            Square::from_str("e5"),
+            Square::from_str("d6"),
+            MoveFlag::EnPassant,
+        );
+        assert!(ep.is_en_passant());
+        assert!(ep.is_capture());
+    }
+
+    #[test]
+    fn flag_from_u8() {
+        assert_eq!(MoveFlag::Capture, MoveFlag::from_u8(4));
+        assert!(MoveFlag::try_from_u8(6).is_err());
+    }
+
+    #[test]
+    fn display() {
+        let m1 = Move::new(
+            Square::from_str("g1"),
+            Square::from_str("f3"),
+            MoveFlag::Quiet,
+        );
+        let m2 = Move::new(
+            Square::from_str("a7"),
+            Square::from_str("a8"),
+            MoveFlag::QueenPromotion,
+        );
+
+        assert_eq!("g1f3", m1.to_string());
+        assert_eq!("a7a8q", m2.to_string());
+        assert_eq!("0000", NULL_MOVE.to_string());
+    }
+}
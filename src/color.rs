@@ -1,6 +1,7 @@
 use crate::error::{ChessifyError, Result};
 
 use std::fmt;
+use std::ops;
 
 /// Exhaustive enum of the available colors in chess.
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd)]
@@ -27,6 +28,7 @@ impl Color {
     /// # Panics
     /// If either the string is empty or the string does not contain any of the characters
     /// ('w', 'W', 'b', 'B') as the first character in the string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Color {
         Color::try_from_str(s).unwrap()
     }
@@ -48,6 +50,17 @@ impl Color {
     }
 }
 
+impl ops::Not for Color {
+    type Output = Color;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -73,6 +86,12 @@ mod tests {
         assert_eq!(Color::Black, COLORS[Color::Black.as_index()]);
     }
 
+    #[test]
+    fn op_not() {
+        assert_eq!(Color::Black, !Color::White);
+        assert_eq!(Color::White, !Color::Black);
+    }
+
     #[test]
     fn try_from_str_ok() {
         assert_eq!(Color::White, Color::try_from_str("W").unwrap());
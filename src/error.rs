@@ -1,9 +1,16 @@
+use crate::fen::{FenErrorKind, FenField};
+#[cfg(feature = "nnue")]
+use crate::nnue::NetworkErrorKind;
+use crate::pgn::PgnErrorKind;
+use crate::tuner::TuningErrorKind;
+use crate::uci_engine::EngineErrorKind;
+
 use thiserror::Error;
 
 use std::error;
 use std::result;
 
-///
+/// Result type used throughout chessify, where any error is boxed.
 pub type Result<T> = result::Result<T, Box<dyn error::Error>>;
 
 #[derive(Error, Debug, Eq, PartialEq)]
@@ -14,12 +21,57 @@ pub enum ChessifyError {
     #[error("`{0}` is an invalid FEN string")]
     InvalidFen(String),
 
+    #[error("invalid FEN {field} at offset {offset}: {kind}")]
+    InvalidFenField {
+        field: FenField,
+        offset: usize,
+        kind: FenErrorKind,
+    },
+
+    #[error("`{0}` is not a legal move in this position")]
+    IllegalMove(String),
+
+    #[error("`{0}` matches more than one legal move")]
+    AmbiguousMove(String),
+
+    #[error("`{0}` is not a valid move in standard algebraic notation")]
+    InvalidSan(String),
+
+    #[error("`{0}` is not a valid move in UCI notation")]
+    InvalidUciMove(String),
+
+    #[error("invalid PGN at line {line}, column {column}: {kind}")]
+    InvalidPgn {
+        line: usize,
+        column: usize,
+        kind: PgnErrorKind,
+    },
+
+    #[error("invalid tuning data at line {line}: {kind}")]
+    InvalidTuningData { line: usize, kind: TuningErrorKind },
+
+    #[cfg(feature = "nnue")]
+    #[error("invalid NNUE network: {0}")]
+    InvalidNetwork(NetworkErrorKind),
+
+    #[error("a Polyglot book of {0} bytes does not consist of whole 16 byte entries")]
+    InvalidBookSize(usize),
+
+    #[error("UCI engine error: {0}")]
+    Engine(EngineErrorKind),
+
+    #[error("`{0}` is not a Scharnagl number, which range from 0 to 959")]
+    InvalidScharnaglNumber(u16),
+
     #[error("could not parse `{0}` as `{0}`")]
     ParsingError(String, String),
 
     #[error("could not parse `{0}` as castling rights")]
     UnknownCastlingRights(String),
 
+    #[error("could not parse `{0}` as a move flag")]
+    UnknownMoveFlag(String),
+
     #[error("could not parse `{0}` as a color")]
     UnknownColor(String),
 
@@ -0,0 +1,1002 @@
+use crate::attacks::{bishop_attacks, king_attacks, knight_attacks, queen_attacks, rook_attacks};
+use crate::bitboard::{Bitboard, DARK_SQUARES, FILES, LIGHT_SQUARES};
+use crate::board::Board;
+use crate::color::{Color, COLORS, NUM_COLORS};
+use crate::piece::{Piece, NUM_PIECES, PIECES};
+use crate::search::Evaluator;
+use crate::square::Square;
+
+use std::fmt;
+use std::ops;
+
+/// The game phase with every piece still on the board, falling to zero as pieces are traded.
+pub const MAX_PHASE: i32 = 24;
+
+/// How much every piece counts towards the game phase, indexed by [`Piece::as_index()`].
+const PHASE_WEIGHTS: [i32; NUM_PIECES] = [0, 1, 1, 2, 4, 0];
+
+/// A pair of middlegame and endgame scores in centipawns, which are interpolated by the game
+/// phase.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Score = Score::new(0, 0);
+
+    /// Create a new [`Score`] from a middlegame and an endgame score.
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Score { mg, eg }
+    }
+
+    /// Interpolate between the middlegame score at [`MAX_PHASE`] and the endgame score at
+    /// phase zero.
+    pub fn taper(&self, phase: i32) -> i32 {
+        let phase: i32 = phase.clamp(0, MAX_PHASE);
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl ops::Add for Score {
+    type Output = Score;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Score::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl ops::AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl ops::Sub for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Score::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+impl ops::SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl ops::Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Self::Output {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl ops::Mul<i32> for Score {
+    type Output = Score;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Score::new(self.mg * rhs, self.eg * rhs)
+    }
+}
+
+/// Exhaustive enum of the terms an evaluation is made of.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EvalTerm {
+    Material,
+    PieceSquares,
+    Mobility,
+    PawnStructure,
+    KingSafety,
+    BishopPair,
+}
+
+/// The number of different evaluation terms.
+pub const NUM_EVAL_TERMS: usize = 6;
+
+/// An [`array`] containing the evaluation terms with placement corresponding to their
+/// respective index.
+pub const EVAL_TERMS: [EvalTerm; NUM_EVAL_TERMS] = [
+    EvalTerm::Material,
+    EvalTerm::PieceSquares,
+    EvalTerm::Mobility,
+    EvalTerm::PawnStructure,
+    EvalTerm::KingSafety,
+    EvalTerm::BishopPair,
+];
+
+impl EvalTerm {
+    /// Get the [`usize`] index of the term.
+    pub fn as_index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for EvalTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s: &str = match self {
+            EvalTerm::Material => "Material",
+            EvalTerm::PieceSquares => "Piece squares",
+            EvalTerm::Mobility => "Mobility",
+            EvalTerm::PawnStructure => "Pawn structure",
+            EvalTerm::KingSafety => "King safety",
+            EvalTerm::BishopPair => "Bishop pair",
+        };
+        f.pad(s)
+    }
+}
+
+/// The weights of a handcrafted evaluation, every one of which is a tapered [`Score`].
+///
+/// Arrays indexed by piece use [`Piece::as_index()`] and ignore the entries of pieces the
+/// term does not apply to. Bonuses are positive and penalties negative.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct EvalParams {
+    /// The value of every piece, kings excluded.
+    pub material: [Score; NUM_PIECES],
+    /// A bonus for every piece on every square, from the point of view of white and indexed
+    /// from a8 to h1. The squares are mirrored for black.
+    pub psqt: [[Score; 64]; NUM_PIECES],
+    /// A bonus for every square a knight, bishop, rook or queen attacks that is neither
+    /// occupied by a piece of its own nor attacked by an enemy pawn.
+    pub mobility: [Score; NUM_PIECES],
+    /// A penalty for every pawn on a file beyond the first.
+    pub doubled_pawn: Score,
+    /// A penalty for every pawn without pawns of its own on the adjacent files.
+    pub isolated_pawn: Score,
+    /// A penalty for every pawn that no pawn of its own can defend anymore and that cannot
+    /// advance without being captured by an enemy pawn.
+    pub backward_pawn: Score,
+    /// A bonus for every pawn no enemy pawn can stop, indexed by its rank as seen by its own
+    /// side, the first rank being 0.
+    pub passed_pawn: [Score; 8],
+    /// A bonus for every pawn of its own up to two ranks in front of the king.
+    pub pawn_shield: Score,
+    /// A penalty for every square next to the king, or of the king itself, that an enemy
+    /// knight, bishop, rook or queen attacks.
+    pub king_attack: [Score; NUM_PIECES],
+    /// A bonus for having bishops on both light and dark squares.
+    pub bishop_pair: Score,
+}
+
+/// Zip separate middlegame and endgame tables.
+const fn table(mg: [i32; 64], eg: [i32; 64]) -> [Score; 64] {
+    let mut t: [Score; 64] = [Score::ZERO; 64];
+    let mut i: usize = 0;
+    while i < 64 {
+        t[i] = Score::new(mg[i], eg[i]);
+        i += 1;
+    }
+    t
+}
+
+#[rustfmt::skip]
+const PAWN_SQUARES: [Score; 64] = table(
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         98, 134,  61,  95,  68, 126,  34, -11,
+         -6,   7,  26,  31,  65,  56,  25, -20,
+        -14,  13,   6,  21,  23,  12,  17, -23,
+        -27,  -2,  -5,  12,  17,   6,  10, -25,
+        -26,  -4,  -4, -10,   3,   3,  33, -12,
+        -35,  -1, -20, -23, -15,  24,  38, -22,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         98,  93,  78,  54,  67,  52,  85, 107,
+         54,  60,  45,  27,  16,  13,  42,  44,
+         22,  14,   3,  -5, -12,  -6,   7,   7,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+);
+
+#[rustfmt::skip]
+const KNIGHT_SQUARES: [Score; 64] = table(
+    [
+       -167, -89, -34, -49,  61, -97, -15,-107,
+        -73, -41,  72,  36,  23,  62,   7, -17,
+        -47,  60,  37,  65,  84, 129,  73,  44,
+         -9,  17,  19,  53,  37,  69,  18,  22,
+        -13,   4,  16,  13,  28,  19,  21,  -8,
+        -23,  -9,  12,  10,  19,  17,  25, -16,
+        -29, -53, -12,  -3,  -1,  18, -14, -19,
+       -105, -21, -58, -33, -17, -28, -19, -23,
+    ],
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+);
+
+#[rustfmt::skip]
+const BISHOP_SQUARES: [Score; 64] = table(
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    [
+        -14, -21, -11,  -8,  -7,  -9, -17, -24,
+         -8,  -4,   7, -12,  -3, -13,  -4, -14,
+          2,  -8,   0,  -1,  -2,   6,   0,   4,
+         -3,   9,  12,   9,  14,  10,   3,   2,
+         -6,   3,  13,  19,   7,  10,  -3,  -9,
+        -12,  -3,   8,  10,  13,   3,  -7, -15,
+        -14, -18,  -7,  -1,   4,  -9, -15, -27,
+        -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+    ],
+);
+
+#[rustfmt::skip]
+const ROOK_SQUARES: [Score; 64] = table(
+    [
+         32,  42,  32,  51,  63,   9,  31,  43,
+         27,  32,  58,  62,  80,  67,  26,  44,
+         -5,  19,  26,  36,  17,  45,  61,  16,
+        -24, -11,   7,  26,  24,  35,  -8, -20,
+        -36, -26, -12,  -1,   9,  -7,   6, -23,
+        -45, -25, -16, -17,   3,   0,  -5, -33,
+        -44, -16, -20,  -9,  -1,  11,  -6, -71,
+        -19, -13,   1,  17,  16,   7, -37, -26,
+    ],
+    [
+         13,  10,  18,  15,  12,  12,   8,   5,
+         11,  13,  13,  11,  -3,   3,   8,   3,
+          7,   7,   7,   5,   4,  -3,  -5,  -3,
+          4,   3,  13,   1,   2,   1,  -1,   2,
+          3,   5,   8,   4,  -5,  -6,  -8, -11,
+         -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+         -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+         -9,   2,   3,  -1,  -5, -13,   4, -20,
+    ],
+);
+
+#[rustfmt::skip]
+const QUEEN_SQUARES: [Score; 64] = table(
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+);
+
+#[rustfmt::skip]
+const KING_SQUARES: [Score; 64] = table(
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+);
+
+/// The material and piece-square tables of PeSTO, with what passed pawns are worth moved
+/// out of the pawn table, and hand-picked weights for the other terms.
+pub const DEFAULT_EVAL_PARAMS: EvalParams = EvalParams {
+    material: [
+        Score::new(82, 94),
+        Score::new(337, 281),
+        Score::new(365, 297),
+        Score::new(477, 512),
+        Score::new(1025, 936),
+        Score::ZERO,
+    ],
+    psqt: [
+        PAWN_SQUARES,
+        KNIGHT_SQUARES,
+        BISHOP_SQUARES,
+        ROOK_SQUARES,
+        QUEEN_SQUARES,
+        KING_SQUARES,
+    ],
+    mobility: [
+        Score::ZERO,
+        Score::new(4, 4),
+        Score::new(5, 5),
+        Score::new(2, 4),
+        Score::new(1, 2),
+        Score::ZERO,
+    ],
+    doubled_pawn: Score::new(-10, -20),
+    isolated_pawn: Score::new(-10, -10),
+    backward_pawn: Score::new(-8, -8),
+    passed_pawn: [
+        Score::ZERO,
+        Score::new(0, 5),
+        Score::new(0, 5),
+        Score::new(5, 15),
+        Score::new(15, 30),
+        Score::new(30, 60),
+        Score::new(50, 90),
+        Score::ZERO,
+    ],
+    pawn_shield: Score::new(12, 0),
+    king_attack: [
+        Score::ZERO,
+        Score::new(-7, -1),
+        Score::new(-5, -1),
+        Score::new(-8, -2),
+        Score::new(-10, -3),
+        Score::ZERO,
+    ],
+    bishop_pair: Score::new(30, 50),
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_EVAL_PARAMS
+    }
+}
+
+/// The index of the first weight of every kind in [`EvalParams::weights()`].
+const MATERIAL: usize = 0;
+const PSQT: usize = MATERIAL + NUM_PIECES;
+const MOBILITY: usize = PSQT + 64 * NUM_PIECES;
+const DOUBLED_PAWN: usize = MOBILITY + NUM_PIECES;
+const ISOLATED_PAWN: usize = DOUBLED_PAWN + 1;
+const BACKWARD_PAWN: usize = ISOLATED_PAWN + 1;
+const PASSED_PAWN: usize = BACKWARD_PAWN + 1;
+const PAWN_SHIELD: usize = PASSED_PAWN + 8;
+const KING_ATTACK: usize = PAWN_SHIELD + 1;
+const BISHOP_PAIR: usize = KING_ATTACK + NUM_PIECES;
+
+/// The number of weights in [`EvalParams`].
+pub const NUM_EVAL_WEIGHTS: usize = BISHOP_PAIR + 1;
+
+impl EvalParams {
+    /// Score a position in centipawns from the point of view of the side to move.
+    ///
+    /// This is the [`EvalBreakdown::total()`] of [`EvalParams::breakdown()`].
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        self.breakdown(board).total()
+    }
+
+    /// Evaluate a position term by term and side by side.
+    pub fn breakdown(&self, board: &Board) -> EvalBreakdown {
+        let mut scores: [[Score; NUM_COLORS]; NUM_EVAL_TERMS] =
+            [[Score::ZERO; NUM_COLORS]; NUM_EVAL_TERMS];
+        for c in COLORS {
+            side_features(&Side::new(board, c), |term, i, count| {
+                scores[term.as_index()][c.as_index()] += self.weight(i) * count;
+            });
+        }
+
+        EvalBreakdown {
+            phase: phase(board),
+            side_to_move: board.side_to_move(),
+            scores,
+        }
+    }
+
+    /// Get every weight, in an order that stays the same as long as the fields do.
+    pub fn weights(&self) -> Vec<Score> {
+        (0..NUM_EVAL_WEIGHTS).map(|i| self.weight(i)).collect()
+    }
+
+    /// Create new [`EvalParams`] from the weights in the order of [`EvalParams::weights()`].
+    ///
+    /// # Panics
+    /// Iff there are not exactly [`NUM_EVAL_WEIGHTS`] weights.
+    pub fn from_weights(weights: &[Score]) -> Self {
+        assert_eq!(NUM_EVAL_WEIGHTS, weights.len());
+        let mut params: EvalParams = DEFAULT_EVAL_PARAMS;
+        for (i, w) in weights.iter().enumerate() {
+            *params.weight_mut(i) = *w;
+        }
+        params
+    }
+
+    /// Get the name of the weight at an index of [`EvalParams::weights()`] as it would be
+    /// accessed in Rust, e.g. `psqt[1][36]`.
+    ///
+    /// # Panics
+    /// Iff the index is not below [`NUM_EVAL_WEIGHTS`].
+    pub fn weight_name(i: usize) -> String {
+        match i {
+            MATERIAL..PSQT => format!("material[{}]", i - MATERIAL),
+            PSQT..MOBILITY => format!("psqt[{}][{}]", (i - PSQT) / 64, (i - PSQT) % 64),
+            MOBILITY..DOUBLED_PAWN => format!("mobility[{}]", i - MOBILITY),
+            DOUBLED_PAWN => "doubled_pawn".to_string(),
+            ISOLATED_PAWN => "isolated_pawn".to_string(),
+            BACKWARD_PAWN => "backward_pawn".to_string(),
+            PASSED_PAWN..PAWN_SHIELD => format!("passed_pawn[{}]", i - PASSED_PAWN),
+            PAWN_SHIELD => "pawn_shield".to_string(),
+            KING_ATTACK..BISHOP_PAIR => format!("king_attack[{}]", i - KING_ATTACK),
+            BISHOP_PAIR => "bishop_pair".to_string(),
+            _ => panic!("there are only {} weights", NUM_EVAL_WEIGHTS),
+        }
+    }
+
+    fn weight(&self, i: usize) -> Score {
+        match i {
+            MATERIAL..PSQT => self.material[i - MATERIAL],
+            PSQT..MOBILITY => self.psqt[(i - PSQT) / 64][(i - PSQT) % 64],
+            MOBILITY..DOUBLED_PAWN => self.mobility[i - MOBILITY],
+            DOUBLED_PAWN => self.doubled_pawn,
+            ISOLATED_PAWN => self.isolated_pawn,
+            BACKWARD_PAWN => self.backward_pawn,
+            PASSED_PAWN..PAWN_SHIELD => self.passed_pawn[i - PASSED_PAWN],
+            PAWN_SHIELD => self.pawn_shield,
+            KING_ATTACK..BISHOP_PAIR => self.king_attack[i - KING_ATTACK],
+            BISHOP_PAIR => self.bishop_pair,
+            _ => panic!("there are only {} weights", NUM_EVAL_WEIGHTS),
+        }
+    }
+
+    fn weight_mut(&mut self, i: usize) -> &mut Score {
+        match i {
+            MATERIAL..PSQT => &mut self.material[i - MATERIAL],
+            PSQT..MOBILITY => &mut self.psqt[(i - PSQT) / 64][(i - PSQT) % 64],
+            MOBILITY..DOUBLED_PAWN => &mut self.mobility[i - MOBILITY],
+            DOUBLED_PAWN => &mut self.doubled_pawn,
+            ISOLATED_PAWN => &mut self.isolated_pawn,
+            BACKWARD_PAWN => &mut self.backward_pawn,
+            PASSED_PAWN..PAWN_SHIELD => &mut self.passed_pawn[i - PASSED_PAWN],
+            PAWN_SHIELD => &mut self.pawn_shield,
+            KING_ATTACK..BISHOP_PAIR => &mut self.king_attack[i - KING_ATTACK],
+            BISHOP_PAIR => &mut self.bishop_pair,
+            _ => panic!("there are only {} weights", NUM_EVAL_WEIGHTS),
+        }
+    }
+}
+
+/// How often every weight applies in a position, which the evaluation is linear in.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct EvalFeatures {
+    pub phase: i32,
+    /// The indices of the weights in [`EvalParams::weights()`] that apply, with how much more
+    /// often they apply to white than to black.
+    pub counts: Vec<(usize, i32)>,
+}
+
+impl EvalFeatures {
+    pub fn new(board: &Board) -> Self {
+        let mut counts: Vec<i32> = vec![0; NUM_EVAL_WEIGHTS];
+        for c in COLORS {
+            let sign: i32 = if c == Color::White { 1 } else { -1 };
+            side_features(&Side::new(board, c), |_, i, count| {
+                counts[i] += sign * count
+            });
+        }
+        EvalFeatures {
+            phase: phase(board),
+            counts: counts
+                .into_iter()
+                .enumerate()
+                .filter(|(_, count)| *count != 0)
+                .collect(),
+        }
+    }
+}
+
+fn phase(board: &Board) -> i32 {
+    PIECES
+        .iter()
+        .map(|p| {
+            let count: u32 = COLORS
+                .iter()
+                .map(|c| board.piece_bitboard(*p, *c).popcount())
+                .sum();
+            PHASE_WEIGHTS[p.as_index()] * count as i32
+        })
+        .sum::<i32>()
+        .min(MAX_PHASE)
+}
+
+/// Call `add` with the term, the index in [`EvalParams::weights()`] and the number of times
+/// for every weight that applies to one side.
+fn side_features<F: FnMut(EvalTerm, usize, i32)>(side: &Side, mut add: F) {
+    for p in PIECES {
+        for s in side.pieces(p) {
+            add(EvalTerm::Material, MATERIAL + p.as_index(), 1);
+            let square: usize = side.relative(s).index();
+            add(EvalTerm::PieceSquares, PSQT + 64 * p.as_index() + square, 1);
+        }
+    }
+
+    let area: Bitboard = !side.ours & !side.their_pawn_attacks;
+    for p in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for s in side.pieces(p) {
+            let reach: u32 = (side.attacks(p, s) & area).popcount();
+            add(EvalTerm::Mobility, MOBILITY + p.as_index(), reach as i32);
+        }
+    }
+
+    pawn_structure(side, |i, count| add(EvalTerm::PawnStructure, i, count));
+    king_safety(side, |i, count| add(EvalTerm::KingSafety, i, count));
+
+    let bishops: Bitboard = side.pieces(Piece::Bishop);
+    if !(bishops & LIGHT_SQUARES).is_empty() && !(bishops & DARK_SQUARES).is_empty() {
+        add(EvalTerm::BishopPair, BISHOP_PAIR, 1);
+    }
+}
+
+fn pawn_structure<F: FnMut(usize, i32)>(side: &Side, mut add: F) {
+    let pawns: Bitboard = side.pieces(Piece::Pawn);
+    let theirs: Bitboard = side.board.piece_bitboard(Piece::Pawn, !side.color);
+
+    for file in FILES {
+        let count: i32 = (pawns & file).popcount() as i32;
+        if count > 1 {
+            add(DOUBLED_PAWN, count - 1);
+        }
+    }
+
+    for s in pawns {
+        let file: Bitboard = FILES[s.file_as_u8() as usize];
+        let adjacent: Bitboard = file.east() | file.west();
+        let ahead: Bitboard = side.ahead(s);
+
+        if (pawns & adjacent).is_empty() {
+            add(ISOLATED_PAWN, 1);
+        } else if (pawns & adjacent & !ahead).is_empty()
+            && !(side.forward(Bitboard::from_square(s)) & side.their_pawn_attacks).is_empty()
+        {
+            add(BACKWARD_PAWN, 1);
+        }
+
+        let stoppers: Bitboard = (theirs & (file | adjacent)) | (pawns & file);
+        if (stoppers & ahead).is_empty() {
+            add(PASSED_PAWN + side.relative(s).rank_as_u8() as usize, 1);
+        }
+    }
+}
+
+fn king_safety<F: FnMut(usize, i32)>(side: &Side, mut add: F) {
+    let king: Square = match side.pieces(Piece::King).lsb() {
+        Some(king) => king,
+        None => return,
+    };
+    let zone: Bitboard = king_attacks(king) | Bitboard::from_square(king);
+    let shield: u32 = (side.forward(zone) & side.pieces(Piece::Pawn)).popcount();
+    add(PAWN_SHIELD, shield as i32);
+
+    let them: Side = Side::new(side.board, !side.color);
+    for p in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for s in them.pieces(p) {
+            let attacked: u32 = (them.attacks(p, s) & zone).popcount();
+            add(KING_ATTACK + p.as_index(), attacked as i32);
+        }
+    }
+}
+
+impl Evaluator for EvalParams {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        self.breakdown(board).total()
+    }
+}
+
+/// The bitboards of one side that several terms need.
+struct Side<'a> {
+    board: &'a Board,
+    color: Color,
+    ours: Bitboard,
+    their_pawn_attacks: Bitboard,
+}
+
+impl<'a> Side<'a> {
+    fn new(board: &'a Board, color: Color) -> Self {
+        let theirs: Bitboard = board.piece_bitboard(Piece::Pawn, !color);
+        let front: Bitboard = match color {
+            Color::White => theirs.south(),
+            Color::Black => theirs.north(),
+        };
+        Side {
+            board,
+            color,
+            ours: board.color_bitboard(color),
+            their_pawn_attacks: front.east() | front.west(),
+        }
+    }
+
+    fn pieces(&self, p: Piece) -> Bitboard {
+        self.board.piece_bitboard(p, self.color)
+    }
+
+    fn attacks(&self, p: Piece, s: Square) -> Bitboard {
+        let occupied: Bitboard = self.board.occupied();
+        match p {
+            Piece::Knight => knight_attacks(s),
+            Piece::Bishop => bishop_attacks(s, occupied),
+            Piece::Rook => rook_attacks(s, occupied),
+            Piece::Queen => queen_attacks(s, occupied),
+            Piece::Pawn | Piece::King => Bitboard(0),
+        }
+    }
+
+    /// Shift every square one rank towards the opponent.
+    fn forward(&self, b: Bitboard) -> Bitboard {
+        match self.color {
+            Color::White => b.north(),
+            Color::Black => b.south(),
+        }
+    }
+
+    /// Get every square on the ranks in front of `s`, as seen from this side.
+    fn ahead(&self, s: Square) -> Bitboard {
+        let row: u32 = s.0 as u32 / 8;
+        match self.color {
+            Color::White => Bitboard((1u64 << (row * 8)) - 1),
+            Color::Black => Bitboard(u64::MAX.checked_shl((row + 1) * 8).unwrap_or(0)),
+        }
+    }
+
+    /// Get the square as seen from this side, i.e. mirrored for black.
+    fn relative(&self, s: Square) -> Square {
+        match self.color {
+            Color::White => s,
+            Color::Black => Square(s.0 ^ 56),
+        }
+    }
+}
+
+/// An evaluation split up into its terms, for both sides separately.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct EvalBreakdown {
+    /// The game phase, from 0 without any pieces but pawns and kings to [`MAX_PHASE`].
+    pub phase: i32,
+    pub side_to_move: Color,
+    /// The untapered score of every term, indexed by [`EvalTerm::as_index()`] and
+    /// [`Color::as_index()`].
+    pub scores: [[Score; NUM_COLORS]; NUM_EVAL_TERMS],
+}
+
+impl EvalBreakdown {
+    /// Get what a term contributes to the score of one side, tapered by the game phase.
+    pub fn side_term(&self, term: EvalTerm, c: Color) -> i32 {
+        self.scores[term.as_index()][c.as_index()].taper(self.phase)
+    }
+
+    /// Get what a term contributes to the evaluation, from the point of view of the side to
+    /// move.
+    pub fn term(&self, term: EvalTerm) -> i32 {
+        self.side_term(term, self.side_to_move) - self.side_term(term, !self.side_to_move)
+    }
+
+    /// Get the evaluation from the point of view of the side to move, which is the sum of
+    /// its terms.
+    pub fn total(&self) -> i32 {
+        EVAL_TERMS.iter().map(|t| self.term(*t)).sum()
+    }
+}
+
+/// Formats the breakdown as a table in pawns, from the point of view of white.
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pawns = |cp: i32| format!("{:+.2}", cp as f64 / 100.0);
+        let rule: &str = "----------------+---------+---------+---------";
+        writeln!(f, "Term            |  White  |  Black  |  Total")?;
+        writeln!(f, "{}", rule)?;
+        let mut totals: [i32; NUM_COLORS] = [0; NUM_COLORS];
+        for term in EVAL_TERMS {
+            let white: i32 = self.side_term(term, Color::White);
+            let black: i32 = self.side_term(term, Color::Black);
+            totals[0] += white;
+            totals[1] += black;
+            writeln!(
+                f,
+                "{:<15} | {:>7} | {:>7} | {:>7}",
+                term,
+                pawns(white),
+                pawns(black),
+                pawns(white - black)
+            )?;
+        }
+        writeln!(f, "{}", rule)?;
+        writeln!(
+            f,
+            "{:<15} | {:>7} | {:>7} | {:>7}",
+            "Total",
+            pawns(totals[0]),
+            pawns(totals[1]),
+            pawns(totals[0] - totals[1])
+        )?;
+        write!(f, "\nPhase: {}/{}", self.phase, MAX_PHASE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchLimits, Searcher};
+
+    /// Weights that are all zero, for counting what a single term sees.
+    fn zero() -> EvalParams {
+        EvalParams {
+            material: [Score::ZERO; NUM_PIECES],
+            psqt: [[Score::ZERO; 64]; NUM_PIECES],
+            mobility: [Score::ZERO; NUM_PIECES],
+            doubled_pawn: Score::ZERO,
+            isolated_pawn: Score::ZERO,
+            backward_pawn: Score::ZERO,
+            passed_pawn: [Score::ZERO; 8],
+            pawn_shield: Score::ZERO,
+            king_attack: [Score::ZERO; NUM_PIECES],
+            bishop_pair: Score::ZERO,
+        }
+    }
+
+    /// Get the untapered score of a term for white and black.
+    fn scores(params: &EvalParams, fen: &str, term: EvalTerm) -> [i32; NUM_COLORS] {
+        let breakdown: EvalBreakdown = params.breakdown(&Board::from_fen(fen));
+        breakdown.scores[term.as_index()].map(|s| {
+            assert_eq!(s.mg, s.eg);
+            s.mg
+        })
+    }
+
+    /// Mirror a position vertically and swap the colors.
+    fn flip(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let swap_case = |s: &str| -> String {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+        let placement: Vec<&str> = fields[0].split('/').rev().collect();
+        let side: &str = if fields[1] == "w" { "b" } else { "w" };
+        let mut castling: Vec<char> = swap_case(fields[2]).chars().collect();
+        castling.sort_by_key(|c| c.is_ascii_lowercase());
+        let castling: String = castling.into_iter().collect();
+        format!(
+            "{} {} {} - {} {}",
+            swap_case(&placement.join("/")),
+            side,
+            castling,
+            fields[4],
+            fields[5]
+        )
+    }
+
+    #[test]
+    fn tapering() {
+        let s: Score = Score::new(100, -20);
+        assert_eq!(100, s.taper(MAX_PHASE));
+        assert_eq!(-20, s.taper(0));
+        assert_eq!(40, s.taper(MAX_PHASE / 2));
+        assert_eq!(Score::new(0, -40), (s - Score::new(50, 10)) * 2 + -s);
+
+        let params: EvalParams = EvalParams::default();
+        assert_eq!(MAX_PHASE, params.breakdown(&Board::default()).phase);
+        let endgame: EvalBreakdown =
+            params.breakdown(&Board::from_fen("4k3/pp6/8/8/8/8/5R2/4K3 w - - 0 1"));
+        assert_eq!(2, endgame.phase);
+    }
+
+    #[test]
+    fn symmetry() {
+        let params: EvalParams = EvalParams::default();
+        assert_eq!(0, params.evaluate(&Board::default()));
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/3p4/3P4/4P3/8/4K3 b - - 0 1",
+        ] {
+            let board: Board = Board::from_fen(fen);
+            let flipped: Board = Board::from_fen(&flip(fen));
+            assert_eq!(
+                params.evaluate(&board),
+                params.evaluate(&flipped),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn breakdown() {
+        let params: EvalParams = EvalParams::default();
+        // White is a knight up.
+        let board: Board =
+            Board::from_fen("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let breakdown: EvalBreakdown = params.breakdown(&board);
+        let total: i32 = EVAL_TERMS.iter().map(|t| breakdown.term(*t)).sum();
+        assert_eq!(total, breakdown.total());
+        assert_eq!(total, params.evaluate(&board));
+        assert_eq!(
+            params.material[Piece::Knight.as_index()].taper(breakdown.phase),
+            breakdown.term(EvalTerm::Material)
+        );
+        assert!(breakdown.total() > 200);
+
+        // The same position seen by black.
+        let board: Board =
+            Board::from_fen("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1");
+        let black: EvalBreakdown = params.breakdown(&board);
+        assert_eq!(
+            -breakdown.term(EvalTerm::Material),
+            black.term(EvalTerm::Material)
+        );
+        assert_eq!(
+            breakdown.side_term(EvalTerm::Mobility, Color::White),
+            black.side_term(EvalTerm::Mobility, Color::White)
+        );
+
+        let table: String = breakdown.to_string();
+        assert!(table.contains("Pawn structure  |"));
+        assert!(table.contains(&format!(
+            "Material        | {:>+7.2} |",
+            breakdown.side_term(EvalTerm::Material, Color::White) as f64 / 100.0
+        )));
+        assert!(table.ends_with("Phase: 23/24"));
+    }
+
+    #[test]
+    fn pawn_structure() {
+        let params: EvalParams = EvalParams {
+            doubled_pawn: Score::new(1, 1),
+            isolated_pawn: Score::new(10, 10),
+            backward_pawn: Score::new(100, 100),
+            passed_pawn: [Score::new(1_000, 1_000); 8],
+            ..zero()
+        };
+        // Two isolated passers, the rear one of the doubled pawns not being passed.
+        assert_eq!(
+            [2_031, 0],
+            scores(
+                &params,
+                "4k3/8/8/8/8/2P5/P1P5/4K3 w - - 0 1",
+                EvalTerm::PawnStructure
+            )
+        );
+        // The e3 pawn is backward, the d5 pawn isolated and nothing is passed.
+        assert_eq!(
+            [100, 10],
+            scores(
+                &params,
+                "4k3/8/8/3p4/3P4/4P3/8/4K3 w - - 0 1",
+                EvalTerm::PawnStructure
+            )
+        );
+
+        let params: EvalParams = EvalParams {
+            passed_pawn: [0, 1, 2, 3, 4, 5, 6, 7].map(|r| Score::new(r, r)),
+            ..zero()
+        };
+        assert_eq!(
+            [5, 4],
+            scores(
+                &params,
+                "4k3/8/1P6/8/6p1/8/8/4K3 w - - 0 1",
+                EvalTerm::PawnStructure
+            )
+        );
+    }
+
+    #[test]
+    fn pieces() {
+        let mut params: EvalParams = EvalParams {
+            bishop_pair: Score::new(1, 1),
+            pawn_shield: Score::new(1, 1),
+            ..zero()
+        };
+        params.mobility[Piece::Knight.as_index()] = Score::new(1, 1);
+        params.king_attack[Piece::Queen.as_index()] = Score::new(1, 1);
+
+        // The square the e6 pawn guards does not count.
+        assert_eq!(
+            [7, 0],
+            scores(
+                &params,
+                "4k3/8/4p3/8/3N4/8/8/4K3 w - - 0 1",
+                EvalTerm::Mobility
+            )
+        );
+        assert_eq!(
+            [1, 0],
+            scores(
+                &params,
+                "2b1k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+                EvalTerm::BishopPair
+            )
+        );
+        assert_eq!(
+            [0, 0],
+            scores(
+                &params,
+                "4k3/8/8/8/8/8/8/2B1K1B1 w - - 0 1",
+                EvalTerm::BishopPair
+            )
+        );
+        // Three shield pawns against a queen hitting a1 and b1.
+        assert_eq!(
+            [2, 3],
+            scores(
+                &params,
+                "6k1/5ppp/8/8/8/8/8/K5q1 w - - 0 1",
+                EvalTerm::KingSafety
+            )
+        );
+    }
+
+    #[test]
+    fn weights() {
+        let params: EvalParams = EvalParams::default();
+        let weights: Vec<Score> = params.weights();
+        assert_eq!(NUM_EVAL_WEIGHTS, weights.len());
+        assert_eq!(params, EvalParams::from_weights(&weights));
+        assert_eq!("material[1]", EvalParams::weight_name(1));
+        assert_eq!("psqt[5][63]", EvalParams::weight_name(PSQT + 383));
+        assert_eq!("bishop_pair", EvalParams::weight_name(NUM_EVAL_WEIGHTS - 1));
+
+        // The features describe the evaluation up to rounding.
+        let board: Board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1");
+        let features: EvalFeatures = EvalFeatures::new(&board);
+        let white: i32 = features
+            .counts
+            .iter()
+            .fold(Score::ZERO, |score, (i, count)| {
+                score + weights[*i] * *count
+            })
+            .taper(features.phase);
+        let diff: i32 = white + params.evaluate(&board);
+        assert!(diff.abs() <= NUM_EVAL_TERMS as i32, "{}", diff);
+    }
+
+    #[test]
+    fn search() {
+        let mut searcher = Searcher::with_evaluator(EvalParams::default());
+        let r = searcher.search(
+            &Board::from_fen("r3k3/8/8/1N6/8/8/7P/4K3 w - - 0 1"),
+            &SearchLimits::depth(4),
+        );
+        assert_eq!("b5c7", r.best_move.unwrap().to_uci());
+        assert!(r.score > 300);
+    }
+}
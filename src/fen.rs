@@ -0,0 +1,383 @@
+use crate::attacks::{bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks};
+use crate::bitboard::{Bitboard, EMPTY, RANK_1, RANK_8};
+use crate::castling_rights::{corner_file, CastlingSide};
+use crate::color::Color;
+use crate::piece::{Piece, NUM_PIECES};
+use crate::square::{File, Square};
+
+use std::fmt;
+
+/// Exhaustive enum of the six space separated fields of a FEN string.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FenField {
+    PiecePlacement,
+    ActiveColor,
+    CastlingRights,
+    EnPassantSquare,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+/// The fields of a FEN string in the order they appear in.
+pub const FEN_FIELDS: [FenField; 6] = [
+    FenField::PiecePlacement,
+    FenField::ActiveColor,
+    FenField::CastlingRights,
+    FenField::EnPassantSquare,
+    FenField::HalfmoveClock,
+    FenField::FullmoveNumber,
+];
+
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s: &str = match self {
+            FenField::PiecePlacement => "piece placement",
+            FenField::ActiveColor => "active color",
+            FenField::CastlingRights => "castling rights",
+            FenField::EnPassantSquare => "en passant square",
+            FenField::HalfmoveClock => "halfmove clock",
+            FenField::FullmoveNumber => "fullmove number",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Exhaustive enum of the reasons a FEN string can be rejected or repaired for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FenErrorKind {
+    /// The FEN did not consist of one of the accepted numbers of fields.
+    FieldCount {
+        /// The number of fields found.
+        found: usize,
+        /// The numbers of fields accepted in this mode, in ascending order.
+        expected: &'static [usize],
+    },
+    /// A character that has no meaning in this field.
+    UnexpectedCharacter(char),
+    /// A rank describes more than eight files.
+    RankTooLong,
+    /// A rank describes fewer than eight files.
+    RankTooShort,
+    /// The piece placement does not consist of exactly eight ranks.
+    RankCount(usize),
+    /// A castling right was listed more than once.
+    DuplicateCastlingRight(char),
+    /// A castling right is listed, but the king or rook are not on their home squares.
+    ImpossibleCastlingRight(char),
+    /// The en passant square can not be the result of a double pawn push.
+    ImpossibleEnPassantSquare,
+    /// A clock is not a valid number for this field.
+    InvalidNumber,
+    /// A color does not have exactly one king.
+    KingCount(Color, usize),
+    /// A pawn stands on the first or the eighth rank.
+    PawnOnBackRank,
+    /// A color has more pawns or pieces than can be reached in a game.
+    TooManyPieces(Color),
+    /// The side that is not to move is in check.
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenErrorKind::FieldCount { found, expected } => {
+                write!(f, "expected ")?;
+                for (i, n) in expected.iter().enumerate() {
+                    match i {
+                        0 => {}
+                        _ if i + 1 == expected.len() => write!(f, " or ")?,
+                        _ => write!(f, ", ")?,
+                    }
+                    write!(f, "{}", n)?;
+                }
+                write!(f, " fields but found {}", found)
+            }
+            FenErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{}`", c),
+            FenErrorKind::RankTooLong => write!(f, "rank has more than 8 files"),
+            FenErrorKind::RankTooShort => write!(f, "rank has fewer than 8 files"),
+            FenErrorKind::RankCount(n) => write!(f, "expected 8 ranks but found {}", n),
+            FenErrorKind::DuplicateCastlingRight(c) => {
+                write!(f, "castling right `{}` appears more than once", c)
+            }
+            FenErrorKind::ImpossibleCastlingRight(c) => {
+                write!(f, "castling right `{}` without king and rook in place", c)
+            }
+            FenErrorKind::ImpossibleEnPassantSquare => {
+                write!(f, "en passant square does not follow a double pawn push")
+            }
+            FenErrorKind::InvalidNumber => write!(f, "not a valid number"),
+            FenErrorKind::KingCount(c, n) => write!(f, "{} has {} kings instead of 1", c, n),
+            FenErrorKind::PawnOnBackRank => write!(f, "pawn on the first or eighth rank"),
+            FenErrorKind::TooManyPieces(c) => write!(f, "{} has too many pawns or pieces", c),
+            FenErrorKind::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+/// A problem found in a FEN string that was repaired when parsing leniently.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FenWarning {
+    /// The field the problem was found in.
+    pub field: FenField,
+    /// The character offset into the FEN string.
+    pub offset: usize,
+    /// What was wrong with the field.
+    pub kind: FenErrorKind,
+}
+
+impl fmt::Display for FenWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in {} at offset {}",
+            self.kind, self.field, self.offset
+        )
+    }
+}
+
+/// Split a FEN string into its whitespace separated fields along with their character offsets.
+pub(crate) fn split_fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields: Vec<(usize, &str)> = Vec::with_capacity(6);
+    let mut start: Option<(usize, usize)> = None;
+
+    for (chars, (bytes, c)) in fen.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (true, Some((offset, from))) => {
+                fields.push((offset, &fen[from..bytes]));
+                start = None;
+            }
+            (false, None) => start = Some((chars, bytes)),
+            _ => {}
+        }
+    }
+    if let Some((offset, from)) = start {
+        fields.push((offset, &fen[from..]));
+    }
+    fields
+}
+
+/// Check the piece placement of a parsed FEN for positions that can not occur in a game.
+///
+/// On failure, returns the offending kind and, if it can be attributed to a single square,
+/// that square.
+pub(crate) fn validate_position(
+    bitboards: &[Bitboard; NUM_PIECES * 2],
+    side_to_move: Color,
+) -> std::result::Result<(), (FenErrorKind, Option<Square>)> {
+    let bb = |p: Piece, c: Color| bitboards[c.as_index() * NUM_PIECES + p.as_index()];
+
+    for c in [Color::White, Color::Black] {
+        let kings: usize = bb(Piece::King, c).popcount() as usize;
+        if kings != 1 {
+            return Err((FenErrorKind::KingCount(c, kings), None));
+        }
+
+        let pieces: u32 = bitboards[c.as_index() * NUM_PIECES..(c.as_index() + 1) * NUM_PIECES]
+            .iter()
+            .map(|b| b.popcount())
+            .sum();
+        if bb(Piece::Pawn, c).popcount() > 8 || pieces > 16 {
+            return Err((FenErrorKind::TooManyPieces(c), None));
+        }
+    }
+
+    let pawns: Bitboard =
+        (bb(Piece::Pawn, Color::White) | bb(Piece::Pawn, Color::Black)) & (RANK_1 | RANK_8);
+    if let Some(s) = pawns.lsb() {
+        return Err((FenErrorKind::PawnOnBackRank, Some(s)));
+    }
+
+    let them: Color = !side_to_move;
+    let king: Square = match bb(Piece::King, them).lsb() {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+    let occupied: Bitboard = bitboards.iter().fold(EMPTY, |acc, b| acc | *b);
+    let queens: Bitboard = bb(Piece::Queen, side_to_move);
+    let attackers: Bitboard = (pawn_attacks(them, king) & bb(Piece::Pawn, side_to_move))
+        | (knight_attacks(king) & bb(Piece::Knight, side_to_move))
+        | (king_attacks(king) & bb(Piece::King, side_to_move))
+        | (bishop_attacks(king, occupied) & (bb(Piece::Bishop, side_to_move) | queens))
+        | (rook_attacks(king, occupied) & (bb(Piece::Rook, side_to_move) | queens));
+    if attackers != EMPTY {
+        return Err((FenErrorKind::OpponentInCheck, None));
+    }
+
+    Ok(())
+}
+
+/// The king of color `c` if it stands on its back rank.
+pub(crate) fn back_rank_king(bitboards: &[Bitboard; NUM_PIECES * 2], c: Color) -> Option<File> {
+    let kings: Bitboard = bitboards[c.as_index() * NUM_PIECES + Piece::King.as_index()];
+    let back_rank: Bitboard = match c {
+        Color::White => RANK_1,
+        Color::Black => RANK_8,
+    };
+    (kings & back_rank).lsb().map(|s| s.file())
+}
+
+/// The square on the back rank of color `c` on file `f`.
+fn back_rank_square(c: Color, f: File) -> Square {
+    match c {
+        Color::White => Square(56 + f.0),
+        Color::Black => Square(f.0),
+    }
+}
+
+/// The rook of color `c` on its back rank that is furthest from the king towards `side`.
+///
+/// Without a king on the back rank the king is assumed to stand on the e-file.
+pub(crate) fn outermost_rook(
+    bitboards: &[Bitboard; NUM_PIECES * 2],
+    c: Color,
+    side: CastlingSide,
+) -> Option<File> {
+    let king: u8 = back_rank_king(bitboards, c).unwrap_or(File(4)).0;
+    let rooks: Bitboard = bitboards[c.as_index() * NUM_PIECES + Piece::Rook.as_index()];
+    let is_rook = |f: &u8| rooks.contains(back_rank_square(c, File(*f)));
+    match side {
+        CastlingSide::Kingside => (king + 1..8).rev().find(is_rook),
+        CastlingSide::Queenside => (0..king).find(is_rook),
+    }
+    .map(File)
+}
+
+/// Resolve a character of the castling field to the color, side and rook file it stands for.
+///
+/// In Chess960 `KQkq` refer to the outermost rook on that side of the king as in X-FEN, or to
+/// the corner if there is none, in standard chess always to the corner. File letters as in
+/// Shredder-FEN name the rook directly and are on the kingside if they are right of the king.
+pub(crate) fn castling_right_from_char(
+    bitboards: &[Bitboard; NUM_PIECES * 2],
+    c: char,
+    chess960: bool,
+) -> Option<(Color, CastlingSide, File)> {
+    let color: Color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let side: CastlingSide = match c.to_ascii_lowercase() {
+        'k' => CastlingSide::Kingside,
+        'q' => CastlingSide::Queenside,
+        'a'..='h' => {
+            let file: File = File(c.to_ascii_lowercase() as u8 - b'a');
+            let king: File = back_rank_king(bitboards, color).unwrap_or(File(4));
+            let side: CastlingSide = if file > king {
+                CastlingSide::Kingside
+            } else {
+                CastlingSide::Queenside
+            };
+            return Some((color, side, file));
+        }
+        _ => return None,
+    };
+    let file: File = match chess960 {
+        true => outermost_rook(bitboards, color, side).unwrap_or(corner_file(side)),
+        false => corner_file(side),
+    };
+    Some((color, side, file))
+}
+
+/// Check whether color `c` can castle to `side` with the rook on `rook_file`, i.e. whether king
+/// and rook stand on the back rank with the rook on that side of the king.
+pub(crate) fn castling_right_possible(
+    bitboards: &[Bitboard; NUM_PIECES * 2],
+    c: Color,
+    side: CastlingSide,
+    rook_file: File,
+) -> bool {
+    let rooks: Bitboard = bitboards[c.as_index() * NUM_PIECES + Piece::Rook.as_index()];
+    let rook_in_place: bool = rooks.contains(back_rank_square(c, rook_file));
+    match back_rank_king(bitboards, c) {
+        Some(king) => {
+            rook_in_place
+                && match side {
+                    CastlingSide::Kingside => rook_file > king,
+                    CastlingSide::Queenside => rook_file < king,
+                }
+        }
+        None => false,
+    }
+}
+
+/// Check whether `s` can be the en passant square right after the opponent of `side_to_move`
+/// pushed a pawn two squares.
+pub(crate) fn en_passant_square_possible(
+    bitboards: &[Bitboard; NUM_PIECES * 2],
+    side_to_move: Color,
+    s: Square,
+) -> bool {
+    let them: Color = !side_to_move;
+    // Rows count from the eighth rank, so a white pawn skips a square on row 5 (the third rank).
+    let expected_row: u8 = match them {
+        Color::White => 5,
+        Color::Black => 2,
+    };
+    if s.0 / 8 != expected_row {
+        return false;
+    }
+
+    let (pawn, origin) = match them {
+        Color::White => (s.0 - 8, s.0 + 8),
+        Color::Black => (s.0 + 8, s.0 - 8),
+    };
+
+    let occupied: Bitboard = bitboards.iter().fold(EMPTY, |acc, b| acc | *b);
+    let pawns: Bitboard = bitboards[them.as_index() * NUM_PIECES + Piece::Pawn.as_index()];
+    pawns & Bitboard::from_square(Square(pawn)) != EMPTY
+        && occupied & (Bitboard::from_square(s) | Bitboard::from_square(Square(origin))) == EMPTY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_fields_offsets() {
+        let fields = split_fields("  8/8 w  -\t- 0 1 ");
+        assert_eq!(
+            vec![
+                (2, "8/8"),
+                (6, "w"),
+                (9, "-"),
+                (11, "-"),
+                (13, "0"),
+                (15, "1")
+            ],
+            fields
+        );
+        assert!(split_fields("   ").is_empty());
+    }
+
+    #[test]
+    fn display() {
+        let w = FenWarning {
+            field: FenField::CastlingRights,
+            offset: 47,
+            kind: FenErrorKind::ImpossibleCastlingRight('K'),
+        };
+        assert_eq!(
+            "castling right `K` without king and rook in place in castling rights at offset 47",
+            w.to_string()
+        );
+        assert_eq!("en passant square", FEN_FIELDS[3].to_string());
+        assert_eq!(
+            "expected 6 fields but found 4",
+            FenErrorKind::FieldCount {
+                found: 4,
+                expected: &[6]
+            }
+            .to_string()
+        );
+        assert_eq!(
+            "expected 4, 5 or 6 fields but found 3",
+            FenErrorKind::FieldCount {
+                found: 3,
+                expected: &[4, 5, 6]
+            }
+            .to_string()
+        );
+    }
+}
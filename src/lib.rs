@@ -3,6 +3,13 @@
 //! ...
 //!
 
+mod attackers;
+
+pub mod attacks;
+pub use attacks::{
+    bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+};
+
 pub mod bitboard;
 pub use bitboard::*;
 
@@ -12,14 +19,69 @@ pub use board::*;
 pub mod castling_rights;
 pub use castling_rights::*;
 
+mod chess960;
+
+pub mod chess_move;
+pub use chess_move::*;
+
 pub mod color;
 pub use color::*;
 
 pub mod error;
 pub use error::*;
 
+pub mod eval;
+pub use eval::*;
+
+pub mod fen;
+pub use fen::*;
+
+mod movegen;
+
+#[cfg(feature = "nnue")]
+pub mod nnue;
+#[cfg(feature = "nnue")]
+pub use nnue::*;
+
+pub mod outcome;
+pub use outcome::*;
+
+mod perft;
+
+pub mod pgn;
+pub use pgn::*;
+
 pub mod piece;
 pub use piece::*;
 
+pub mod polyglot;
+pub use polyglot::*;
+
+mod san;
+
+pub mod search;
+pub use search::*;
+
+pub mod see;
+pub use see::*;
+
 pub mod square;
 pub use square::*;
+
+#[cfg(test)]
+mod test_util;
+
+pub mod transposition;
+pub use transposition::*;
+
+pub mod tuner;
+pub use tuner::*;
+
+pub mod uci;
+pub use uci::*;
+
+pub mod uci_engine;
+pub use uci_engine::*;
+
+pub mod zobrist;
+pub use zobrist::*;
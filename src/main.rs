@@ -1,11 +1,536 @@
-use chessify::Board;
+use chessify::{
+    mate_in, read_training_positions, Board, BookBuilder, BookConfig, CastlingMode, Color,
+    EvalParams, Move, PolyglotBook, SearchLimits, SearchResult, Searcher, TrainingPosition,
+    TranspositionTable, Tuner, TunerConfig, DEFAULT_BOARD_FEN, DEFAULT_TT_SIZE_MB,
+};
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: chessify [perft <depth> [fen] | tune <dataset> <output> [steps] | \
+                     book <pgn> <output> [max ply]]";
+
+/// The number of steps `chessify tune` takes unless told otherwise.
+const DEFAULT_TUNING_STEPS: usize = 1000;
+
+/// The name the engine reports to UCI graphical user interfaces.
+const ENGINE_NAME: &str = concat!("chessify ", env!("CARGO_PKG_VERSION"));
+
+/// The time kept in reserve for communicating with the user interface.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// The largest transposition table the `Hash` option allows, in megabytes.
+const MAX_HASH_MB: usize = 4096;
 
 fn main() {
-    let b = Board::default();
-    println!("{}", b);
-    println!("{:?}", b);
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("perft") => {
+            if let Err(e) = perft(&args[1..]) {
+                eprintln!("error: {}", e);
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+        Some("tune") => {
+            if let Err(e) = tune(&args[1..]) {
+                eprintln!("error: {}", e);
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+        Some("book") => {
+            if let Err(e) = book(&args[1..]) {
+                eprintln!("error: {}", e);
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+        None => uci(),
+    }
+}
+
+/// Speak the UCI protocol on stdin and stdout until `quit` or the end of the input.
+///
+/// At the end of the input a running search is allowed to finish, unless it would never stop
+/// on its own, so that scripted input can be piped into the engine.
+fn uci() {
+    let mut engine: Engine = Engine::new();
+    for line in io::stdin().lock().lines() {
+        let line: String = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if !engine.handle(&line) {
+            engine.stop();
+            return;
+        }
+    }
+    engine.wait();
+}
+
+/// The state of the UCI engine between commands.
+struct Engine {
+    board: Board,
+    chess960: bool,
+    /// The searcher, which is lent to the search thread while searching.
+    searcher: Option<Searcher>,
+    search: Option<SearchHandle>,
+}
+
+/// A search running on its own thread.
+struct SearchHandle {
+    thread: JoinHandle<Searcher>,
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+    infinite: bool,
+    /// The time to spend once a pondering search becomes a real one.
+    budget: Option<Duration>,
+}
+
+impl Engine {
+    fn new() -> Self {
+        Engine {
+            board: Board::default(),
+            chess960: false,
+            searcher: Some(Searcher::new()),
+            search: None,
+        }
+    }
+
+    fn castling_mode(&self) -> CastlingMode {
+        if self.chess960 {
+            CastlingMode::Chess960
+        } else {
+            CastlingMode::Standard
+        }
+    }
+
+    /// Handle a single command, returning `false` once the engine should quit.
+    ///
+    /// Unknown commands are ignored, as the protocol asks for.
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (command, args) = match tokens.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return true,
+        };
+
+        match command {
+            "uci" => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author the chessify developers");
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_TT_SIZE_MB, MAX_HASH_MB
+                );
+                println!("option name Clear Hash type button");
+                println!("option name Ponder type check default false");
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "setoption" => self.set_option(args),
+            "ucinewgame" => {
+                self.wait();
+                self.board = Board::default();
+                if let Some(searcher) = &mut self.searcher {
+                    searcher.clear();
+                }
+            }
+            "position" => {
+                self.wait();
+                match self.parse_position(args) {
+                    Ok(board) => self.board = board,
+                    Err(e) => println!("info string {}", e),
+                }
+            }
+            "go" => {
+                self.wait();
+                self.go(Limits::parse(args));
+            }
+            "stop" => self.stop(),
+            "ponderhit" => self.ponderhit(),
+            "quit" => return false,
+            _ => {}
+        }
+        true
+    }
+
+    /// Handle `setoption name <name> [value <value>]`, option names being case insensitive.
+    fn set_option(&mut self, args: &[&str]) {
+        let value_at: usize = args
+            .iter()
+            .position(|t| *t == "value")
+            .unwrap_or(args.len());
+        if args.first() != Some(&"name") || value_at < 2 {
+            println!("info string expected setoption name <name> [value <value>]");
+            return;
+        }
+        let name: String = args[1..value_at].join(" ");
+        let value: String = args.get(value_at + 1..).unwrap_or_default().join(" ");
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => {
+                    self.wait();
+                    if let Some(searcher) = &mut self.searcher {
+                        searcher.set_tt(Arc::new(TranspositionTable::new(mb)));
+                    }
+                }
+                _ => println!("info string invalid Hash value {}", value),
+            },
+            "clear hash" => {
+                self.wait();
+                if let Some(searcher) = &self.searcher {
+                    searcher.tt().clear();
+                }
+            }
+            "uci_chess960" => self.chess960 = value == "true",
+            // Only tells whether the user interface may send `go ponder`.
+            "ponder" => {}
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    /// Parse `position (startpos | fen <fen>) [moves <move>...]` into a new board.
+    fn parse_position(&self, args: &[&str]) -> chessify::Result<Board> {
+        let moves_at: usize = args
+            .iter()
+            .position(|t| *t == "moves")
+            .unwrap_or(args.len());
+        let mut board: Board = match args.first() {
+            Some(&"startpos") if moves_at == 1 => Board::default(),
+            Some(&"fen") if self.chess960 => {
+                Board::try_from_fen_chess960(&args[1..moves_at].join(" "))?
+            }
+            Some(&"fen") => Board::try_from_fen(&args[1..moves_at].join(" "))?,
+            _ => return Err("expected position (startpos | fen <fen>) [moves <move>...]".into()),
+        };
+
+        for uci in args.iter().skip(moves_at + 1) {
+            let m: Move = board.parse_uci_move_with_mode(uci, self.castling_mode())?;
+            if m.is_null() {
+                return Err(format!("can not play the null move {}", uci).into());
+            }
+            board.make_move(m);
+        }
+        Ok(board)
+    }
+
+    /// Start searching the current position on a new thread.
+    fn go(&mut self, limits: Limits) {
+        let stop: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let ponder: Arc<AtomicBool> = Arc::new(AtomicBool::new(limits.ponder));
+        let infinite: bool = limits.infinite;
+        let us: Color = self.board.side_to_move();
+        let search_limits: SearchLimits = limits.to_search_limits(us, Arc::clone(&stop));
+
+        let mut searcher: Searcher = self.searcher.take().unwrap_or_default();
+        let board: Board = self.board.clone();
+        let mode: CastlingMode = self.castling_mode();
+        let (thread_stop, thread_ponder) = (Arc::clone(&stop), Arc::clone(&ponder));
+        let thread = thread::spawn(move || {
+            let result: SearchResult =
+                searcher.search_with(&board, &search_limits, |r| print_info(&board, r, mode));
+
+            // The best move may only be sent once the user interface asks for it.
+            while !thread_stop.load(Ordering::Relaxed)
+                && (infinite || thread_ponder.load(Ordering::Relaxed))
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            print_best_move(&board, &result, mode);
+            searcher
+        });
+
+        self.search = Some(SearchHandle {
+            thread,
+            stop,
+            ponder,
+            infinite,
+            budget: limits.budget(us),
+        });
+    }
+
+    /// Start the clock of a pondering search, which runs out after the budget for the move.
+    fn ponderhit(&mut self) {
+        if let Some(search) = &self.search {
+            search.ponder.store(false, Ordering::Relaxed);
+            if let Some(budget) = search.budget {
+                let stop: Arc<AtomicBool> = Arc::clone(&search.stop);
+                thread::spawn(move || {
+                    thread::sleep(budget);
+                    stop.store(true, Ordering::Relaxed);
+                });
+            }
+        }
+    }
+
+    /// Stop the running search, if any, and wait for it to report its best move.
+    fn stop(&mut self) {
+        if let Some(search) = &self.search {
+            search.stop.store(true, Ordering::Relaxed);
+        }
+        self.join();
+    }
+
+    /// Wait for the running search to finish, stopping it if it never would on its own.
+    ///
+    /// User interfaces stop the search before sending anything else, but scripted input can
+    /// rely on every search reaching its limits.
+    fn wait(&mut self) {
+        if let Some(search) = &self.search {
+            if search.infinite || search.ponder.load(Ordering::Relaxed) {
+                search.stop.store(true, Ordering::Relaxed);
+            }
+        }
+        self.join();
+    }
+
+    /// Wait for the search thread to end and take back the searcher.
+    fn join(&mut self) {
+        if let Some(search) = self.search.take() {
+            // The search thread only panics on a bug, which it has already reported.
+            self.searcher = search.thread.join().ok();
+        }
+    }
+}
+
+/// The limits of a search given by the `go` command.
+#[derive(Debug, Default)]
+struct Limits {
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    movetime: Option<Duration>,
+    time: [Option<Duration>; 2],
+    inc: [Duration; 2],
+    movestogo: Option<u32>,
+    infinite: bool,
+    ponder: bool,
+}
+
+impl Limits {
+    /// Parse the arguments of `go`, ignoring anything not understood.
+    ///
+    /// A `go` without any limit searches until it is stopped.
+    fn parse(args: &[&str]) -> Self {
+        let mut limits: Limits = Limits::default();
+        let mut tokens = args.iter();
+        while let Some(token) = tokens.next() {
+            let mut value = || tokens.next().and_then(|v| v.parse::<u64>().ok());
+            let millis = |ms: Option<u64>| ms.map(Duration::from_millis);
+            match *token {
+                "depth" => limits.depth = value().map(|d| d as u32),
+                "nodes" => limits.nodes = value(),
+                "movetime" => limits.movetime = millis(value()),
+                "wtime" => limits.time[Color::White.as_index()] = millis(value()),
+                "btime" => limits.time[Color::Black.as_index()] = millis(value()),
+                "winc" => limits.inc[Color::White.as_index()] = millis(value()).unwrap_or_default(),
+                "binc" => limits.inc[Color::Black.as_index()] = millis(value()).unwrap_or_default(),
+                "movestogo" => limits.movestogo = value().map(|n| n as u32),
+                "infinite" => limits.infinite = true,
+                "ponder" => limits.ponder = true,
+                _ => {}
+            }
+        }
+
+        if limits.depth.is_none()
+            && limits.nodes.is_none()
+            && limits.movetime.is_none()
+            && limits.time.iter().all(Option::is_none)
+        {
+            limits.infinite = true;
+        }
+        limits
+    }
+
+    /// The time to spend on the move of color `us`, or [`None`] if the search is not timed.
+    fn budget(&self, us: Color) -> Option<Duration> {
+        if let Some(movetime) = self.movetime {
+            return Some(movetime.saturating_sub(MOVE_OVERHEAD));
+        }
+        let time: Duration = self.time[us.as_index()]?;
+        let moves_to_go: u32 = self.movestogo.unwrap_or(30).max(1);
+        let budget: Duration = time / moves_to_go + self.inc[us.as_index()] * 3 / 4;
+        Some(budget.min(time.saturating_sub(MOVE_OVERHEAD)))
+    }
+
+    /// Translate the limits for a search by color `us` that `stop` aborts.
+    ///
+    /// A pondering search is not timed, its clock is only started by `ponderhit`.
+    fn to_search_limits(&self, us: Color, stop: Arc<AtomicBool>) -> SearchLimits {
+        let mut limits: SearchLimits = SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            stop: Some(stop),
+            ..Default::default()
+        };
+        if self.ponder {
+            return limits;
+        }
+        if self.movetime.is_some() {
+            limits.time = self.budget(us);
+        } else if let Some(time) = self.time[us.as_index()] {
+            // Iterations may overrun the budget, but never eat up the whole clock.
+            limits.soft_time = self.budget(us);
+            limits.time = limits
+                .soft_time
+                .map(|b| (b * 3).min(time.saturating_sub(MOVE_OVERHEAD)));
+        }
+        limits
+    }
+}
+
+/// Print the `info` line for a finished iteration.
+fn print_info(board: &Board, result: &SearchResult, mode: CastlingMode) {
+    println!(
+        "info depth {} seldepth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        result.depth,
+        result.seldepth,
+        format_score(result.score),
+        result.nodes,
+        result.nps(),
+        result.hashfull,
+        result.time.as_millis(),
+        format_line(board, &result.pv, mode)
+    );
+}
+
+/// Print the `bestmove` with the expected reply to ponder on, if there is one.
+fn print_best_move(board: &Board, result: &SearchResult, mode: CastlingMode) {
+    match result.best_move {
+        Some(best) => {
+            let line: &[Move] = if result.pv.first() == Some(&best) {
+                &result.pv[..result.pv.len().min(2)]
+            } else {
+                &[best]
+            };
+            let moves: Vec<String> = format_moves(board, line, mode);
+            match moves.as_slice() {
+                [best, reply] => println!("bestmove {} ponder {}", best, reply),
+                _ => println!("bestmove {}", moves[0]),
+            }
+        }
+        None => {
+            println!("info depth 0 score {}", format_score(result.score));
+            println!("bestmove 0000");
+        }
+    }
+}
+
+/// Format a score as `cp <centipawns>` or `mate <moves>`, negative if the engine gets mated.
+fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+/// Format a line of moves from `board` in UCI notation.
+fn format_line(board: &Board, line: &[Move], mode: CastlingMode) -> String {
+    format_moves(board, line, mode).join(" ")
+}
+
+fn format_moves(board: &Board, line: &[Move], mode: CastlingMode) -> Vec<String> {
+    let mut board: Board = board.clone();
+    let mut moves: Vec<String> = Vec::with_capacity(line.len());
+    for m in line {
+        moves.push(m.to_uci_with_mode(&board, mode));
+        board.make_move(*m);
+    }
+    moves
+}
+
+/// Run perft divide for `<depth> [fen]`, printing the node count of every root move.
+fn perft(args: &[String]) -> chessify::Result<()> {
+    let depth: usize = args.first().ok_or("missing depth")?.parse()?;
+    let fen: String = if args.len() > 1 {
+        args[1..].join(" ")
+    } else {
+        DEFAULT_BOARD_FEN.to_string()
+    };
+    let board: Board = Board::try_from_fen(&fen)?;
+
+    let start: Instant = Instant::now();
+    let divide = board.perft_divide(depth);
+    let nodes: u64 = if depth == 0 {
+        1
+    } else {
+        divide.iter().map(|(_, n)| n).sum()
+    };
+    let elapsed = start.elapsed();
+
+    for (m, n) in &divide {
+        println!("{}: {}", m, n);
+    }
+    println!();
+    println!("Nodes searched: {}", nodes);
+    println!(
+        "Time: {} ms ({:.0} nps)",
+        elapsed.as_millis(),
+        nodes as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+    Ok(())
+}
+
+/// Tune the evaluation to a data set, see [`Tuner`], writing the weights as Rust source if the
+/// output file ends in `.rs` and as a weights file otherwise.
+fn tune(args: &[String]) -> chessify::Result<()> {
+    let dataset: &String = args.first().ok_or("missing dataset")?;
+    let output: &String = args.get(1).ok_or("missing output file")?;
+    let steps: usize = match args.get(2) {
+        Some(steps) => steps.parse()?,
+        None => DEFAULT_TUNING_STEPS,
+    };
+
+    let positions: Vec<TrainingPosition> =
+        read_training_positions(BufReader::new(File::open(dataset)?))?;
+    println!("Positions: {}", positions.len());
+    let mut tuner: Tuner = Tuner::new(&EvalParams::default(), &positions, TunerConfig::default());
+    println!("Scale: {:.4}", tuner.scale());
+    tuner.tune(steps, |step, error| {
+        if step == 1 || step % 100 == 0 {
+            println!("Step {}: error {:.6}", step, error);
+        }
+    });
+    println!("Final error: {:.6}", tuner.error());
+
+    let params: EvalParams = tuner.params();
+    let mut writer: BufWriter<File> = BufWriter::new(File::create(output)?);
+    if output.ends_with(".rs") {
+        writer.write_all(params.to_rust_source("TUNED_EVAL_PARAMS").as_bytes())?;
+    } else {
+        params.write_weights(&mut writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build a Polyglot opening book from the games of a PGN file, see [`BookBuilder`].
+fn book(args: &[String]) -> chessify::Result<()> {
+    let pgn: &String = args.first().ok_or("missing PGN file")?;
+    let output: &String = args.get(1).ok_or("missing output file")?;
+    let mut config: BookConfig = BookConfig::default();
+    if let Some(max_ply) = args.get(2) {
+        config.max_ply = max_ply.parse()?;
+    }
 
-    let bb = Board::from_fen("r1bqk2r/ppp2ppp/2n2n2/2bpP3/2Bp4/5N2/PPP2PPP/RNBQKR2 w Qkq d6 0 7");
-    println!("{}", bb);
-    println!("{:?}", bb);
+    let mut builder: BookBuilder = BookBuilder::new(config);
+    let games: usize = builder.add_pgn(BufReader::new(File::open(pgn)?))?;
+    println!("Games: {}", games);
+    let book: PolyglotBook = builder.build();
+    println!("Entries: {}", book.len());
+    book.write(File::create(output)?)
 }
@@ -0,0 +1,416 @@
+use crate::attacks::{
+    between, bishop_attacks, king_attacks, knight_attacks, line, pawn_attacks, queen_attacks,
+    rook_attacks,
+};
+use crate::bitboard::{Bitboard, EMPTY, FULL, RANK_1, RANK_3, RANK_6, RANK_8};
+use crate::board::Board;
+use crate::castling_rights::CastlingSide;
+use crate::chess_move::{Move, MoveFlag};
+use crate::color::Color;
+use crate::piece::Piece;
+use crate::square::Square;
+
+const PROMOTION_PIECES: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+
+/// Precomputed bitboards of a position that the generator needs repeatedly.
+struct Context {
+    us: Color,
+    them: Color,
+    own: Bitboard,
+    enemy: Bitboard,
+    occ: Bitboard,
+}
+
+impl Context {
+    fn new(board: &Board) -> Self {
+        let us: Color = board.side_to_move();
+        let them: Color = !us;
+        let own: Bitboard = board.color_bitboard(us);
+        let enemy: Bitboard = board.color_bitboard(them);
+
+        Context {
+            us,
+            them,
+            own,
+            enemy,
+            occ: own | enemy,
+        }
+    }
+}
+
+/// The squares a piece on `from` may move to without exposing its king.
+fn pin_mask(pinned: Bitboard, ksq: Square, from: Square) -> Bitboard {
+    if pinned.contains(from) {
+        line(ksq, from)
+    } else {
+        FULL
+    }
+}
+
+fn push_moves(moves: &mut Vec<Move>, from: Square, targets: Bitboard, enemy: Bitboard) {
+    for to in targets {
+        let flag: MoveFlag = if enemy.contains(to) {
+            MoveFlag::Capture
+        } else {
+            MoveFlag::Quiet
+        };
+        moves.push(Move::new(from, to, flag));
+    }
+}
+
+fn push_pawn_moves(
+    moves: &mut Vec<Move>,
+    from: Square,
+    to: Square,
+    capture: bool,
+    promotion: bool,
+) {
+    if promotion {
+        for piece in PROMOTION_PIECES {
+            moves.push(Move::new(from, to, MoveFlag::promotion(piece, capture)));
+        }
+    } else {
+        let flag: MoveFlag = if capture {
+            MoveFlag::Capture
+        } else {
+            MoveFlag::Quiet
+        };
+        moves.push(Move::new(from, to, flag));
+    }
+}
+
+fn generate_pawn_moves(
+    board: &Board,
+    ctx: &Context,
+    moves: &mut Vec<Move>,
+    ksq: Square,
+    pinned: Bitboard,
+    target: Bitboard,
+) {
+    let (promotion_rank, double_push_rank) = match ctx.us {
+        Color::White => (RANK_8, RANK_3),
+        Color::Black => (RANK_1, RANK_6),
+    };
+    let forward = |b: Bitboard| match ctx.us {
+        Color::White => b.north(),
+        Color::Black => b.south(),
+    };
+
+    for from in board.piece_bitboard(Piece::Pawn, ctx.us) {
+        let pin_mask: Bitboard = pin_mask(pinned, ksq, from);
+
+        let single: Bitboard = forward(Bitboard::from_square(from)) & !ctx.occ;
+        let double: Bitboard = forward(single & double_push_rank) & !ctx.occ;
+        let captures: Bitboard = pawn_attacks(ctx.us, from) & ctx.enemy;
+
+        for to in (single | captures) & target & pin_mask {
+            push_pawn_moves(
+                moves,
+                from,
+                to,
+                ctx.enemy.contains(to),
+                promotion_rank.contains(to),
+            );
+        }
+
+        if let Some(to) = (double & target & pin_mask).lsb() {
+            moves.push(Move::new(from, to, MoveFlag::DoublePawnPush));
+        }
+
+        if let Some(ep) = board.en_passante_square() {
+            if pawn_attacks(ctx.us, from).contains(ep)
+                && en_passant_is_legal(board, ctx, ksq, from, ep)
+            {
+                moves.push(Move::new(from, ep, MoveFlag::EnPassant));
+            }
+        }
+    }
+}
+
+/// En passant removes two pieces from a single rank, so the safest check is to play it out
+/// on the occupancy and look for any attack on the king.
+fn en_passant_is_legal(
+    board: &Board,
+    ctx: &Context,
+    ksq: Square,
+    from: Square,
+    ep: Square,
+) -> bool {
+    let captured: Bitboard = match ctx.us {
+        Color::White => Bitboard::from_square(Square(ep.0 + 8)),
+        Color::Black => Bitboard::from_square(Square(ep.0 - 8)),
+    };
+    if board.piece_bitboard(Piece::Pawn, ctx.them) & captured == EMPTY {
+        return false;
+    }
+
+    let occ: Bitboard =
+        (ctx.occ ^ Bitboard::from_square(from) ^ captured) | Bitboard::from_square(ep);
+    board.attackers_by(ksq, ctx.them, occ) & !captured == EMPTY
+}
+
+fn generate_piece_moves(
+    board: &Board,
+    ctx: &Context,
+    moves: &mut Vec<Move>,
+    ksq: Square,
+    pinned: Bitboard,
+    target: Bitboard,
+) {
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for from in board.piece_bitboard(piece, ctx.us) {
+            let attacks: Bitboard = match piece {
+                Piece::Knight => knight_attacks(from),
+                Piece::Bishop => bishop_attacks(from, ctx.occ),
+                Piece::Rook => rook_attacks(from, ctx.occ),
+                _ => queen_attacks(from, ctx.occ),
+            };
+
+            let targets: Bitboard = attacks & !ctx.own & target & pin_mask(pinned, ksq, from);
+            push_moves(moves, from, targets, ctx.enemy);
+        }
+    }
+}
+
+fn generate_king_moves(board: &Board, ctx: &Context, moves: &mut Vec<Move>, ksq: Square) {
+    // The king must not be able to hide behind itself from a slider attack.
+    let occ: Bitboard = ctx.occ ^ Bitboard::from_square(ksq);
+
+    for to in king_attacks(ksq) & !ctx.own {
+        if board.attackers_by(to, ctx.them, occ) == EMPTY {
+            push_moves(moves, ksq, Bitboard::from_square(to), ctx.enemy);
+        }
+    }
+}
+
+fn generate_castling_moves(board: &Board, ctx: &Context, moves: &mut Vec<Move>, ksq: Square) {
+    let back_rank: u8 = match ctx.us {
+        Color::White => 56,
+        Color::Black => 0,
+    };
+    if ksq.0 / 8 * 8 != back_rank {
+        return;
+    }
+
+    let rooks: Bitboard = board.piece_bitboard(Piece::Rook, ctx.us);
+    let king: Bitboard = Bitboard::from_square(ksq);
+
+    // (side, king destination file, rook destination file, flag)
+    let candidates = [
+        (CastlingSide::Kingside, 6, 5, MoveFlag::KingCastle),
+        (CastlingSide::Queenside, 2, 3, MoveFlag::QueenCastle),
+    ];
+
+    for (side, king_to, rook_to, flag) in candidates {
+        let rook: Square = match board.castling_rights().rook_file(ctx.us, side) {
+            Some(f) => Square(back_rank + f.0),
+            None => continue,
+        };
+        let rook_bb: Bitboard = Bitboard::from_square(rook);
+        if !rooks.contains(rook) {
+            continue;
+        }
+
+        // Apart from king and rook themselves, everything between their origins and
+        // destinations has to be empty. In standard chess this is the span between the two.
+        let (to, rook_to) = (Square(back_rank + king_to), Square(back_rank + rook_to));
+        let span: Bitboard = between(ksq, to)
+            | Bitboard::from_square(to)
+            | between(rook, rook_to)
+            | Bitboard::from_square(rook_to);
+        if span & ctx.occ & !king & !rook_bb != EMPTY {
+            continue;
+        }
+
+        // The king may neither pass through nor land on an attacked square. The rook no longer
+        // shields anything once it has moved.
+        let occ: Bitboard = ctx.occ ^ rook_bb;
+        let path: Bitboard = between(ksq, to) | Bitboard::from_square(to);
+        if path
+            .squares()
+            .all(|s| board.attackers_by(s, ctx.them, occ).is_empty())
+        {
+            moves.push(Move::new(ksq, to, flag));
+        }
+    }
+}
+
+impl Board {
+    /// Generate every legal move for the side to move.
+    ///
+    /// This includes castling (as far as the [`CastlingRights`](crate::CastlingRights) allow it),
+    /// en passant captures, all four promotions and only those moves that get the king out of check.
+    /// A position without a king for the side to move has no legal moves.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::with_capacity(64);
+        let ctx: Context = Context::new(self);
+
+        let ksq: Square = match self.king_square(ctx.us) {
+            Some(s) => s,
+            None => return moves,
+        };
+
+        let checkers: Bitboard = self.checkers();
+        generate_king_moves(self, &ctx, &mut moves, ksq);
+
+        // In double check only the king can move.
+        if checkers.more_than_one() {
+            return moves;
+        }
+
+        // When in check, every other move has to capture the checker or block the check.
+        let target: Bitboard = match checkers.lsb() {
+            Some(checker) => checkers | between(ksq, checker),
+            None => {
+                generate_castling_moves(self, &ctx, &mut moves, ksq);
+                FULL
+            }
+        };
+
+        let pinned: Bitboard = self.pinned(ctx.us);
+        generate_pawn_moves(self, &ctx, &mut moves, ksq, pinned, target);
+        generate_piece_moves(self, &ctx, &mut moves, ksq, pinned, target);
+
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(moves: &[Move], uci: &str) -> Option<Move> {
+        moves.iter().copied().find(|m| m.to_string() == uci)
+    }
+
+    fn bitboard_of(moves: &[Move]) -> Bitboard {
+        Bitboard(
+            moves
+                .iter()
+                .fold(0, |acc, m| acc | (1u64 << m.to().index())),
+        )
+    }
+
+    #[test]
+    fn legal_move_counts() {
+        let positions: [(&str, usize); 6] = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                20,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                48,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 14),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                6,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                44,
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                46,
+            ),
+        ];
+
+        for (fen, count) in positions {
+            assert_eq!(count, Board::from_fen(fen).legal_moves().len(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn castling() {
+        let b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let moves = b.legal_moves();
+        assert_eq!(
+            Some(MoveFlag::KingCastle),
+            find(&moves, "e1g1").map(|m| m.flag())
+        );
+        assert_eq!(
+            Some(MoveFlag::QueenCastle),
+            find(&moves, "e1c1").map(|m| m.flag())
+        );
+
+        // The king may not pass through the attacked f1 square.
+        let b = Board::from_fen("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1");
+        let moves = b.legal_moves();
+        assert!(find(&moves, "e1g1").is_none());
+        assert!(find(&moves, "e1c1").is_some());
+
+        // Without the rights there is no castling at all.
+        let b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1");
+        assert!(b.legal_moves().iter().all(|m| !m.is_castle()));
+    }
+
+    #[test]
+    fn en_passant() {
+        let b = Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3");
+        let m = find(&b.legal_moves(), "e5f6").unwrap();
+        assert!(m.is_en_passant());
+
+        // Capturing en passant would expose the king along the fifth rank.
+        let b = Board::from_fen("8/8/8/KPp4r/8/8/8/7k w - c6 0 1");
+        assert!(find(&b.legal_moves(), "b5c6").is_none());
+    }
+
+    #[test]
+    fn promotions() {
+        let b = Board::from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1");
+        let moves = b.legal_moves();
+        let promotions: Vec<&Move> = moves.iter().filter(|m| m.is_promotion()).collect();
+
+        assert_eq!(8, promotions.len());
+        assert_eq!(4, promotions.iter().filter(|m| m.is_capture()).count());
+    }
+
+    #[test]
+    fn check_evasion() {
+        // Only king moves, the interposition on f2 and capturing the queen resolve the check.
+        let b = Board::from_fen("4k3/8/8/8/7q/8/3P2N1/4K3 w - - 0 1");
+        assert!(b.is_check());
+        for m in b.legal_moves() {
+            let resolves: bool = m.from() == Square::from_str("e1")
+                || m.to() == Square::from_str("f2")
+                || m.to() == Square::from_str("h4");
+            assert!(resolves, "{}", m);
+        }
+
+        // Double check: the king has to move.
+        let b = Board::from_fen("4k3/8/8/8/1b6/8/4r3/R3K3 w - - 0 1");
+        assert!(b
+            .legal_moves()
+            .iter()
+            .all(|m| m.from() == Square::from_str("e1")));
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        // The knight on e2 is pinned by the rook and can not move at all.
+        let b = Board::from_fen("4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert!(b
+            .legal_moves()
+            .iter()
+            .all(|m| m.from() != Square::from_str("e2")));
+
+        // The rook on e2 is pinned, but may move along the pin.
+        let b = Board::from_fen("4r2k/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        let rook_moves: Vec<Move> = b
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.from() == Square::from_str("e2"))
+            .collect();
+        assert_eq!(6, rook_moves.len());
+        assert_eq!(Bitboard(0x1010_1010_1010), bitboard_of(&rook_moves));
+    }
+
+    #[test]
+    fn checkmate_has_no_moves() {
+        let b = Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert!(b.is_check());
+        assert!(b.legal_moves().is_empty());
+    }
+}
@@ -0,0 +1,951 @@
+use crate::board::Board;
+use crate::color::{Color, COLORS, NUM_COLORS};
+use crate::error::{ChessifyError, Result};
+use crate::piece::{ColoredPiece, Piece};
+use crate::search::Evaluator;
+use crate::square::Square;
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"CNUE";
+const VERSION: u32 = 1;
+
+/// The largest accumulator a network file may ask for.
+pub const MAX_ACCUMULATOR_SIZE: usize = 4096;
+
+/// The largest hidden layer a network file may ask for.
+pub const MAX_HIDDEN_SIZE: usize = 1024;
+
+/// The largest value passed on between layers.
+const ACTIVATION_MAX: i32 = 127;
+
+/// How many bits the sums of the hidden layer are shifted right.
+const HIDDEN_SHIFT: u32 = 6;
+
+/// What the output of the network is divided by to get centipawns.
+const OUTPUT_SCALE: i32 = 16;
+
+/// Exhaustive enum of the ways a network file can be invalid.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum NetworkErrorKind {
+    /// The file does not start with the magic bytes of a network.
+    InvalidMagic,
+    /// The file is written in a format version that is not supported.
+    UnsupportedVersion(u32),
+    /// The file uses a feature set that does not exist.
+    UnknownFeatureSet(u32),
+    /// The layer sizes are zero or too large.
+    InvalidSize { accumulator: u32, hidden: u32 },
+    /// There is more data after the output layer.
+    TrailingData,
+}
+
+impl fmt::Display for NetworkErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkErrorKind::InvalidMagic => write!(f, "not a network file"),
+            NetworkErrorKind::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            NetworkErrorKind::UnknownFeatureSet(n) => write!(f, "unknown feature set {}", n),
+            NetworkErrorKind::InvalidSize {
+                accumulator,
+                hidden,
+            } => write!(
+                f,
+                "invalid layer sizes {} and {}, which range from 1 to {} and {}",
+                accumulator, hidden, MAX_ACCUMULATOR_SIZE, MAX_HIDDEN_SIZE
+            ),
+            NetworkErrorKind::TrailingData => write!(f, "unexpected data after the network"),
+        }
+    }
+}
+
+fn network_error(kind: NetworkErrorKind) -> Box<dyn Error> {
+    Box::new(ChessifyError::InvalidNetwork(kind))
+}
+
+/// Exhaustive enum of the ways the pieces are turned into input features.
+///
+/// Both feature sets see the board from the point of view of one player, with the board
+/// mirrored vertically for black, and have a feature for every square of that player's
+/// king, piece, color relative to the player and square of the piece.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FeatureSet {
+    /// The kings are left out of the pieces, giving 64 * 10 * 64 features.
+    HalfKP,
+    /// The kings are counted among the pieces, giving 64 * 12 * 64 features.
+    HalfKA,
+}
+
+impl FeatureSet {
+    /// Get the number of input features of a network using this feature set.
+    pub fn num_features(&self) -> usize {
+        64 * self.num_pieces() * 2 * 64
+    }
+
+    /// Get the index of the feature for `cp` standing on `s`, seen by `perspective` whose
+    /// king stands on `king`, or `None` if the piece is not a feature.
+    pub fn index(
+        &self,
+        perspective: Color,
+        king: Square,
+        cp: ColoredPiece,
+        s: Square,
+    ) -> Option<usize> {
+        let piece: usize = cp.piece.as_index();
+        if piece >= self.num_pieces() {
+            return None;
+        }
+        let orient = |s: Square| -> usize {
+            match perspective {
+                Color::White => s.index(),
+                Color::Black => s.index() ^ 56,
+            }
+        };
+        let piece: usize = piece * 2 + (cp.color != perspective) as usize;
+        Some((orient(king) * self.num_pieces() * 2 + piece) * 64 + orient(s))
+    }
+
+    fn num_pieces(&self) -> usize {
+        match self {
+            FeatureSet::HalfKP => 5,
+            FeatureSet::HalfKA => 6,
+        }
+    }
+}
+
+/// A quantized efficiently updatable neural network (NNUE), loaded from a file.
+///
+/// The first layer, the feature transformer, sums the weights of the active features of a
+/// [`FeatureSet`] into an accumulator per player. A move only switches a handful of features
+/// on and off, so [`NnueEvaluator`] updates the accumulators incrementally during a search.
+///
+/// All weights are quantized:
+/// 1. The feature transformer has `i16` weights and biases, and its accumulators are
+///    clipped to `0..=127`. The accumulator of the side to move comes first.
+/// 2. The hidden layer has `i8` weights and `i32` biases. Its sums are shifted right by 6
+///    bits and clipped to `0..=127`.
+/// 3. The output layer has `i8` weights and an `i32` bias. Its sum divided by 16 is the
+///    score in centipawns from the point of view of the side to move.
+///
+/// A network is immutable, so one [`Arc`] can be shared by the evaluators of many threads.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Network {
+    feature_set: FeatureSet,
+    accumulator_size: usize,
+    hidden_size: usize,
+    feature_biases: Vec<i16>,
+    /// Indexed by feature and then accumulator neuron.
+    feature_weights: Vec<i16>,
+    hidden_biases: Vec<i32>,
+    /// Indexed by hidden neuron and then input, the side to move coming first.
+    hidden_weights: Vec<i8>,
+    output_bias: i32,
+    output_weights: Vec<i8>,
+}
+
+impl Network {
+    /// Load a network from the file at `path`.
+    ///
+    /// # Errors
+    /// Returns the I/O error if the file can not be read, including an unexpected end of
+    /// file, and a [`ChessifyError::InvalidNetwork`] error if its content is not valid.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Network> {
+        Network::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a network from `reader`, which must end right after it.
+    ///
+    /// A network file holds the following, all integers being little-endian:
+    /// - the magic bytes `CNUE` and the format version 1 as a `u32`,
+    /// - the [`FeatureSet`] as a `u32`, 0 for HalfKP and 1 for HalfKA,
+    /// - the accumulator size and the hidden layer size as `u32`s,
+    /// - the feature transformer biases and then its weights, feature by feature,
+    /// - the hidden layer biases and then its weights, neuron by neuron,
+    /// - the output bias and the output weights.
+    ///
+    /// # Errors
+    /// Same as [`Network::load()`].
+    pub fn read<R: Read>(mut reader: R) -> Result<Network> {
+        let mut magic: [u8; 4] = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(network_error(NetworkErrorKind::InvalidMagic));
+        }
+        let version: u32 = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(network_error(NetworkErrorKind::UnsupportedVersion(version)));
+        }
+        let feature_set: FeatureSet = match read_u32(&mut reader)? {
+            0 => FeatureSet::HalfKP,
+            1 => FeatureSet::HalfKA,
+            n => return Err(network_error(NetworkErrorKind::UnknownFeatureSet(n))),
+        };
+        let accumulator: u32 = read_u32(&mut reader)?;
+        let hidden: u32 = read_u32(&mut reader)?;
+        let (accumulator_size, hidden_size) = (accumulator as usize, hidden as usize);
+        if !(1..=MAX_ACCUMULATOR_SIZE).contains(&accumulator_size)
+            || !(1..=MAX_HIDDEN_SIZE).contains(&hidden_size)
+        {
+            return Err(network_error(NetworkErrorKind::InvalidSize {
+                accumulator,
+                hidden,
+            }));
+        }
+
+        let feature_inputs: usize = feature_set.num_features();
+        let network = Network {
+            feature_set,
+            accumulator_size,
+            hidden_size,
+            feature_biases: read_i16s(&mut reader, accumulator_size)?,
+            feature_weights: read_i16s(&mut reader, feature_inputs * accumulator_size)?,
+            hidden_biases: read_i32s(&mut reader, hidden_size)?,
+            hidden_weights: read_i8s(&mut reader, hidden_size * 2 * accumulator_size)?,
+            output_bias: read_u32(&mut reader)? as i32,
+            output_weights: read_i8s(&mut reader, hidden_size)?,
+        };
+        if reader.read(&mut [0])? > 0 {
+            return Err(network_error(NetworkErrorKind::TrailingData));
+        }
+        Ok(network)
+    }
+
+    /// Write the network to `writer` in the format [`Network::read()`] expects.
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(MAGIC)?;
+        let feature_set: u32 = match self.feature_set {
+            FeatureSet::HalfKP => 0,
+            FeatureSet::HalfKA => 1,
+        };
+        for n in [
+            VERSION,
+            feature_set,
+            self.accumulator_size as u32,
+            self.hidden_size as u32,
+        ] {
+            writer.write_all(&n.to_le_bytes())?;
+        }
+        for w in self.feature_biases.iter().chain(&self.feature_weights) {
+            writer.write_all(&w.to_le_bytes())?;
+        }
+        for b in &self.hidden_biases {
+            writer.write_all(&b.to_le_bytes())?;
+        }
+        for w in &self.hidden_weights {
+            writer.write_all(&w.to_le_bytes())?;
+        }
+        writer.write_all(&self.output_bias.to_le_bytes())?;
+        for w in &self.output_weights {
+            writer.write_all(&w.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Get the feature set of the network.
+    pub fn feature_set(&self) -> FeatureSet {
+        self.feature_set
+    }
+
+    /// Get the number of neurons in the accumulator of each player.
+    pub fn accumulator_size(&self) -> usize {
+        self.accumulator_size
+    }
+
+    /// Get the number of neurons in the hidden layer.
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    /// Score `board` in centipawns from the point of view of the side to move, computing
+    /// the accumulators from scratch.
+    ///
+    /// Use an [`NnueEvaluator`] to evaluate many related positions.
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        let mut accumulator: Accumulator = Accumulator::new(self);
+        accumulator.refresh(self, board);
+        let mut buffers: Buffers = Buffers::new(self);
+        self.propagate(&accumulator, board.side_to_move(), &mut buffers)
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.accumulator_size..][..self.accumulator_size]
+    }
+
+    /// Run the layers after the feature transformer.
+    fn propagate(&self, accumulator: &Accumulator, us: Color, buffers: &mut Buffers) -> i32 {
+        let (ours, theirs) = buffers.input.split_at_mut(self.accumulator_size);
+        simd::clipped_relu(&accumulator.values[us.as_index()], ours);
+        simd::clipped_relu(&accumulator.values[(!us).as_index()], theirs);
+
+        let rows = self.hidden_weights.chunks_exact(2 * self.accumulator_size);
+        for ((h, bias), row) in buffers.hidden.iter_mut().zip(&self.hidden_biases).zip(rows) {
+            let sum: i32 = bias + simd::dot(&buffers.input, row);
+            *h = (sum >> HIDDEN_SHIFT).clamp(0, ACTIVATION_MAX) as u8;
+        }
+        (self.output_bias + simd::dot(&buffers.hidden, &self.output_weights)) / OUTPUT_SCALE
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes: [u8; 4] = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    // Grow the buffer with the data actually read, so that a header claiming huge layers
+    // can not make us allocate more than the file holds.
+    let mut bytes: Vec<u8> = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(Box::new(io::Error::from(io::ErrorKind::UnexpectedEof)));
+    }
+    Ok(bytes)
+}
+
+fn read_i8s<R: Read>(reader: &mut R, n: usize) -> Result<Vec<i8>> {
+    Ok(read_bytes(reader, n)?
+        .into_iter()
+        .map(|b| b as i8)
+        .collect())
+}
+
+fn read_i16s<R: Read>(reader: &mut R, n: usize) -> Result<Vec<i16>> {
+    let bytes: Vec<u8> = read_bytes(reader, 2 * n)?;
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+fn read_i32s<R: Read>(reader: &mut R, n: usize) -> Result<Vec<i32>> {
+    let bytes: Vec<u8> = read_bytes(reader, 4 * n)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// The feature transformer output of both players for one position, along with the pieces
+/// it was computed from.
+#[derive(Clone, Debug)]
+struct Accumulator {
+    /// Indexed by [`Color::as_index()`] of the player whose features were summed.
+    values: [Vec<i16>; NUM_COLORS],
+    pieces: [Option<ColoredPiece>; 64],
+}
+
+impl Accumulator {
+    fn new(network: &Network) -> Self {
+        Accumulator {
+            values: [
+                network.feature_biases.clone(),
+                network.feature_biases.clone(),
+            ],
+            pieces: [None; 64],
+        }
+    }
+
+    /// Compute both players' accumulators from scratch.
+    fn refresh(&mut self, network: &Network, board: &Board) {
+        for c in COLORS {
+            self.refresh_side(network, board, c);
+        }
+        self.pieces = *board.pieces();
+    }
+
+    fn refresh_side(&mut self, network: &Network, board: &Board, perspective: Color) {
+        let values: &mut Vec<i16> = &mut self.values[perspective.as_index()];
+        values.copy_from_slice(&network.feature_biases);
+        let king: Square = king_square(board, perspective);
+        for (i, cp) in board.pieces().iter().enumerate() {
+            let feature = cp.and_then(|cp| {
+                network
+                    .feature_set
+                    .index(perspective, king, cp, Square::from_index(i))
+            });
+            if let Some(f) = feature {
+                simd::add(values, network.weights(f));
+            }
+        }
+    }
+
+    /// Copy `parent` and update it to the pieces of `board`, refreshing a player only when
+    /// their king has moved.
+    fn update(&mut self, parent: &Accumulator, network: &Network, board: &Board) {
+        // A move changes at most four squares, when castling.
+        let mut changes: [(usize, Option<ColoredPiece>, Option<ColoredPiece>); 4] =
+            [(0, None, None); 4];
+        let mut count: usize = 0;
+        for (i, (old, new)) in parent.pieces.iter().zip(board.pieces()).enumerate() {
+            if old != new {
+                if count == changes.len() {
+                    self.refresh(network, board);
+                    return;
+                }
+                changes[count] = (i, *old, *new);
+                count += 1;
+            }
+        }
+
+        for c in COLORS {
+            let king_moved: bool = changes[..count].iter().any(|(_, old, new)| {
+                [old, new]
+                    .iter()
+                    .any(|cp| **cp == Some(ColoredPiece::new(Piece::King, c)))
+            });
+            if king_moved {
+                self.refresh_side(network, board, c);
+                continue;
+            }
+
+            let values: &mut Vec<i16> = &mut self.values[c.as_index()];
+            values.copy_from_slice(&parent.values[c.as_index()]);
+            let king: Square = king_square(board, c);
+            let index = |cp: Option<ColoredPiece>, i: usize| -> Option<usize> {
+                network
+                    .feature_set
+                    .index(c, king, cp?, Square::from_index(i))
+            };
+            for (i, old, new) in changes[..count].iter().copied() {
+                if let Some(f) = index(old, i) {
+                    simd::sub(values, network.weights(f));
+                }
+                if let Some(f) = index(new, i) {
+                    simd::add(values, network.weights(f));
+                }
+            }
+        }
+        self.pieces = *board.pieces();
+    }
+}
+
+/// A position without a king of `c` has its features computed as if the king stood on a8.
+fn king_square(board: &Board, c: Color) -> Square {
+    board.king_square(c).unwrap_or(Square::from_index(0))
+}
+
+/// The activations of the layers after the feature transformer.
+#[derive(Clone, Debug)]
+struct Buffers {
+    input: Vec<u8>,
+    hidden: Vec<u8>,
+}
+
+impl Buffers {
+    fn new(network: &Network) -> Self {
+        Buffers {
+            input: vec![0; 2 * network.accumulator_size],
+            hidden: vec![0; network.hidden_size],
+        }
+    }
+}
+
+/// An [`Evaluator`] using a [`Network`], which keeps a stack of accumulators in step with
+/// the moves the search makes and unmakes.
+///
+/// Positions that do not follow from the previous one through [`Evaluator::make_move()`]
+/// are still evaluated correctly, by computing their accumulators from scratch.
+#[derive(Clone, Debug)]
+pub struct NnueEvaluator {
+    network: Arc<Network>,
+    /// Grows to the deepest ply reached, the entries above `ply` being reused.
+    stack: Vec<Accumulator>,
+    ply: usize,
+    buffers: Buffers,
+}
+
+impl NnueEvaluator {
+    /// Create a new [`NnueEvaluator`] using `network`.
+    pub fn new(network: Arc<Network>) -> Self {
+        NnueEvaluator {
+            stack: vec![Accumulator::new(&network)],
+            ply: 0,
+            buffers: Buffers::new(&network),
+            network,
+        }
+    }
+
+    /// Get the network.
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let accumulator: &mut Accumulator = &mut self.stack[self.ply];
+        if accumulator.pieces != *board.pieces() {
+            accumulator.refresh(&self.network, board);
+        }
+        self.network
+            .propagate(accumulator, board.side_to_move(), &mut self.buffers)
+    }
+
+    fn reset(&mut self, board: &Board) {
+        self.ply = 0;
+        self.stack[0].refresh(&self.network, board);
+    }
+
+    fn make_move(&mut self, board: &Board) {
+        self.ply += 1;
+        if self.ply == self.stack.len() {
+            self.stack.push(Accumulator::new(&self.network));
+        }
+        let (parents, children) = self.stack.split_at_mut(self.ply);
+        children[0].update(&parents[self.ply - 1], &self.network, board);
+    }
+
+    fn unmake_move(&mut self) {
+        self.ply = self.ply.saturating_sub(1);
+    }
+}
+
+/// Vector arithmetic on the layers, using SIMD instructions when a `nnue-avx2` or `nnue-sse`
+/// feature is enabled and the CPU supports them.
+///
+/// Every path gives exactly the same results, as long as the inputs of [`dot()`] are at
+/// most 127.
+mod simd {
+    /// Add `weights` to `values` element by element, wrapping around on overflow.
+    pub fn add(values: &mut [i16], weights: &[i16]) {
+        #[cfg(all(feature = "nnue-avx2", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: The CPU supports AVX2.
+            return unsafe { avx2::add(values, weights) };
+        }
+        #[cfg(all(feature = "nnue-sse", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: The CPU supports SSSE3.
+            return unsafe { sse::add(values, weights) };
+        }
+        scalar::add(values, weights)
+    }
+
+    /// Subtract `weights` from `values` element by element, wrapping around on overflow.
+    pub fn sub(values: &mut [i16], weights: &[i16]) {
+        #[cfg(all(feature = "nnue-avx2", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: The CPU supports AVX2.
+            return unsafe { avx2::sub(values, weights) };
+        }
+        #[cfg(all(feature = "nnue-sse", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: The CPU supports SSSE3.
+            return unsafe { sse::sub(values, weights) };
+        }
+        scalar::sub(values, weights)
+    }
+
+    /// Compute the dot product of `inputs` and `weights`.
+    pub fn dot(inputs: &[u8], weights: &[i8]) -> i32 {
+        #[cfg(all(feature = "nnue-avx2", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: The CPU supports AVX2.
+            return unsafe { avx2::dot(inputs, weights) };
+        }
+        #[cfg(all(feature = "nnue-sse", target_arch = "x86_64"))]
+        if is_x86_feature_detected!("ssse3") {
+            // SAFETY: The CPU supports SSSE3.
+            return unsafe { sse::dot(inputs, weights) };
+        }
+        scalar::dot(inputs, weights)
+    }
+
+    /// Clip `values` to `0..=127` into `out`.
+    pub fn clipped_relu(values: &[i16], out: &mut [u8]) {
+        // Simple enough for the compiler to vectorize on its own.
+        for (o, v) in out.iter_mut().zip(values) {
+            *o = (*v).clamp(0, super::ACTIVATION_MAX as i16) as u8;
+        }
+    }
+
+    pub(super) mod scalar {
+        pub fn add(values: &mut [i16], weights: &[i16]) {
+            for (v, w) in values.iter_mut().zip(weights) {
+                *v = v.wrapping_add(*w);
+            }
+        }
+
+        pub fn sub(values: &mut [i16], weights: &[i16]) {
+            for (v, w) in values.iter_mut().zip(weights) {
+                *v = v.wrapping_sub(*w);
+            }
+        }
+
+        pub fn dot(inputs: &[u8], weights: &[i8]) -> i32 {
+            inputs
+                .iter()
+                .zip(weights)
+                .map(|(i, w)| *i as i32 * *w as i32)
+                .sum()
+        }
+    }
+
+    #[cfg(all(feature = "nnue-avx2", target_arch = "x86_64"))]
+    mod avx2 {
+        use super::scalar;
+
+        use std::arch::x86_64::*;
+
+        const LANES_I16: usize = 16;
+        const LANES_I8: usize = 32;
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn add(values: &mut [i16], weights: &[i16]) {
+            let n: usize = values.len().min(weights.len()) / LANES_I16 * LANES_I16;
+            for i in (0..n).step_by(LANES_I16) {
+                let v = values.as_mut_ptr().add(i) as *mut __m256i;
+                let w = weights.as_ptr().add(i) as *const __m256i;
+                _mm256_storeu_si256(
+                    v,
+                    _mm256_add_epi16(_mm256_loadu_si256(v), _mm256_loadu_si256(w)),
+                );
+            }
+            scalar::add(&mut values[n..], &weights[n..]);
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn sub(values: &mut [i16], weights: &[i16]) {
+            let n: usize = values.len().min(weights.len()) / LANES_I16 * LANES_I16;
+            for i in (0..n).step_by(LANES_I16) {
+                let v = values.as_mut_ptr().add(i) as *mut __m256i;
+                let w = weights.as_ptr().add(i) as *const __m256i;
+                _mm256_storeu_si256(
+                    v,
+                    _mm256_sub_epi16(_mm256_loadu_si256(v), _mm256_loadu_si256(w)),
+                );
+            }
+            scalar::sub(&mut values[n..], &weights[n..]);
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn dot(inputs: &[u8], weights: &[i8]) -> i32 {
+            let n: usize = inputs.len().min(weights.len()) / LANES_I8 * LANES_I8;
+            let ones = _mm256_set1_epi16(1);
+            let mut sum = _mm256_setzero_si256();
+            for i in (0..n).step_by(LANES_I8) {
+                let x = _mm256_loadu_si256(inputs.as_ptr().add(i) as *const __m256i);
+                let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+                // Pairs of products only saturate the 16 bits for inputs above 127.
+                let pairs = _mm256_maddubs_epi16(x, w);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(pairs, ones));
+            }
+            let sum = _mm_add_epi32(
+                _mm256_castsi256_si128(sum),
+                _mm256_extracti128_si256(sum, 1),
+            );
+            let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+            let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+            _mm_cvtsi128_si32(sum) + scalar::dot(&inputs[n..], &weights[n..])
+        }
+    }
+
+    #[cfg(all(feature = "nnue-sse", target_arch = "x86_64"))]
+    mod sse {
+        use super::scalar;
+
+        use std::arch::x86_64::*;
+
+        const LANES_I16: usize = 8;
+        const LANES_I8: usize = 16;
+
+        #[target_feature(enable = "ssse3")]
+        pub unsafe fn add(values: &mut [i16], weights: &[i16]) {
+            let n: usize = values.len().min(weights.len()) / LANES_I16 * LANES_I16;
+            for i in (0..n).step_by(LANES_I16) {
+                let v = values.as_mut_ptr().add(i) as *mut __m128i;
+                let w = weights.as_ptr().add(i) as *const __m128i;
+                _mm_storeu_si128(v, _mm_add_epi16(_mm_loadu_si128(v), _mm_loadu_si128(w)));
+            }
+            scalar::add(&mut values[n..], &weights[n..]);
+        }
+
+        #[target_feature(enable = "ssse3")]
+        pub unsafe fn sub(values: &mut [i16], weights: &[i16]) {
+            let n: usize = values.len().min(weights.len()) / LANES_I16 * LANES_I16;
+            for i in (0..n).step_by(LANES_I16) {
+                let v = values.as_mut_ptr().add(i) as *mut __m128i;
+                let w = weights.as_ptr().add(i) as *const __m128i;
+                _mm_storeu_si128(v, _mm_sub_epi16(_mm_loadu_si128(v), _mm_loadu_si128(w)));
+            }
+            scalar::sub(&mut values[n..], &weights[n..]);
+        }
+
+        #[target_feature(enable = "ssse3")]
+        pub unsafe fn dot(inputs: &[u8], weights: &[i8]) -> i32 {
+            let n: usize = inputs.len().min(weights.len()) / LANES_I8 * LANES_I8;
+            let ones = _mm_set1_epi16(1);
+            let mut sum = _mm_setzero_si128();
+            for i in (0..n).step_by(LANES_I8) {
+                let x = _mm_loadu_si128(inputs.as_ptr().add(i) as *const __m128i);
+                let w = _mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i);
+                // Pairs of products only saturate the 16 bits for inputs above 127.
+                let pairs = _mm_maddubs_epi16(x, w);
+                sum = _mm_add_epi32(sum, _mm_madd_epi16(pairs, ones));
+            }
+            let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+            let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+            _mm_cvtsi128_si32(sum) + scalar::dot(&inputs[n..], &weights[n..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchLimits, SearchResult, Searcher};
+
+    use std::io;
+
+    /// A network with pseudo-random weights, small enough to keep the layers in range.
+    fn random_network(feature_set: FeatureSet, mut seed: u64) -> Network {
+        let mut next = move |range: i32| -> i32 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % (2 * range as u64 + 1)) as i32 - range
+        };
+        let (accumulator_size, hidden_size): (usize, usize) = (40, 8);
+        Network {
+            feature_set,
+            accumulator_size,
+            hidden_size,
+            feature_biases: (0..accumulator_size).map(|_| next(64) as i16).collect(),
+            feature_weights: (0..feature_set.num_features() * accumulator_size)
+                .map(|_| next(32) as i16)
+                .collect(),
+            hidden_biases: (0..hidden_size).map(|_| next(1000)).collect(),
+            hidden_weights: (0..hidden_size * 2 * accumulator_size)
+                .map(|_| next(8) as i8)
+                .collect(),
+            output_bias: next(1000),
+            output_weights: (0..hidden_size).map(|_| next(127) as i8).collect(),
+        }
+    }
+
+    fn error_kind(e: Box<dyn Error>) -> NetworkErrorKind {
+        match *e.downcast::<ChessifyError>().unwrap() {
+            ChessifyError::InvalidNetwork(kind) => kind,
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn features() {
+        let e1: Square = Square::try_from("e1").unwrap();
+        let e8: Square = Square::try_from("e8").unwrap();
+        let white_pawn: ColoredPiece = ColoredPiece::new(Piece::Pawn, Color::White);
+        let black_pawn: ColoredPiece = ColoredPiece::new(Piece::Pawn, Color::Black);
+        let white_king: ColoredPiece = ColoredPiece::new(Piece::King, Color::White);
+        let e2: Square = Square::try_from("e2").unwrap();
+        let e7: Square = Square::try_from("e7").unwrap();
+
+        for feature_set in [FeatureSet::HalfKP, FeatureSet::HalfKA] {
+            // Both players see their own pieces the same way.
+            assert_eq!(
+                feature_set.index(Color::White, e1, white_pawn, e2),
+                feature_set.index(Color::Black, e8, black_pawn, e7)
+            );
+            assert_eq!(
+                feature_set.index(Color::White, e1, black_pawn, e7),
+                feature_set.index(Color::Black, e8, white_pawn, e2)
+            );
+            assert_ne!(
+                feature_set.index(Color::White, e1, white_pawn, e2),
+                feature_set.index(Color::White, e1, black_pawn, e2)
+            );
+        }
+        assert_eq!(
+            None,
+            FeatureSet::HalfKP.index(Color::White, e1, white_king, e1)
+        );
+        assert_eq!(40960, FeatureSet::HalfKP.num_features());
+
+        let h1: Square = Square::try_from("h1").unwrap();
+        let black_king: ColoredPiece = ColoredPiece::new(Piece::King, Color::Black);
+        assert_eq!(
+            Some(FeatureSet::HalfKA.num_features() - 1),
+            FeatureSet::HalfKA.index(Color::White, h1, black_king, h1)
+        );
+    }
+
+    #[test]
+    fn read_write() {
+        for feature_set in [FeatureSet::HalfKP, FeatureSet::HalfKA] {
+            let network: Network = random_network(feature_set, 1);
+            let mut bytes: Vec<u8> = Vec::new();
+            network.write(&mut bytes).unwrap();
+            assert_eq!(network, Network::read(&bytes[..]).unwrap());
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        random_network(FeatureSet::HalfKA, 1)
+            .write(&mut bytes)
+            .unwrap();
+        let with = |offset: usize, value: u32| -> Vec<u8> {
+            let mut b: Vec<u8> = bytes.clone();
+            b[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            b
+        };
+        let mut trailing: Vec<u8> = bytes.clone();
+        trailing.push(0);
+        for (b, kind) in [
+            (with(0, 0), NetworkErrorKind::InvalidMagic),
+            (with(4, 2), NetworkErrorKind::UnsupportedVersion(2)),
+            (with(8, 2), NetworkErrorKind::UnknownFeatureSet(2)),
+            (
+                with(12, 0),
+                NetworkErrorKind::InvalidSize {
+                    accumulator: 0,
+                    hidden: 8,
+                },
+            ),
+            (
+                with(16, 1025),
+                NetworkErrorKind::InvalidSize {
+                    accumulator: 40,
+                    hidden: 1025,
+                },
+            ),
+            (trailing, NetworkErrorKind::TrailingData),
+        ] {
+            assert_eq!(kind, error_kind(Network::read(&b[..]).unwrap_err()));
+        }
+
+        // A truncated file fails without first allocating the layers its header claims.
+        for b in [&bytes[..bytes.len() - 1], &with(12, 4096)[..20]] {
+            let e = Network::read(b).unwrap_err();
+            assert_eq!(
+                io::ErrorKind::UnexpectedEof,
+                e.downcast::<io::Error>().unwrap().kind()
+            );
+        }
+    }
+
+    #[test]
+    fn symmetry() {
+        let network: Network = random_network(FeatureSet::HalfKA, 2);
+        let board: Board =
+            Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let flipped: Board =
+            Board::from_fen("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4");
+        assert_eq!(network.evaluate(&board), network.evaluate(&flipped));
+        assert_ne!(
+            network.evaluate(&board),
+            network.evaluate(&Board::default())
+        );
+    }
+
+    /// Check the accumulators against ones computed from scratch in every position up to
+    /// `depth` plies deep.
+    fn walk(evaluator: &mut NnueEvaluator, board: &mut Board, depth: usize) {
+        let network: Arc<Network> = evaluator.network().clone();
+        let mut fresh: Accumulator = Accumulator::new(&network);
+        fresh.refresh(&network, board);
+        let top: &Accumulator = &evaluator.stack[evaluator.ply];
+        assert_eq!(fresh.pieces, top.pieces, "{}", board.to_fen());
+        assert_eq!(fresh.values, top.values, "{}", board.to_fen());
+        assert_eq!(network.evaluate(board), evaluator.evaluate(board));
+
+        if depth == 0 {
+            return;
+        }
+        for m in board.legal_moves() {
+            board.make_move(m);
+            evaluator.make_move(board);
+            walk(evaluator, board, depth - 1);
+            board.unmake_move(m);
+            evaluator.unmake_move();
+        }
+    }
+
+    #[test]
+    fn incremental() {
+        for feature_set in [FeatureSet::HalfKP, FeatureSet::HalfKA] {
+            let network: Arc<Network> = Arc::new(random_network(feature_set, 3));
+            let mut evaluator: NnueEvaluator = NnueEvaluator::new(network.clone());
+            for fen in [
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+                "4k3/8/8/2pP4/8/8/8/4K3 w - c6 0 2",
+            ] {
+                let mut board: Board = Board::from_fen(fen);
+                evaluator.reset(&board);
+                walk(&mut evaluator, &mut board, 2);
+            }
+
+            // Positions not reached through make_move are computed from scratch.
+            let board: Board = Board::default();
+            assert_eq!(network.evaluate(&board), evaluator.evaluate(&board));
+        }
+    }
+
+    /// Evaluates every position from scratch, ignoring the moves of the search.
+    struct Fresh(Arc<Network>);
+
+    impl Evaluator for Fresh {
+        fn evaluate(&mut self, board: &Board) -> i32 {
+            self.0.evaluate(board)
+        }
+    }
+
+    #[test]
+    fn search() {
+        // Random weights make for a wild quiescence search, so stay clear of captures.
+        let network: Arc<Network> = Arc::new(random_network(FeatureSet::HalfKP, 4));
+        let board: Board = Board::default();
+        let limits: SearchLimits = SearchLimits::depth(3);
+        let incremental: SearchResult =
+            Searcher::with_evaluator(NnueEvaluator::new(network.clone())).search(&board, &limits);
+        let fresh: SearchResult = Searcher::with_evaluator(Fresh(network)).search(&board, &limits);
+        assert_eq!(fresh.pv, incremental.pv);
+        assert_eq!(fresh.score, incremental.score);
+        assert_eq!(fresh.nodes, incremental.nodes);
+    }
+
+    #[test]
+    fn simd() {
+        let mut seed: u64 = 5;
+        let mut next = move || -> u64 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for len in [0, 1, 15, 16, 17, 33, 80, 257] {
+            let values: Vec<i16> = (0..len).map(|_| next() as i16).collect();
+            let weights: Vec<i16> = (0..len).map(|_| next() as i16).collect();
+            for (f, g) in [
+                (
+                    simd::add as fn(&mut [i16], &[i16]),
+                    simd::scalar::add as fn(&mut [i16], &[i16]),
+                ),
+                (simd::sub, simd::scalar::sub),
+            ] {
+                let (mut a, mut b) = (values.clone(), values.clone());
+                f(&mut a, &weights);
+                g(&mut b, &weights);
+                assert_eq!(a, b);
+            }
+
+            let inputs: Vec<u8> = (0..len).map(|_| (next() % 128) as u8).collect();
+            let weights: Vec<i8> = (0..len).map(|_| next() as i8).collect();
+            assert_eq!(
+                simd::scalar::dot(&inputs, &weights),
+                simd::dot(&inputs, &weights)
+            );
+        }
+
+        let mut out: Vec<u8> = vec![0; 4];
+        simd::clipped_relu(&[-5, 0, 100, 300], &mut out);
+        assert_eq!([0, 0, 100, 127], out[..]);
+    }
+}
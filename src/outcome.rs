@@ -0,0 +1,280 @@
+use crate::bitboard::{Bitboard, EMPTY, LIGHT_SQUARES};
+use crate::board::Board;
+use crate::color::Color;
+use crate::piece::Piece;
+
+use std::fmt;
+
+/// Exhaustive enum of the reasons a game can be drawn.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DrawReason {
+    /// The side to move has no legal moves but is not in check.
+    Stalemate,
+    /// The same position occurred for the third time, which a player may claim.
+    ThreefoldRepetition,
+    /// The same position occurred for the fifth time, which ends the game.
+    FivefoldRepetition,
+    /// Fifty moves by each side passed without a capture or pawn move, which a player may claim.
+    FiftyMoveRule,
+    /// Seventy-five moves by each side passed without a capture or pawn move, which ends the game.
+    SeventyFiveMoveRule,
+    /// Neither side has the material left to ever checkmate.
+    InsufficientMaterial,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s: &str = match self {
+            DrawReason::Stalemate => "stalemate",
+            DrawReason::ThreefoldRepetition => "threefold repetition",
+            DrawReason::FivefoldRepetition => "fivefold repetition",
+            DrawReason::FiftyMoveRule => "fifty-move rule",
+            DrawReason::SeventyFiveMoveRule => "seventy-five-move rule",
+            DrawReason::InsufficientMaterial => "insufficient material",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The result of a finished game.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Outcome {
+    /// The winner checkmated the opponent.
+    Checkmate { winner: Color },
+    /// The game ended in a draw.
+    Draw(DrawReason),
+}
+
+impl Outcome {
+    /// Get the winning color, or [`None`] for a draw.
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            Outcome::Checkmate { winner } => Some(*winner),
+            Outcome::Draw(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    /// Formats the outcome as a game result, e.g. `1-0` or `1/2-1/2`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s: &str = match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Board {
+    /// Get the outcome of the game if it has ended without anybody having to claim it.
+    ///
+    /// This covers checkmate, stalemate, fivefold repetition, the seventy-five-move rule and
+    /// insufficient material. Checkmate takes precedence over the other rules. Use
+    /// [`Board::claimable_draw()`] for the draws a player has to claim.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.is_check() {
+                Outcome::Checkmate {
+                    winner: !self.side_to_move(),
+                }
+            } else {
+                Outcome::Draw(DrawReason::Stalemate)
+            });
+        }
+
+        if self.is_insufficient_material() {
+            Some(Outcome::Draw(DrawReason::InsufficientMaterial))
+        } else if self.repetition_count() >= 5 {
+            Some(Outcome::Draw(DrawReason::FivefoldRepetition))
+        } else if self.halfmove_clock() >= 150 {
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoveRule))
+        } else {
+            None
+        }
+    }
+
+    /// Get the reason a player may claim a draw in the current position, if any.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.halfmove_clock() >= 100 && !self.is_checkmate() {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the side to move is checkmated.
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.legal_moves().is_empty()
+    }
+
+    /// Check whether the side to move is stalemated.
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && self.legal_moves().is_empty()
+    }
+
+    /// Check whether neither side can possibly checkmate the other.
+    ///
+    /// This recognizes the simple dead positions: king against king with at most one minor
+    /// piece, and positions where all remaining minor pieces are bishops on the same colored
+    /// squares.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy: Bitboard =
+            [Piece::Pawn, Piece::Rook, Piece::Queen]
+                .iter()
+                .fold(EMPTY, |acc, p| {
+                    acc | self.piece_bitboard(*p, Color::White)
+                        | self.piece_bitboard(*p, Color::Black)
+                });
+        if heavy != EMPTY {
+            return false;
+        }
+
+        let knights: Bitboard = self.piece_bitboard(Piece::Knight, Color::White)
+            | self.piece_bitboard(Piece::Knight, Color::Black);
+        let bishops: Bitboard = self.piece_bitboard(Piece::Bishop, Color::White)
+            | self.piece_bitboard(Piece::Bishop, Color::Black);
+        let minors: u32 = (knights | bishops).popcount();
+
+        minors <= 1
+            || (knights == EMPTY
+                && (bishops & LIGHT_SQUARES == EMPTY || bishops & !LIGHT_SQUARES == EMPTY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::Move;
+
+    fn play(b: &mut Board, moves: &str) {
+        for uci in moves.split_whitespace() {
+            let m: Move = b
+                .legal_moves()
+                .into_iter()
+                .find(|m| m.to_string() == uci)
+                .unwrap_or_else(|| panic!("{} is not legal", uci));
+            b.make_move(m);
+        }
+    }
+
+    #[test]
+    fn ongoing() {
+        let b = Board::default();
+        assert_eq!(None, b.outcome());
+        assert_eq!(None, b.claimable_draw());
+    }
+
+    #[test]
+    fn checkmate() {
+        let mut b = Board::default();
+        play(&mut b, "f2f3 e7e5 g2g4 d8h4");
+
+        assert!(b.is_checkmate());
+        assert_eq!(
+            Some(Outcome::Checkmate {
+                winner: Color::Black
+            }),
+            b.outcome()
+        );
+        assert_eq!("0-1", b.outcome().unwrap().to_string());
+    }
+
+    #[test]
+    fn stalemate() {
+        let b = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert!(b.is_stalemate());
+        assert_eq!(Some(Outcome::Draw(DrawReason::Stalemate)), b.outcome());
+        assert_eq!("1/2-1/2", b.outcome().unwrap().to_string());
+    }
+
+    #[test]
+    fn repetition() {
+        let mut b = Board::default();
+        let shuffle: &str = "g1f3 g8f6 f3g1 f6g8";
+
+        play(&mut b, shuffle);
+        assert_eq!(2, b.repetition_count());
+        assert_eq!(None, b.claimable_draw());
+
+        play(&mut b, shuffle);
+        assert_eq!(3, b.repetition_count());
+        assert_eq!(Some(DrawReason::ThreefoldRepetition), b.claimable_draw());
+        assert_eq!(None, b.outcome());
+
+        play(&mut b, shuffle);
+        play(&mut b, shuffle);
+        assert_eq!(5, b.repetition_count());
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::FivefoldRepetition)),
+            b.outcome()
+        );
+
+        // A pawn move makes all earlier positions unreachable.
+        play(&mut b, "e2e4");
+        assert_eq!(1, b.repetition_count());
+    }
+
+    #[test]
+    fn repetition_needs_same_castling_rights() {
+        let mut b = Board::from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1");
+        play(&mut b, "e1f1 e8f8 f1e1 f8e8");
+        assert_eq!(1, b.repetition_count());
+        play(&mut b, "e1f1 e8f8 f1e1 f8e8");
+        assert_eq!(2, b.repetition_count());
+    }
+
+    #[test]
+    fn move_rules() {
+        let b = Board::from_fen("4k3/8/8/8/8/8/4P3/4K2R w K - 100 80");
+        assert_eq!(Some(DrawReason::FiftyMoveRule), b.claimable_draw());
+        assert_eq!(None, b.outcome());
+
+        let b = Board::from_fen("4k3/8/8/8/8/8/4P3/4K2R w K - 150 80");
+        assert_eq!(
+            Some(Outcome::Draw(DrawReason::SeventyFiveMoveRule)),
+            b.outcome()
+        );
+
+        // Checkmate on the last move still counts.
+        let b = Board::from_fen("4k2R/8/4K3/8/8/8/8/8 b - - 150 80");
+        assert_eq!(
+            Some(Outcome::Checkmate {
+                winner: Color::White
+            }),
+            b.outcome()
+        );
+        assert_eq!(None, b.claimable_draw());
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/B1B1K1B1 w - - 0 1",
+        ] {
+            let b = Board::from_fen(fen);
+            assert!(b.is_insufficient_material(), "{}", fen);
+            assert_eq!(
+                Some(Outcome::Draw(DrawReason::InsufficientMaterial)),
+                b.outcome()
+            );
+        }
+
+        for fen in [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3NKN2 w - - 0 1",
+            "4k1n1/8/8/8/8/8/8/4KB2 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/3BK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2R w - - 0 1",
+        ] {
+            assert!(!Board::from_fen(fen).is_insufficient_material(), "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::board::Board;
+use crate::chess_move::Move;
+
+impl Board {
+    /// Count the leaf nodes of the legal move tree up to the given depth.
+    ///
+    /// Perft ("performance test") numbers are published for many positions, which makes this
+    /// the standard way of verifying move generation.
+    pub fn perft(&self, depth: usize) -> u64 {
+        let mut board: Board = self.clone();
+        perft_recursive(&mut board, depth)
+    }
+
+    /// Count the leaf nodes of the legal move tree per root move.
+    ///
+    /// The node counts of all moves add up to [`Board::perft()`] of the same depth. A depth of
+    /// zero yields no moves.
+    pub fn perft_divide(&self, depth: usize) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        let mut board: Board = self.clone();
+        board
+            .legal_moves()
+            .into_iter()
+            .map(|m| {
+                board.make_move(m);
+                let nodes: u64 = perft_recursive(&mut board, depth - 1);
+                board.unmake_move(m);
+                (m, nodes)
+            })
+            .collect()
+    }
+}
+
+fn perft_recursive(board: &mut Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves: Vec<Move> = board.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes: u64 = 0;
+    for m in moves {
+        board.make_move(m);
+        nodes += perft_recursive(board, depth - 1);
+        board.unmake_move(m);
+    }
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::DEFAULT_BOARD_FEN;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_4_MIRRORED: &str =
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn check(fen: &str, expected: &[u64]) {
+        let b = Board::from_fen(fen);
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(*nodes, b.perft(depth + 1), "{} at depth {}", fen, depth + 1);
+        }
+    }
+
+    #[test]
+    fn depth_zero() {
+        let b = Board::default();
+        assert_eq!(1, b.perft(0));
+        assert!(b.perft_divide(0).is_empty());
+    }
+
+    #[test]
+    fn start_position() {
+        check(DEFAULT_BOARD_FEN, &[20, 400, 8_902, 197_281]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        check(KIWIPETE, &[48, 2_039, 97_862]);
+    }
+
+    #[test]
+    fn position_3() {
+        check(POSITION_3, &[14, 191, 2_812, 43_238]);
+    }
+
+    #[test]
+    fn position_4() {
+        check(POSITION_4, &[6, 264, 9_467]);
+        check(POSITION_4_MIRRORED, &[6, 264, 9_467]);
+    }
+
+    #[test]
+    fn position_5() {
+        check(POSITION_5, &[44, 1_486, 62_379]);
+    }
+
+    #[test]
+    fn position_6() {
+        check(POSITION_6, &[46, 2_079, 89_890]);
+    }
+
+    #[test]
+    fn chess960() {
+        check(
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            &[21, 528, 12_189],
+        );
+        check(
+            "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+            &[21, 807, 18_002],
+        );
+        check(
+            "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+            &[20, 479, 10_471],
+        );
+        check(
+            "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+            &[22, 593, 13_440],
+        );
+        check(
+            "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+            &[28, 1_120, 31_058],
+        );
+        check(
+            "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+            &[29, 899, 26_578],
+        );
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release -- --ignored"]
+    fn deep() {
+        assert_eq!(4_865_609, Board::default().perft(5));
+        assert_eq!(4_085_603, Board::from_fen(KIWIPETE).perft(4));
+        assert_eq!(11_030_083, Board::from_fen(POSITION_3).perft(6));
+        assert_eq!(422_333, Board::from_fen(POSITION_4).perft(4));
+        assert_eq!(2_103_487, Board::from_fen(POSITION_5).perft(4));
+        assert_eq!(3_894_594, Board::from_fen(POSITION_6).perft(4));
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let b = Board::from_fen(KIWIPETE);
+        let divide = b.perft_divide(2);
+
+        assert_eq!(48, divide.len());
+        assert_eq!(b.perft(2), divide.iter().map(|(_, n)| n).sum::<u64>());
+
+        let (_, nodes) = divide
+            .iter()
+            .find(|(m, _)| m.to_string() == "e1g1")
+            .unwrap();
+        assert_eq!(43, *nodes);
+    }
+}
@@ -0,0 +1,1134 @@
+use crate::board::{Board, DEFAULT_BOARD_FEN};
+use crate::chess_move::Move;
+use crate::color::Color;
+use crate::error::{ChessifyError, Result};
+
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::mem;
+
+/// Exhaustive enum of the results a PGN game can be terminated with.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is still in progress, was abandoned or its result is unknown (`*`).
+    #[default]
+    Unknown,
+}
+
+impl GameResult {
+    /// Try and create a new [`GameResult`] from its PGN representation, e.g. `1/2-1/2`.
+    pub fn try_from_str(s: &str) -> Option<Self> {
+        match s {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s: &str = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A move of a PGN game together with its annotations.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PgnMove {
+    /// The move itself, which is legal in the position it is played in.
+    pub mv: Move,
+    /// Numeric annotation glyphs, e.g. `1` for `$1` or `!`.
+    pub nags: Vec<u8>,
+    /// Comments that precede the move, which only occur before the first move of a line.
+    pub comments_before: Vec<String>,
+    /// Comments that follow the move.
+    pub comments: Vec<String>,
+    /// Alternative lines that could have been played instead of this move.
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    /// Create a new [`PgnMove`] without any annotations.
+    pub fn new(mv: Move) -> Self {
+        PgnMove {
+            mv,
+            ..Default::default()
+        }
+    }
+}
+
+/// A single game of a PGN file.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Game {
+    /// The tag pairs in the order they appeared in.
+    pub tags: Vec<(String, String)>,
+    /// The main line of the game.
+    pub moves: Vec<PgnMove>,
+    /// Comments of a movetext without any moves, which have no move to go with.
+    pub comments: Vec<String>,
+    /// The game termination marker at the end of the movetext.
+    pub result: GameResult,
+}
+
+impl Game {
+    /// Create a new [`Game`] without any tags or moves.
+    pub fn new() -> Self {
+        Game::default()
+    }
+
+    /// Get the value of a tag, if it is present.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the value of a tag, replacing an existing value or appending a new tag pair.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Get the position the game starts from, as given by the `FEN` tag or the standard
+    /// starting position.
+    ///
+    /// The `FEN` tag is ignored if the `SetUp` tag is `0`. Without a `SetUp` tag it is still
+    /// used, since many files leave it out. It is read as Chess960 if the `Variant` tag says so.
+    ///
+    /// # Errors
+    /// Iff the `FEN` tag holds an invalid FEN string.
+    pub fn starting_board(&self) -> Result<Board> {
+        let fen: &str = match self.tag("SetUp") {
+            Some("0") => DEFAULT_BOARD_FEN,
+            _ => self.tag("FEN").unwrap_or(DEFAULT_BOARD_FEN),
+        };
+        match self.tag("Variant") {
+            Some(v) if v.eq_ignore_ascii_case("chess960") => Board::try_from_fen_chess960(fen),
+            _ => Board::try_from_fen(fen),
+        }
+    }
+}
+
+/// Exhaustive enum of the reasons a PGN game can be rejected for.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PgnErrorKind {
+    /// A character that has no meaning at this point.
+    UnexpectedCharacter(char),
+    /// A tag pair is not of the form `[Name "Value"]`.
+    InvalidTag,
+    /// A `{` comment is never closed.
+    UnterminatedComment,
+    /// A `(` variation is never closed.
+    UnterminatedVariation,
+    /// A variation is opened before any move it could be an alternative to.
+    VariationWithoutMove,
+    /// A numeric annotation glyph appears before any move it could annotate.
+    NagWithoutMove,
+    /// A game termination marker appears inside a variation.
+    ResultInVariation,
+    /// A numeric annotation glyph is not a number from 0 to 255, or a suffix is not one of
+    /// `!`, `?`, `!!`, `??`, `!?` and `?!`.
+    InvalidNag(String),
+    /// The `FEN` tag does not hold a valid FEN string.
+    InvalidFen(String),
+    /// A move is not valid SAN, or not legal, or ambiguous in its position.
+    IllegalMove(String),
+}
+
+impl fmt::Display for PgnErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{}`", c),
+            PgnErrorKind::InvalidTag => write!(f, "malformed tag pair"),
+            PgnErrorKind::UnterminatedComment => write!(f, "comment is never closed"),
+            PgnErrorKind::UnterminatedVariation => write!(f, "variation is never closed"),
+            PgnErrorKind::VariationWithoutMove => write!(f, "variation does not follow a move"),
+            PgnErrorKind::NagWithoutMove => write!(f, "annotation does not follow a move"),
+            PgnErrorKind::ResultInVariation => write!(f, "game result inside a variation"),
+            PgnErrorKind::InvalidNag(s) => write!(f, "`{}` is not a valid annotation", s),
+            PgnErrorKind::InvalidFen(s) => write!(f, "`{}` is an invalid FEN string", s),
+            PgnErrorKind::IllegalMove(s) => write!(f, "`{}` is not a legal move", s),
+        }
+    }
+}
+
+/// A character source over a [`BufRead`] that keeps only the current line in memory.
+struct Source<R> {
+    reader: R,
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    previous_line_was_tag: bool,
+}
+
+impl<R: BufRead> Source<R> {
+    fn peek(&mut self) -> io::Result<Option<char>> {
+        while self.pos >= self.chars.len() {
+            if !self.next_line()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.chars[self.pos]))
+    }
+
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+
+    /// Load the next line, returning whether there was one.
+    fn next_line(&mut self) -> io::Result<bool> {
+        // PGN files are supposed to be ASCII, but databases in Latin-1 are common enough that
+        // invalid UTF-8 should not stop the whole file.
+        let mut buf: Vec<u8> = Vec::new();
+        if self.reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(false);
+        }
+
+        self.previous_line_was_tag = self.chars.first() == Some(&'[');
+        self.chars = String::from_utf8_lossy(&buf).chars().collect();
+        if self.line == 0 && self.chars.first() == Some(&'\u{feff}') {
+            self.chars.remove(0);
+        }
+        self.line += 1;
+        self.pos = 0;
+        Ok(true)
+    }
+
+    /// The one-based line and column of the next character.
+    fn position(&self) -> (usize, usize) {
+        (self.line, self.pos + 1)
+    }
+}
+
+/// A streaming reader of PGN games.
+///
+/// Games are read one at a time, so files of any size can be processed. Every move is validated
+/// against a [`Board`] set up from the `FEN` tag or the standard starting position. A malformed
+/// game is reported as a [`ChessifyError::InvalidPgn`] error, after which reading resumes at the
+/// next game.
+pub struct PgnReader<R> {
+    source: Source<R>,
+    done: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    /// Create a new [`PgnReader`] reading from the given source.
+    pub fn new(reader: R) -> Self {
+        PgnReader {
+            source: Source {
+                reader,
+                chars: Vec::new(),
+                pos: 0,
+                line: 0,
+                previous_line_was_tag: false,
+            },
+            done: false,
+        }
+    }
+
+    /// Read the next game, returning [`None`] once the input is exhausted.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidPgn`] error if the game is malformed, or the underlying
+    /// I/O error if reading fails. Unlike iterating the reader, this does not skip ahead to the
+    /// next game after an error.
+    pub fn read_game(&mut self) -> Result<Option<Game>> {
+        self.skip_whitespace()?;
+        if self.source.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut game: Game = Game::new();
+        while self.source.peek()? == Some('[') {
+            let tag: (String, String) = self.read_tag()?;
+            game.tags.push(tag);
+            self.skip_whitespace()?;
+        }
+
+        let (line, column) = self.source.position();
+        let mut board: Board = game.starting_board().map_err(|_| {
+            let fen: String = game.tag("FEN").unwrap_or_default().to_string();
+            pgn_error(line, column, PgnErrorKind::InvalidFen(fen))
+        })?;
+
+        let mut result: Option<GameResult> = None;
+        (game.moves, game.comments) = self.read_line(&mut board, 0, (line, column), &mut result)?;
+        game.result = result
+            .or_else(|| game.tag("Result").and_then(GameResult::try_from_str))
+            .unwrap_or_default();
+        Ok(Some(game))
+    }
+
+    /// Skip whitespace and escaped lines, i.e., lines starting with `%`.
+    fn skip_whitespace(&mut self) -> io::Result<()> {
+        while let Some(c) = self.source.peek()? {
+            if c == '%' && self.source.pos == 0 {
+                self.source.pos = self.source.chars.len();
+            } else if c.is_whitespace() {
+                self.source.bump();
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a tag pair of the form `[Name "Value"]`.
+    fn read_tag(&mut self) -> Result<(String, String)> {
+        let (line, column) = self.source.position();
+        let invalid = || pgn_error(line, column, PgnErrorKind::InvalidTag);
+        self.source.bump();
+
+        let mut c: Option<char> = self.next_tag_char(true)?;
+        let mut name: String = String::new();
+        while let Some(n) = c.filter(|n| n.is_alphanumeric() || *n == '_') {
+            name.push(n);
+            c = self.next_tag_char(false)?;
+        }
+        if c.is_some_and(char::is_whitespace) {
+            c = self.next_tag_char(true)?;
+        }
+        if name.is_empty() || c != Some('"') {
+            return Err(invalid());
+        }
+
+        let mut value: String = String::new();
+        loop {
+            match self.next_tag_char(false)?.ok_or_else(invalid)? {
+                '"' => break,
+                '\\' => value.push(self.next_tag_char(false)?.ok_or_else(invalid)?),
+                v => value.push(v),
+            }
+        }
+
+        match self.next_tag_char(true)? {
+            Some(']') => Ok((name, value)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Consume the next character of a tag pair, which can not span multiple lines.
+    fn next_tag_char(&mut self, skip_whitespace: bool) -> io::Result<Option<char>> {
+        loop {
+            match self.source.peek()? {
+                None | Some('\n') => return Ok(None),
+                Some(c) => {
+                    self.source.bump();
+                    if !(skip_whitespace && c.is_whitespace()) {
+                        return Ok(Some(c));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read the moves of a line until its end, i.e., the closing parenthesis of a variation or
+    /// the game termination marker of the main line, together with the comments of a line
+    /// without moves.
+    ///
+    /// `open` is the position of the parenthesis that opened a variation.
+    fn read_line(
+        &mut self,
+        board: &mut Board,
+        depth: usize,
+        open: (usize, usize),
+        result: &mut Option<GameResult>,
+    ) -> Result<(Vec<PgnMove>, Vec<String>)> {
+        let unterminated = || pgn_error(open.0, open.1, PgnErrorKind::UnterminatedVariation);
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+            let (line, column) = self.source.position();
+            let error = |kind: PgnErrorKind| pgn_error(line, column, kind);
+
+            let c: char = match self.source.peek()? {
+                Some(c) => c,
+                None if depth > 0 => return Err(unterminated()),
+                None => break,
+            };
+
+            match c {
+                '{' | ';' => {
+                    let comment: String = self.read_comment()?;
+                    match moves.last_mut() {
+                        Some(m) => m.comments.push(comment),
+                        None => pending.push(comment),
+                    }
+                }
+                '(' => {
+                    self.source.bump();
+                    let last: Move = match moves.last() {
+                        Some(m) => m.mv,
+                        None => return Err(error(PgnErrorKind::VariationWithoutMove)),
+                    };
+
+                    let mut before: Board = board.clone();
+                    before.unmake_move(last);
+                    let (variation, comments) =
+                        self.read_line(&mut before, depth + 1, (line, column), result)?;
+                    let m: &mut PgnMove = moves.last_mut().unwrap();
+                    if variation.is_empty() {
+                        // The comments of an empty variation are kept with the move it follows.
+                        m.comments.extend(comments);
+                    } else {
+                        m.variations.push(variation);
+                    }
+                }
+                ')' if depth > 0 => {
+                    self.source.bump();
+                    break;
+                }
+                // A tag at the start of a line begins the next game, even without a result.
+                '[' if column == 1 && depth == 0 => break,
+                '[' if column == 1 => return Err(unterminated()),
+                '$' | '!' | '?' => {
+                    let nag: u8 = self
+                        .read_nag()?
+                        .map_err(|s| error(PgnErrorKind::InvalidNag(s)))?;
+                    match moves.last_mut() {
+                        Some(m) => m.nags.push(nag),
+                        None => return Err(error(PgnErrorKind::NagWithoutMove)),
+                    }
+                }
+                '.' => self.source.bump(),
+                '*' => {
+                    self.source.bump();
+                    if depth > 0 {
+                        return Err(error(PgnErrorKind::ResultInVariation));
+                    }
+                    *result = Some(GameResult::Unknown);
+                    break;
+                }
+                c if c.is_ascii_alphanumeric() => {
+                    let symbol: String = self.read_symbol()?;
+                    if let Some(r) = GameResult::try_from_str(&symbol) {
+                        if depth > 0 {
+                            return Err(error(PgnErrorKind::ResultInVariation));
+                        }
+                        *result = Some(r);
+                        break;
+                    }
+                    // Move numbers carry no information, their dots are skipped separately.
+                    if symbol.chars().all(|c| c.is_ascii_digit()) {
+                        continue;
+                    }
+
+                    let m: Move = board
+                        .parse_san(&symbol)
+                        .map_err(|_| error(PgnErrorKind::IllegalMove(symbol)))?;
+                    board.make_move(m);
+                    moves.push(PgnMove {
+                        mv: m,
+                        comments_before: mem::take(&mut pending),
+                        ..Default::default()
+                    });
+                }
+                c => return Err(error(PgnErrorKind::UnexpectedCharacter(c))),
+            }
+        }
+
+        Ok((moves, pending))
+    }
+
+    /// Read a `{}` comment or a `;` comment running to the end of the line.
+    fn read_comment(&mut self) -> Result<String> {
+        let (line, column) = self.source.position();
+        let close: char = match self.source.peek()? {
+            Some('{') => '}',
+            _ => '\n',
+        };
+        self.source.bump();
+
+        let mut comment: String = String::new();
+        loop {
+            match self.source.peek()? {
+                Some(c) if c == close => {
+                    self.source.bump();
+                    break;
+                }
+                // Comments spanning several lines are joined into one.
+                Some('\r') => self.source.bump(),
+                Some('\n') => {
+                    comment.push(' ');
+                    self.source.bump();
+                }
+                Some(c) => {
+                    comment.push(c);
+                    self.source.bump();
+                }
+                None if close == '\n' => break,
+                None => return Err(pgn_error(line, column, PgnErrorKind::UnterminatedComment)),
+            }
+        }
+        Ok(comment.trim().to_string())
+    }
+
+    /// Read a `$` numeric annotation glyph or a suffix annotation like `!?`.
+    fn read_nag(&mut self) -> io::Result<std::result::Result<u8, String>> {
+        let mut s: String = String::new();
+        let numeric: bool = self.source.peek()? == Some('$');
+        if numeric {
+            self.source.bump();
+        }
+
+        while let Some(c) = self.source.peek()? {
+            if numeric && c.is_ascii_digit() || !numeric && matches!(c, '!' | '?') {
+                s.push(c);
+                self.source.bump();
+            } else {
+                break;
+            }
+        }
+
+        let nag: Option<u8> = if numeric {
+            s.parse().ok()
+        } else {
+            match s.as_str() {
+                "!" => Some(1),
+                "?" => Some(2),
+                "!!" => Some(3),
+                "??" => Some(4),
+                "!?" => Some(5),
+                "?!" => Some(6),
+                _ => None,
+            }
+        };
+        Ok(nag.ok_or(if numeric { format!("${}", s) } else { s }))
+    }
+
+    /// Read a symbol token, which is a move, a move number or a game termination marker.
+    fn read_symbol(&mut self) -> io::Result<String> {
+        let mut symbol: String = String::new();
+        while let Some(c) = self.source.peek()? {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '#' | '=' | ':' | '-' | '/') {
+                symbol.push(c);
+                self.source.bump();
+            } else {
+                break;
+            }
+        }
+        Ok(symbol)
+    }
+
+    /// Skip ahead to the first tag of the next game after an error.
+    fn skip_to_next_game(&mut self) -> io::Result<()> {
+        let source: &mut Source<R> = &mut self.source;
+        if source.pos == 0 && source.chars.first() == Some(&'[') {
+            return Ok(());
+        }
+
+        source.pos = source.chars.len();
+        while source.next_line()? {
+            if source.chars.first() == Some(&'[') && !source.previous_line_was_tag {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_game() {
+            Ok(Some(game)) => Some(Ok(game)),
+            Ok(None) => None,
+            Err(e) => {
+                if !e.is::<ChessifyError>() || self.skip_to_next_game().is_err() {
+                    self.done = true;
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The tags of the Seven Tag Roster in the order they are exported in, with their defaults.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// The maximum length of a movetext line.
+const LINE_WIDTH: usize = 80;
+
+/// Exhaustive enum of the formats games can be written in, as defined by the PGN standard.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PgnFormat {
+    /// All tags, comments, variations and numeric annotation glyphs.
+    #[default]
+    Export,
+    /// Only the Seven Tag Roster and the bare main line, for bulk storage of unannotated games.
+    ///
+    /// The `SetUp` and `FEN` tags are kept as well, since the moves can not be read without them.
+    Reduced,
+}
+
+/// A writer of PGN games.
+///
+/// The Seven Tag Roster is always written first and in order, filling in missing tags with
+/// their defaults. The movetext is wrapped at 80 columns.
+pub struct PgnWriter<W> {
+    writer: W,
+    format: PgnFormat,
+}
+
+impl<W: Write> PgnWriter<W> {
+    /// Create a new [`PgnWriter`] writing games in the given format.
+    pub fn new(writer: W, format: PgnFormat) -> Self {
+        PgnWriter { writer, format }
+    }
+
+    /// Write a single game followed by an empty line.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::IllegalMove`] error if a move of the game is not legal in its
+    /// position, or the underlying I/O error if writing fails.
+    pub fn write_game(&mut self, game: &Game) -> Result<()> {
+        let pgn: String = game.to_pgn(self.format)?;
+        self.writer.write_all(pgn.as_bytes())?;
+        Ok(())
+    }
+
+    /// Get the underlying writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl Game {
+    /// Format the game as PGN in the given format, ending with an empty line.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::IllegalMove`] error if a move of the game is not legal in its
+    /// position.
+    pub fn to_pgn(&self, format: PgnFormat) -> Result<String> {
+        let mut pgn: String = String::new();
+
+        let result: String = self.result.to_string();
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value: &str = match name {
+                "Result" => &result,
+                _ => self.tag(name).unwrap_or(default),
+            };
+            push_tag(&mut pgn, name, value);
+        }
+
+        let mut others: Vec<&(String, String)> = self
+            .tags
+            .iter()
+            .filter(|(name, _)| SEVEN_TAG_ROSTER.iter().all(|(n, _)| n != name))
+            .filter(|(name, _)| format == PgnFormat::Export || name == "SetUp" || name == "FEN")
+            .collect();
+        others.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, value) in others {
+            push_tag(&mut pgn, name, value);
+        }
+        pgn.push('\n');
+
+        let mut tokens: Vec<String> = Vec::new();
+        if format == PgnFormat::Export {
+            for comment in &self.comments {
+                push_comment(&mut tokens, comment);
+            }
+        }
+        let mut board: Board = self.starting_board()?;
+        push_line(&mut tokens, &mut board, &self.moves, format)?;
+        tokens.push(result);
+
+        let mut line_length: usize = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > LINE_WIDTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push_str("\n\n");
+        Ok(pgn)
+    }
+}
+
+/// Append a tag pair line, escaping quotes and backslashes in the value.
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    let escaped: String = value.replace('\\', "\\\\").replace('"', "\\\"");
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, escaped));
+}
+
+/// Append the movetext tokens of a line, where `board` is the position before its first move.
+fn push_line(
+    tokens: &mut Vec<String>,
+    board: &mut Board,
+    moves: &[PgnMove],
+    format: PgnFormat,
+) -> Result<()> {
+    let export: bool = format == PgnFormat::Export;
+    // Black moves need their number too when something interrupts the sequence of moves.
+    let mut interrupted: bool = true;
+
+    for m in moves {
+        if export {
+            for comment in &m.comments_before {
+                push_comment(tokens, comment);
+                interrupted = true;
+            }
+        }
+
+        if !board.legal_moves().contains(&m.mv) {
+            return Err(Box::new(ChessifyError::IllegalMove(m.mv.to_string())));
+        }
+        match board.side_to_move() {
+            Color::White => tokens.push(format!("{}.", board.fullmove_number())),
+            Color::Black if interrupted => tokens.push(format!("{}...", board.fullmove_number())),
+            Color::Black => {}
+        }
+        tokens.push(m.mv.to_san(board));
+        interrupted = false;
+
+        if export {
+            tokens.extend(m.nags.iter().map(|nag| format!("${}", nag)));
+            for comment in &m.comments {
+                push_comment(tokens, comment);
+                interrupted = true;
+            }
+            for variation in m.variations.iter().filter(|v| !v.is_empty()) {
+                let start: usize = tokens.len();
+                push_line(tokens, &mut board.clone(), variation, format)?;
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                interrupted = true;
+            }
+        }
+
+        board.make_move(m.mv);
+    }
+    Ok(())
+}
+
+/// Append a comment as one token per word, so that long comments can be wrapped.
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let mut words: Vec<String> = comment
+        .split_whitespace()
+        .map(|w| w.replace('}', ""))
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        words.push(String::new());
+    }
+
+    words[0].insert(0, '{');
+    words.last_mut().unwrap().push('}');
+    tokens.extend(words);
+}
+
+/// Create a boxed [`ChessifyError::InvalidPgn`] error.
+fn pgn_error(line: usize, column: usize, kind: PgnErrorKind) -> Box<dyn error::Error> {
+    Box::new(ChessifyError::InvalidPgn { line, column, kind })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(pgn: &str) -> Vec<Result<Game>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    fn pgn_error_of(r: Result<Game>) -> ChessifyError {
+        *r.unwrap_err().downcast::<ChessifyError>().unwrap()
+    }
+
+    fn uci(moves: &[PgnMove]) -> Vec<String> {
+        moves.iter().map(|m| m.mv.to_string()).collect()
+    }
+
+    #[test]
+    fn tags_and_main_line() {
+        let games = read(concat!(
+            "[Event \"F/S Return Match\"]\n",
+            "[Site \"Belgrade, Serbia JUG\"]\n",
+            "[White \"Fischer, Robert J.\"]\n",
+            "[Black \"Spassky, Boris V.\"]\n",
+            "[Result \"1/2-1/2\"]\n",
+            "\n",
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 {This opening is called the Ruy Lopez.}\n",
+            "4. Ba4 Nf6 5. O-O Be7 1/2-1/2\n",
+        ));
+
+        assert_eq!(1, games.len());
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(5, game.tags.len());
+        assert_eq!(Some("Fischer, Robert J."), game.tag("White"));
+        assert_eq!(None, game.tag("Date"));
+        assert_eq!(GameResult::Draw, game.result);
+        assert_eq!(
+            vec!["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6", "e1g1", "f8e7"],
+            uci(&game.moves)
+        );
+        assert_eq!(
+            vec!["This opening is called the Ruy Lopez.".to_string()],
+            game.moves[5].comments
+        );
+    }
+
+    #[test]
+    fn variations_comments_and_nags() {
+        let games = read(concat!(
+            "{Starting comment} 1. e4 $1 e5!? (1... c5 {Sicilian} 2. Nf3 (2. c3 d5) d6)\n",
+            "(1... e6 ; French\n",
+            "2. d4) 2. Nf3?? *",
+        ));
+        let game = games[0].as_ref().unwrap();
+
+        assert_eq!(GameResult::Unknown, game.result);
+        assert_eq!(vec!["e2e4", "e7e5", "g1f3"], uci(&game.moves));
+        assert_eq!(
+            vec!["Starting comment".to_string()],
+            game.moves[0].comments_before
+        );
+        assert_eq!(vec![1], game.moves[0].nags);
+        assert_eq!(vec![5], game.moves[1].nags);
+        assert_eq!(vec![4], game.moves[2].nags);
+
+        let variations = &game.moves[1].variations;
+        assert_eq!(2, variations.len());
+        assert_eq!(vec!["c7c5", "g1f3", "d7d6"], uci(&variations[0]));
+        assert_eq!(vec!["Sicilian".to_string()], variations[0][0].comments);
+        assert_eq!(vec!["c2c3", "d7d5"], uci(&variations[0][1].variations[0]));
+        assert_eq!(vec!["e7e6", "d2d4"], uci(&variations[1]));
+        assert_eq!(vec!["French".to_string()], variations[1][0].comments);
+
+        let games = read("1. e4 {Best\r\nby test} *");
+        assert_eq!(
+            vec!["Best by test".to_string()],
+            games[0].as_ref().unwrap().moves[0].comments
+        );
+    }
+
+    #[test]
+    fn fen_setup() {
+        let games = read(concat!(
+            "[SetUp \"1\"]\n",
+            "[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 40\"]\n",
+            "\n",
+            "40... Kd7 41. O-O-O+ Kc7 0-1\n",
+        ));
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(vec!["e8d7", "e1c1", "d7c7"], uci(&game.moves));
+        assert_eq!(GameResult::BlackWins, game.result);
+
+        // A `SetUp` tag of `0` means the game starts from the standard position.
+        let games = read(concat!(
+            "[SetUp \"0\"]\n",
+            "[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 40\"]\n",
+            "\n",
+            "1. e4 *\n",
+        ));
+        assert_eq!(vec!["e2e4"], uci(&games[0].as_ref().unwrap().moves));
+
+        // X-FEN castling rights of a Chess960 game.
+        let games = read(concat!(
+            "[Variant \"Chess960\"]\n",
+            "[SetUp \"1\"]\n",
+            "[FEN \"4k3/8/8/8/8/8/8/R5K1 w Q - 0 1\"]\n",
+            "\n",
+            "1. O-O-O *\n",
+        ));
+        assert_eq!(vec!["g1c1"], uci(&games[0].as_ref().unwrap().moves));
+    }
+
+    #[test]
+    fn multiple_games_and_escapes() {
+        let games = read(concat!(
+            "\u{feff}% exported by a database\n",
+            "[Event \"A\"]\n",
+            "[Escaped \"say \\\"hi\\\" \\\\o/\"]\n",
+            "\n",
+            "1. d4 d5 1-0\n",
+            "\n",
+            "[Event \"B\"]\n",
+            "\n",
+            "1. c4 *\n",
+            "[Event \"C\"]\n",
+            "1. Nf3\n",
+            "[Event \"D\"]\n",
+        ));
+
+        assert_eq!(4, games.len());
+        let events: Vec<&str> = games
+            .iter()
+            .map(|g| g.as_ref().unwrap().tag("Event").unwrap())
+            .collect();
+        assert_eq!(vec!["A", "B", "C", "D"], events);
+        assert_eq!(
+            Some("say \"hi\" \\o/"),
+            games[0].as_ref().unwrap().tag("Escaped")
+        );
+        // Missing results fall back to unknown.
+        assert_eq!(GameResult::Unknown, games[2].as_ref().unwrap().result);
+        assert!(games[3].as_ref().unwrap().moves.is_empty());
+    }
+
+    #[test]
+    fn errors_with_positions() {
+        let cases: [(&str, usize, usize, PgnErrorKind); 8] = [
+            (
+                "1. e4 e5 2. Ke3 *",
+                1,
+                13,
+                PgnErrorKind::IllegalMove("Ke3".to_string()),
+            ),
+            ("[Event \"A]\n\n1. e4 *", 1, 1, PgnErrorKind::InvalidTag),
+            (
+                "1. e4 {never closed\n\n",
+                1,
+                7,
+                PgnErrorKind::UnterminatedComment,
+            ),
+            ("1. e4 (1. d4\n", 1, 7, PgnErrorKind::UnterminatedVariation),
+            ("( 1. e4 ) *", 1, 1, PgnErrorKind::VariationWithoutMove),
+            ("1. e4 (!? 1... e5) *", 1, 8, PgnErrorKind::NagWithoutMove),
+            (
+                "1. e4 (1. d4 1-0) *",
+                1,
+                14,
+                PgnErrorKind::ResultInVariation,
+            ),
+            (
+                "1. e4 $256 *",
+                1,
+                7,
+                PgnErrorKind::InvalidNag("$256".to_string()),
+            ),
+        ];
+
+        for (pgn, line, column, kind) in cases {
+            let mut games = read(pgn);
+            assert_eq!(
+                ChessifyError::InvalidPgn { line, column, kind },
+                pgn_error_of(games.remove(0)),
+                "{}",
+                pgn
+            );
+        }
+
+        let mut games = read("[FEN \"8/8/8 w - - 0 1\"]\n\n*");
+        assert!(matches!(
+            pgn_error_of(games.remove(0)),
+            ChessifyError::InvalidPgn {
+                kind: PgnErrorKind::InvalidFen(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn recovers_after_errors() {
+        let games = read(concat!(
+            "[Event \"Broken\"]\n",
+            "[Round \"1\"]\n",
+            "\n",
+            "1. e4 e5 2. Nf4 Nc6\n",
+            "3. Bb5 1-0\n",
+            "\n",
+            "[Event \"Broken tag\n",
+            "[Round \"2\"]\n",
+            "\n",
+            "1. d4 *\n",
+            "\n",
+            "[Event \"Fine\"]\n",
+            "[Round \"3\"]\n",
+            "\n",
+            "1. c4 *\n",
+        ));
+
+        assert_eq!(3, games.len());
+        let mut games = games.into_iter();
+        assert_eq!(
+            ChessifyError::InvalidPgn {
+                line: 4,
+                column: 13,
+                kind: PgnErrorKind::IllegalMove("Nf4".to_string()),
+            },
+            pgn_error_of(games.next().unwrap())
+        );
+        assert!(matches!(
+            pgn_error_of(games.next().unwrap()),
+            ChessifyError::InvalidPgn { line: 7, .. }
+        ));
+        let fine = games.next().unwrap().unwrap();
+        assert_eq!(Some("3"), fine.tag("Round"));
+        assert_eq!(vec!["c2c4"], uci(&fine.moves));
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(read("").is_empty());
+        assert!(read("\n  \n% only an escape\n").is_empty());
+    }
+
+    #[test]
+    fn write_seven_tag_roster() {
+        let mut game = Game::new();
+        game.set_tag("White", "Carlsen, Magnus");
+        game.set_tag("ECO", "C65");
+        game.set_tag("Annotator", "Quote \"this\" \\ that");
+        game.set_tag("Event", "Casual");
+        game.result = GameResult::WhiteWins;
+
+        assert_eq!(
+            concat!(
+                "[Event \"Casual\"]\n",
+                "[Site \"?\"]\n",
+                "[Date \"????.??.??\"]\n",
+                "[Round \"?\"]\n",
+                "[White \"Carlsen, Magnus\"]\n",
+                "[Black \"?\"]\n",
+                "[Result \"1-0\"]\n",
+                "[Annotator \"Quote \\\"this\\\" \\\\ that\"]\n",
+                "[ECO \"C65\"]\n",
+                "\n",
+                "1-0\n",
+                "\n",
+            ),
+            game.to_pgn(PgnFormat::Export).unwrap()
+        );
+    }
+
+    #[test]
+    fn write_formats() {
+        let pgn: &str = concat!(
+            "[Event \"Annotated\"]\n",
+            "[ECO \"B20\"]\n",
+            "\n",
+            "{Intro} 1. e4 $1 c5 {Sicilian} (1... e5 2. Nf3 (2. f4) 2... Nc6) 2. Nf3 *\n",
+        );
+        let game = read(pgn).remove(0).unwrap();
+
+        let export: String = game.to_pgn(PgnFormat::Export).unwrap();
+        assert!(export.contains("[ECO \"B20\"]"));
+        assert!(export.ends_with(
+            "\n{Intro} 1. e4 $1 c5 {Sicilian} (1... e5 2. Nf3 (2. f4) 2... Nc6) 2. Nf3 *\n\n"
+        ));
+
+        let reduced: String = game.to_pgn(PgnFormat::Reduced).unwrap();
+        assert!(!reduced.contains("ECO"));
+        assert!(reduced.ends_with("[Result \"*\"]\n\n1. e4 c5 2. Nf3 *\n\n"));
+    }
+
+    #[test]
+    fn write_set_up_position() {
+        let pgn: &str = concat!(
+            "[SetUp \"1\"]\n",
+            "[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 40\"]\n",
+            "[Annotator \"Someone\"]\n",
+            "\n",
+            "40... Kd7 41. O-O-O+ 0-1\n",
+        );
+        let game = read(pgn).remove(0).unwrap();
+
+        let reduced: String = game.to_pgn(PgnFormat::Reduced).unwrap();
+        assert!(!reduced.contains("Annotator"));
+        assert!(reduced.contains("[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 40\"]\n[SetUp \"1\"]\n"));
+        assert!(reduced.ends_with("\n40... Kd7 41. O-O-O+ 0-1\n\n"));
+    }
+
+    #[test]
+    fn write_wraps_and_round_trips() {
+        let mut board = Board::default();
+        let mut game = Game::new();
+        // A long, deterministic game with a long comment in the middle.
+        for i in 0..120 {
+            let moves = board.legal_moves();
+            if moves.is_empty() || board.outcome().is_some() {
+                break;
+            }
+            let mut m = PgnMove::new(moves[(i * 7) % moves.len()]);
+            if i == 30 {
+                m.comments
+                    .push("a rather long comment that has to be wrapped ".repeat(4));
+                m.nags.push(14);
+            }
+            board.make_move(m.mv);
+            game.moves.push(m);
+        }
+
+        let pgn: String = game.to_pgn(PgnFormat::Export).unwrap();
+        assert!(pgn.lines().all(|l| l.len() <= LINE_WIDTH));
+        assert!(pgn.lines().any(|l| l.len() > LINE_WIDTH - 10));
+
+        let mut read_back = read(&pgn).remove(0).unwrap();
+        read_back.moves[30].comments[0].push(' ');
+        assert_eq!(game.moves, read_back.moves);
+
+        let mut writer = PgnWriter::new(Vec::new(), PgnFormat::Export);
+        writer.write_game(&game).unwrap();
+        writer.write_game(&game).unwrap();
+        let both: Vec<u8> = writer.into_inner();
+        assert_eq!(2, PgnReader::new(both.as_slice()).count());
+    }
+
+    #[test]
+    fn comments_without_moves() {
+        let pgn: &str = "[Result \"1-0\"]\n\n{White wins by forfeit} 1-0\n\n";
+        let game = read(pgn).remove(0).unwrap();
+        assert!(game.moves.is_empty());
+        assert_eq!(vec!["White wins by forfeit".to_string()], game.comments);
+
+        let exported: String = game.to_pgn(PgnFormat::Export).unwrap();
+        assert!(exported.ends_with("\n{White wins by forfeit} 1-0\n\n"));
+        assert_eq!(game.comments, read(&exported).remove(0).unwrap().comments);
+        assert!(game
+            .to_pgn(PgnFormat::Reduced)
+            .unwrap()
+            .ends_with("\n1-0\n\n"));
+
+        // The comment of an empty variation stays with the move before it.
+        let game = read("1. e4 ({Or anything else}) *").remove(0).unwrap();
+        assert!(game.moves[0].variations.is_empty());
+        assert_eq!(vec!["Or anything else".to_string()], game.moves[0].comments);
+    }
+
+    #[test]
+    fn write_illegal_move() {
+        let mut game = Game::new();
+        game.moves.push(PgnMove::new(
+            Board::default().parse_uci_move("e2e4").unwrap(),
+        ));
+        game.moves.push(PgnMove::new(
+            Board::default().parse_uci_move("d2d4").unwrap(),
+        ));
+        assert!(game.to_pgn(PgnFormat::Export).is_err());
+    }
+}
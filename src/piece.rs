@@ -1,5 +1,7 @@
 use crate::color::Color;
 
+use std::fmt;
+
 /// Exhaustive enum of all available piece types.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd)]
 pub enum Piece {
@@ -49,6 +51,27 @@ impl Piece {
     }
 }
 
+/// A piece together with the color of the player it belongs to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ColoredPiece {
+    pub piece: Piece,
+    pub color: Color,
+}
+
+impl ColoredPiece {
+    /// Create a new [`ColoredPiece`] from a piece and its color.
+    pub fn new(piece: Piece, color: Color) -> Self {
+        ColoredPiece { piece, color }
+    }
+}
+
+impl fmt::Display for ColoredPiece {
+    /// Formats the piece as its FEN letter, upper case for white and lower case for black.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.piece.to_string(self.color))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
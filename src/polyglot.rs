@@ -0,0 +1,591 @@
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::color::Color;
+use crate::error::{ChessifyError, Result};
+use crate::pgn::{Game, GameResult, PgnReader};
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The size of an entry in a Polyglot book file.
+const ENTRY_SIZE: usize = 16;
+
+/// An entry of a Polyglot opening book, suggesting a move in the position with the given key.
+///
+/// The key is the Zobrist key of [`Board::zobrist_key()`], which uses the Polyglot random
+/// numbers.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    /// The move in Polyglot encoding, see [`encode_book_move()`].
+    pub raw_move: u16,
+    /// How often the move should be played relative to the other moves of the position.
+    pub weight: u16,
+    /// Data for learning from the games played with the book, which chessify does not use.
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(b: &[u8]) -> Self {
+        BookEntry {
+            key: u64::from_be_bytes(b[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes([b[8], b[9]]),
+            weight: u16::from_be_bytes([b[10], b[11]]),
+            learn: u32::from_be_bytes([b[12], b[13], b[14], b[15]]),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut b: [u8; ENTRY_SIZE] = [0; ENTRY_SIZE];
+        b[0..8].copy_from_slice(&self.key.to_be_bytes());
+        b[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        b[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        b[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        b
+    }
+}
+
+/// Encode a legal move of `board` the way Polyglot books store it.
+///
+/// The destination square takes bits 0-5 and the origin bits 6-11, both counted from a1, and
+/// bits 12-14 hold the promotion piece from 1 for a knight to 4 for a queen. Castling is
+/// encoded as the king capturing its own rook, e.g. `e1h1`.
+pub fn encode_book_move(board: &Board, m: Move) -> u16 {
+    let to = if m.is_castle() {
+        board.castling_rook_squares(m).0
+    } else {
+        m.to()
+    };
+    let promotion: u16 = m.promotion().map_or(0, |p| p.as_index() as u16);
+    (promotion << 12) | (((m.from().index() ^ 56) as u16) << 6) | (to.index() ^ 56) as u16
+}
+
+/// Decode a move of a Polyglot book, or get [`None`] if it is not a legal move of `board`.
+pub fn decode_book_move(board: &Board, raw_move: u16) -> Option<Move> {
+    board
+        .legal_moves()
+        .into_iter()
+        .find(|m| encode_book_move(board, *m) == raw_move)
+}
+
+/// Exhaustive enum of the ways a move is picked from the moves a book suggests.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum BookSelection {
+    /// The move with the highest weight, the first one listed on a tie.
+    #[default]
+    BestMove,
+    /// A random move, each chosen in proportion to its weight.
+    Weighted,
+    /// A random move, each as likely as any other.
+    Uniform,
+}
+
+/// An opening book in the Polyglot `.bin` format.
+///
+/// The whole book is kept in memory, sorted by key, so that the entries of a position are
+/// found with a binary search.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    /// Create a new [`PolyglotBook`] from entries in any order.
+    ///
+    /// Entries of the same position keep their relative order.
+    pub fn new(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by_key(|e| e.key);
+        PolyglotBook { entries }
+    }
+
+    /// Load a book from the file at `path`.
+    ///
+    /// # Errors
+    /// Returns the I/O error if the file can not be read, and a
+    /// [`ChessifyError::InvalidBookSize`] error if it does not consist of whole entries.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<PolyglotBook> {
+        PolyglotBook::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a book from `reader` until its end.
+    ///
+    /// # Errors
+    /// Same as [`PolyglotBook::load()`].
+    pub fn read<R: Read>(mut reader: R) -> Result<PolyglotBook> {
+        let mut bytes: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let chunks = bytes.chunks_exact(ENTRY_SIZE);
+        if !chunks.remainder().is_empty() {
+            return Err(Box::new(ChessifyError::InvalidBookSize(bytes.len())));
+        }
+        Ok(PolyglotBook::new(
+            chunks.map(BookEntry::from_bytes).collect(),
+        ))
+    }
+
+    /// Write the book to `writer` in the Polyglot format.
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        for e in &self.entries {
+            writer.write_all(&e.to_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Get all entries of the book, sorted by key.
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    /// Get the number of entries in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether the book has no entries at all.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the entries of the position with the Zobrist key `key`.
+    pub fn probe(&self, key: u64) -> &[BookEntry] {
+        let start: usize = self.entries.partition_point(|e| e.key < key);
+        let len: usize = self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..start + len]
+    }
+
+    /// Get the legal moves the book suggests for `board` with their weights.
+    ///
+    /// Entries whose move is not legal, e.g. because of a key collision, are left out.
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        self.probe(board.zobrist_key())
+            .iter()
+            .filter_map(|e| decode_book_move(board, e.raw_move).map(|m| (m, e.weight)))
+            .collect()
+    }
+
+    /// Pick one of the moves the book suggests for `board`, or get [`None`] if it has none.
+    ///
+    /// The random selections are made with `random`, which should be a fresh random number for
+    /// every call, e.g. from a seeded generator. A weighted selection among moves that all
+    /// have weight zero is made uniformly.
+    pub fn choose(&self, board: &Board, selection: BookSelection, random: u64) -> Option<Move> {
+        let moves: Vec<(Move, u16)> = self.moves(board);
+        if moves.is_empty() {
+            return None;
+        }
+
+        let total: u64 = moves.iter().map(|(_, w)| *w as u64).sum();
+        match selection {
+            BookSelection::BestMove => moves
+                .iter()
+                .min_by_key(|(_, w)| Reverse(*w))
+                .map(|(m, _)| *m),
+            BookSelection::Weighted if total > 0 => {
+                let mut pick: u64 = random % total;
+                moves.iter().find_map(|(m, w)| {
+                    if pick < *w as u64 {
+                        Some(*m)
+                    } else {
+                        pick -= *w as u64;
+                        None
+                    }
+                })
+            }
+            BookSelection::Weighted | BookSelection::Uniform => {
+                Some(moves[(random % moves.len() as u64) as usize].0)
+            }
+        }
+    }
+}
+
+/// The settings of a [`BookBuilder`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BookConfig {
+    /// How many plies from the start of every game go into the book.
+    pub max_ply: usize,
+    /// How many games a move must have been played in to go into the book.
+    pub min_games: u32,
+    /// What a move adds to its weight in a game won by the side that played it.
+    pub win_weight: u32,
+    /// What a move adds to its weight in a drawn game.
+    pub draw_weight: u32,
+    /// What a move adds to its weight in a game lost by the side that played it.
+    pub loss_weight: u32,
+}
+
+impl Default for BookConfig {
+    fn default() -> Self {
+        BookConfig {
+            max_ply: 24,
+            min_games: 3,
+            win_weight: 2,
+            draw_weight: 1,
+            loss_weight: 0,
+        }
+    }
+}
+
+/// How often a move was played in a position and what it scored.
+#[derive(Clone, Copy, Debug, Default)]
+struct MoveStats {
+    games: u32,
+    weight: u64,
+}
+
+/// Builds a [`PolyglotBook`] from the opening moves of a collection of games.
+pub struct BookBuilder {
+    config: BookConfig,
+    /// Indexed by the key of the position and the encoded move.
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    /// Create a new [`BookBuilder`] without any games.
+    pub fn new(config: BookConfig) -> Self {
+        BookBuilder {
+            config,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Get the settings of the builder.
+    pub fn config(&self) -> &BookConfig {
+        &self.config
+    }
+
+    /// Add the main line of `game` to the book, returning whether it was used.
+    ///
+    /// Games without a known result can not be weighted and are skipped, as are games whose
+    /// `FEN` tag holds an invalid FEN string.
+    pub fn add_game(&mut self, game: &Game) -> bool {
+        let winner: Option<Color> = match game.result {
+            GameResult::WhiteWins => Some(Color::White),
+            GameResult::BlackWins => Some(Color::Black),
+            GameResult::Draw => None,
+            GameResult::Unknown => return false,
+        };
+
+        let mut board: Board = match game.starting_board() {
+            Ok(board) => board,
+            Err(_) => return false,
+        };
+        for pm in game.moves.iter().take(self.config.max_ply) {
+            let weight: u32 = match winner {
+                Some(c) if c == board.side_to_move() => self.config.win_weight,
+                Some(_) => self.config.loss_weight,
+                None => self.config.draw_weight,
+            };
+            let key: (u64, u16) = (board.zobrist_key(), encode_book_move(&board, pm.mv));
+            let stats: &mut MoveStats = self.stats.entry(key).or_default();
+            stats.games += 1;
+            stats.weight += weight as u64;
+            board.make_move(pm.mv);
+        }
+        true
+    }
+
+    /// Add every game read from a PGN source, returning how many games were used.
+    ///
+    /// Malformed games are skipped, as they are by [`PgnReader`].
+    ///
+    /// # Errors
+    /// Returns the I/O error if reading fails.
+    pub fn add_pgn<R: BufRead>(&mut self, reader: R) -> Result<usize> {
+        let mut used: usize = 0;
+        for game in PgnReader::new(reader) {
+            match game {
+                Ok(game) => used += self.add_game(&game) as usize,
+                Err(e) if e.is::<ChessifyError>() => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(used)
+    }
+
+    /// Build the book from the games added so far.
+    ///
+    /// Moves played in fewer than [`BookConfig::min_games`] games or with a weight of zero
+    /// are left out. The weights of a position are scaled down together when the largest
+    /// does not fit into a [`u16`].
+    pub fn build(&self) -> PolyglotBook {
+        let mut positions: HashMap<u64, Vec<(u16, u64)>> = HashMap::new();
+        for ((key, raw_move), stats) in &self.stats {
+            if stats.games >= self.config.min_games && stats.weight > 0 {
+                positions
+                    .entry(*key)
+                    .or_default()
+                    .push((*raw_move, stats.weight));
+            }
+        }
+
+        let mut entries: Vec<BookEntry> = Vec::new();
+        for (key, moves) in positions {
+            let max: u64 = moves.iter().map(|(_, w)| *w).max().unwrap_or(0);
+            let limit: u64 = u16::MAX as u64;
+            for (raw_move, weight) in moves {
+                let weight: u64 = if max > limit {
+                    (weight * limit / max).max(1)
+                } else {
+                    weight
+                };
+                entries.push(BookEntry {
+                    key,
+                    raw_move,
+                    weight: weight as u16,
+                    learn: 0,
+                });
+            }
+        }
+        entries.sort_by_key(|e| (e.key, Reverse(e.weight), e.raw_move));
+        PolyglotBook { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    fn play(board: &mut Board, moves: &[&str]) {
+        for uci in moves {
+            board.make_move(board.parse_uci_move(uci).unwrap());
+        }
+    }
+
+    #[test]
+    fn polyglot_keys() {
+        // The reference keys of the Polyglot book format specification.
+        for (moves, key) in [
+            (&[][..], 0x463B_9618_1691_FC9C),
+            (&["e2e4"][..], 0x823C_9B50_FD11_4196),
+            (&["e2e4", "d7d5"][..], 0x0756_B944_61C5_0FB0),
+            (&["e2e4", "d7d5", "e4e5"][..], 0x662F_AFB9_65DB_29D4),
+            (&["e2e4", "d7d5", "e4e5", "f7f5"][..], 0x22A4_8B5A_8E47_FF78),
+            (
+                &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"][..],
+                0x652A_607C_A3F2_42C1,
+            ),
+            (
+                &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"][..],
+                0x00FD_D303_C946_BDD9,
+            ),
+            (
+                &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"][..],
+                0x3C81_23EA_7B06_7637,
+            ),
+            (
+                &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"][..],
+                0x5C3F_9B82_9B27_9560,
+            ),
+        ] {
+            let mut board: Board = Board::default();
+            play(&mut board, moves);
+            assert_eq!(key, board.zobrist_key(), "{:?}", moves);
+        }
+    }
+
+    #[test]
+    fn move_encoding() {
+        let board: Board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        for (uci, raw) in [
+            // e1 is square 4 and h1 square 7 counted from a1.
+            ("e1g1", 4 << 6 | 7),
+            ("e1c1", 4 << 6),
+            ("b7b8q", 4 << 12 | 49 << 6 | 57),
+            ("b7a8n", 1 << 12 | 49 << 6 | 56),
+            ("a1a8", 56),
+        ] {
+            let m: Move = board.parse_uci_move(uci).unwrap();
+            assert_eq!(raw, encode_book_move(&board, m), "{}", uci);
+            assert_eq!(Some(m), decode_book_move(&board, raw), "{}", uci);
+        }
+        // The king moving two squares is not how books write castling.
+        assert_eq!(None, decode_book_move(&board, 4 << 6 | 6));
+        assert_eq!(None, decode_book_move(&board, 49 << 6 | 57));
+
+        // In Chess960 the king may castle with a rook right next to it.
+        let board: Board = Board::from_fen("4k3/8/8/8/8/8/8/4KR2 w F - 0 1");
+        let m: Move = board.parse_uci_move("e1g1").unwrap();
+        assert!(m.is_castle());
+        assert_eq!(4 << 6 | 5, encode_book_move(&board, m));
+        assert_eq!(Some(m), decode_book_move(&board, 4 << 6 | 5));
+    }
+
+    fn entry(key: u64, raw_move: u16, weight: u16) -> BookEntry {
+        BookEntry {
+            key,
+            raw_move,
+            weight,
+            learn: 0,
+        }
+    }
+
+    #[test]
+    fn read_write() {
+        let book: PolyglotBook = PolyglotBook::new(vec![
+            entry(3, 1, 10),
+            entry(1, 2, 20),
+            entry(3, 3, 30),
+            entry(u64::MAX, 4, 40),
+            BookEntry {
+                learn: 0xDEAD_BEEF,
+                ..entry(2, 5, 50)
+            },
+        ]);
+        assert_eq!(
+            [1, 2, 3, 3, u64::MAX],
+            book.entries().iter().map(|e| e.key).collect::<Vec<u64>>()[..]
+        );
+        assert_eq!([entry(3, 1, 10), entry(3, 3, 30)], book.probe(3));
+        assert_eq!(1, book.probe(u64::MAX).len());
+        assert!(book.probe(0).is_empty());
+        assert!(book.probe(4).is_empty());
+
+        let mut bytes: Vec<u8> = Vec::new();
+        book.write(&mut bytes).unwrap();
+        assert_eq!(5 * 16, bytes.len());
+        // Entries are stored big-endian.
+        assert_eq!(
+            [0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 20, 0, 0, 0, 0],
+            bytes[..16]
+        );
+        assert_eq!(book, PolyglotBook::read(&bytes[..]).unwrap());
+
+        let e = PolyglotBook::read(&bytes[..17]).unwrap_err();
+        assert_eq!(
+            Some(&ChessifyError::InvalidBookSize(17)),
+            e.downcast_ref::<ChessifyError>()
+        );
+        let e = PolyglotBook::load("/nonexistent/book.bin").unwrap_err();
+        assert!(e.is::<io::Error>());
+    }
+
+    #[test]
+    fn choose() {
+        let board: Board = Board::default();
+        let key: u64 = board.zobrist_key();
+        let raw = |uci: &str| encode_book_move(&board, board.parse_uci_move(uci).unwrap());
+        let book: PolyglotBook = PolyglotBook::new(vec![
+            entry(key, raw("d2d4"), 1),
+            entry(key, raw("e2e4"), 3),
+            entry(key, raw("g1f3"), 3),
+            // Not legal in this position.
+            entry(key, raw("e2e4") ^ 1, 100),
+        ]);
+        assert_eq!(3, book.moves(&board).len());
+
+        let choose = |selection: BookSelection, random: u64| -> String {
+            book.choose(&board, selection, random).unwrap().to_uci()
+        };
+        assert_eq!("e2e4", choose(BookSelection::BestMove, 5));
+        let weighted: Vec<String> = (0..7).map(|r| choose(BookSelection::Weighted, r)).collect();
+        assert_eq!(
+            ["d2d4", "e2e4", "e2e4", "e2e4", "g1f3", "g1f3", "g1f3"],
+            weighted[..]
+        );
+        assert_eq!("d2d4", choose(BookSelection::Weighted, 7));
+        let uniform: Vec<String> = (0..3).map(|r| choose(BookSelection::Uniform, r)).collect();
+        assert_eq!(["d2d4", "e2e4", "g1f3"], uniform[..]);
+
+        let zero: PolyglotBook = PolyglotBook::new(vec![entry(key, raw("d2d4"), 0)]);
+        let d4 = board.parse_uci_move("d2d4").ok();
+        assert_eq!(d4, zero.choose(&board, BookSelection::Weighted, 9));
+
+        let mut after: Board = board.clone();
+        play(&mut after, &["e2e4"]);
+        assert_eq!(None, book.choose(&after, BookSelection::BestMove, 0));
+    }
+
+    #[test]
+    fn build_from_pgn() {
+        let pgn: &str = concat!(
+            "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n",
+            "[Result \"0-1\"]\n\n1. e4 c5 2. Nf3 d6 0-1\n\n",
+            "[Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n",
+            "[Result \"*\"]\n\n1. d4 d5 *\n\n",
+            "[Result \"1-0\"]\n\n1. e4 e4 1-0\n\n",
+            "[Result \"1-0\"]\n\n1. e4 e5 2. Bc4 1-0\n\n",
+        );
+        let config: BookConfig = BookConfig {
+            max_ply: 2,
+            min_games: 1,
+            ..Default::default()
+        };
+        let mut builder: BookBuilder = BookBuilder::new(config);
+        assert_eq!(4, builder.add_pgn(pgn.as_bytes()).unwrap());
+        let book: PolyglotBook = builder.build();
+
+        let weights = |board: &Board| -> Vec<(String, u16)> {
+            book.moves(board)
+                .iter()
+                .map(|(m, w)| (m.to_uci(), *w))
+                .collect()
+        };
+        let mut board: Board = Board::default();
+        // Two wins and a loss for e4, a draw for d4.
+        assert_eq!(
+            [("e2e4".to_string(), 4), ("d2d4".to_string(), 1)],
+            weights(&board)[..]
+        );
+        play(&mut board, &["e2e4"]);
+        // Black lost both games with e5, so it is left out.
+        assert_eq!([("c7c5".to_string(), 2)], weights(&board)[..]);
+        play(&mut board, &["c7c5"]);
+        // Moves beyond the maximum ply are left out.
+        assert!(weights(&board).is_empty());
+
+        let config: BookConfig = BookConfig {
+            min_games: 2,
+            loss_weight: 1,
+            ..Default::default()
+        };
+        let mut builder: BookBuilder = BookBuilder::new(config);
+        builder.add_pgn(pgn.as_bytes()).unwrap();
+        let book: PolyglotBook = builder.build();
+        assert_eq!(2, book.len());
+        let mut board: Board = Board::default();
+        assert_eq!(
+            [(board.parse_uci_move("e2e4").unwrap(), 5)],
+            book.moves(&board)[..]
+        );
+        play(&mut board, &["e2e4"]);
+        assert_eq!(
+            [(board.parse_uci_move("e7e5").unwrap(), 2)],
+            book.moves(&board)[..]
+        );
+    }
+
+    #[test]
+    fn scales_weights() {
+        let mut builder: BookBuilder = BookBuilder::new(BookConfig {
+            min_games: 1,
+            win_weight: 40_000,
+            draw_weight: 10_000,
+            ..Default::default()
+        });
+        let pgn: &str = "[Result \"1-0\"]\n\n1. e4 1-0\n\n[Result \"1-0\"]\n\n1. e4 1-0\n\n\
+                         [Result \"1/2-1/2\"]\n\n1. d4 1/2-1/2\n\n";
+        builder.add_pgn(pgn.as_bytes()).unwrap();
+        let weights: Vec<u16> = builder.build().entries().iter().map(|e| e.weight).collect();
+        assert_eq!([u16::MAX, 8191], weights[..]);
+    }
+
+    #[test]
+    fn skips_invalid_games() {
+        let mut builder: BookBuilder = BookBuilder::new(BookConfig::default());
+        let mut game: Game = Game::new();
+        game.result = GameResult::Draw;
+        game.tags.push(("FEN".to_string(), "not a fen".to_string()));
+        assert!(!builder.add_game(&game));
+
+        let pgn: &str = "[Result \"1-0\"]\n[FEN \"8/8/8 w - - 0 1\"]\n\n1-0\n\n\
+                         [Result \"1-0\"]\n\n1. e4 1-0\n\n";
+        assert_eq!(1, builder.add_pgn(pgn.as_bytes()).unwrap());
+    }
+}
@@ -0,0 +1,340 @@
+use crate::board::Board;
+use crate::chess_move::{Move, MoveFlag};
+use crate::color::Color;
+use crate::error::{ChessifyError, Result};
+use crate::piece::Piece;
+use crate::square::{File, Square};
+
+impl Move {
+    /// Format the move in Standard Algebraic Notation (SAN), e.g. `Nbd7`, `exd5` or `O-O-O+`.
+    ///
+    /// The move has to be legal on the given board, which is the position before the move.
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut san: String = match self.flag() {
+            MoveFlag::KingCastle => "O-O".to_string(),
+            MoveFlag::QueenCastle => "O-O-O".to_string(),
+            _ => san_without_suffix(*self, board),
+        };
+
+        let mut after: Board = board.clone();
+        after.make_move(*self);
+        if after.is_check() {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+}
+
+impl Board {
+    /// Parse a move in Standard Algebraic Notation (SAN) and match it to a legal move.
+    ///
+    /// Parsing is tolerant towards common sloppy input: a leading `P` for pawn moves, a missing
+    /// or superfluous `x`, `0-0` for castling, a missing `=` before the promotion piece,
+    /// annotations like `!?`, and long algebraic moves like `e2e4` or `Ng1-f3`.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidSan`] error if the string can not be read as a move,
+    /// a [`ChessifyError::IllegalMove`] error if no legal move matches and a
+    /// [`ChessifyError::AmbiguousMove`] error if more than one legal move matches.
+    pub fn parse_san(&self, san: &str) -> Result<Move> {
+        let invalid = || ChessifyError::InvalidSan(san.to_string());
+
+        let trimmed: &str = san
+            .trim()
+            .trim_end_matches("e.p.")
+            .trim_end_matches(['+', '#', '!', '?', ' ']);
+        let moves: Vec<Move> = self.legal_moves();
+
+        let castle: Option<MoveFlag> = match trimmed {
+            "O-O" | "0-0" | "o-o" => Some(MoveFlag::KingCastle),
+            "O-O-O" | "0-0-0" | "o-o-o" => Some(MoveFlag::QueenCastle),
+            _ => None,
+        };
+        if let Some(flag) = castle {
+            return match moves.into_iter().find(|m| m.flag() == flag) {
+                Some(m) => Ok(m),
+                None => Err(Box::new(ChessifyError::IllegalMove(san.to_string()))),
+            };
+        }
+
+        let mut chars: Vec<char> = trimmed
+            .chars()
+            .filter(|c| !matches!(c, 'x' | 'X' | ':' | '-' | '='))
+            .collect();
+
+        let piece: Option<Piece> = chars.first().copied().and_then(piece_from_char);
+        if piece.is_some() {
+            chars.remove(0);
+        }
+
+        // A trailing piece letter can only be a promotion, since squares end in a digit.
+        let promotion: Option<Piece> = match chars.last().map(|c| c.to_ascii_uppercase()) {
+            Some(c) if chars.len() > 2 && c.is_ascii_alphabetic() => {
+                chars.pop();
+                Some(piece_from_char(c).ok_or_else(invalid)?)
+            }
+            _ => None,
+        };
+        if chars.len() < 2 || chars.len() > 4 || promotion == Some(Piece::Pawn) {
+            return Err(Box::new(invalid()));
+        }
+
+        let (from, to) = chars.split_at(chars.len() - 2);
+        let to: Square =
+            Square::try_from(to.iter().collect::<String>().as_str()).map_err(|_| invalid())?;
+        let mut from_file: Option<u8> = None;
+        let mut from_rank: Option<u8> = None;
+        for c in from {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(File::try_from(*c).map_err(|_| invalid())?.0)
+                }
+                '1'..='8' if from_rank.is_none() => from_rank = Some(*c as u8 - b'1'),
+                _ => return Err(Box::new(invalid())),
+            }
+        }
+
+        // Long algebraic moves name the origin square, which makes the piece letter optional.
+        let piece: Option<Piece> = match (piece, from_file, from_rank) {
+            (None, Some(_), Some(_)) => None,
+            (piece, _, _) => Some(piece.unwrap_or(Piece::Pawn)),
+        };
+        let mut candidates = moves.into_iter().filter(|m| {
+            m.to() == to
+                && piece.is_none_or(|p| self.piece_at(m.from()) == Some(p))
+                && promotion.is_none_or(|p| m.promotion() == Some(p))
+                && from_file.is_none_or(|f| m.from().file_as_u8() == f)
+                && from_rank.is_none_or(|r| m.from().rank_as_u8() == r)
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(m), None) => Ok(m),
+            (Some(_), Some(_)) => Err(Box::new(ChessifyError::AmbiguousMove(san.to_string()))),
+            (None, _) => Err(Box::new(ChessifyError::IllegalMove(san.to_string()))),
+        }
+    }
+}
+
+/// Map an uppercase SAN piece letter to its [`Piece`].
+fn piece_from_char(c: char) -> Option<Piece> {
+    match c {
+        'P' => Some(Piece::Pawn),
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+/// Format a move that is not castling, leaving out the check suffix.
+fn san_without_suffix(m: Move, board: &Board) -> String {
+    let piece: Piece = board
+        .piece_at(m.from())
+        .expect("no piece on the square to move");
+    let mut san: String = String::new();
+
+    if piece == Piece::Pawn {
+        if m.is_capture() {
+            san.push_str(&m.from().file().to_string());
+        }
+    } else {
+        san.push_str(&piece.to_string(Color::White));
+
+        // Other pieces of the same kind that could move to the same square.
+        let rivals: Vec<Square> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|o| {
+                o.to() == m.to() && o.from() != m.from() && board.piece_at(o.from()) == Some(piece)
+            })
+            .map(|o| o.from())
+            .collect();
+
+        if !rivals.is_empty() {
+            if rivals.iter().all(|s| s.file() != m.from().file()) {
+                san.push_str(&m.from().file().to_string());
+            } else if rivals.iter().all(|s| s.rank() != m.from().rank()) {
+                san.push_str(&m.from().rank().to_string());
+            } else {
+                san.push_str(&m.from().to_string());
+            }
+        }
+    }
+
+    if m.is_capture() {
+        san.push('x');
+    }
+    san.push_str(&m.to().to_string());
+
+    if let Some(p) = m.promotion() {
+        san.push('=');
+        san.push_str(&p.to_string(Color::White));
+    }
+    san
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san_error(b: &Board, san: &str) -> ChessifyError {
+        *b.parse_san(san)
+            .unwrap_err()
+            .downcast::<ChessifyError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn to_san() {
+        let b =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let san = |uci: &str| {
+            let m = b
+                .legal_moves()
+                .into_iter()
+                .find(|m| m.to_string() == uci)
+                .unwrap();
+            m.to_san(&b)
+        };
+
+        assert_eq!("O-O", san("e1g1"));
+        assert_eq!("O-O-O", san("e1c1"));
+        assert_eq!("dxe6", san("d5e6"));
+        assert_eq!("Nxf7", san("e5f7"));
+        assert_eq!("Bxa6", san("e2a6"));
+        assert_eq!("Qxf6", san("f3f6"));
+        assert_eq!("gxh3", san("g2h3"));
+        assert_eq!("a4", san("a2a4"));
+        assert_eq!("Rb1", san("a1b1"));
+    }
+
+    #[test]
+    fn disambiguation() {
+        // Knights on b1 and f1 can reach d2, the rooks on a1 and a5 share a file and the
+        // queen on h4 shares its file and rank with the other queens reaching e4.
+        let b = Board::from_fen("6k1/K7/8/R7/1Q5Q/8/8/RN3N1Q w - - 0 1");
+        let san = |uci: &str| {
+            let m = b
+                .legal_moves()
+                .into_iter()
+                .find(|m| m.to_string() == uci)
+                .unwrap();
+            m.to_san(&b)
+        };
+
+        assert_eq!("Nbd2", san("b1d2"));
+        assert_eq!("Nfd2", san("f1d2"));
+        assert_eq!("R1a3", san("a1a3"));
+        assert_eq!("R5a3", san("a5a3"));
+        assert_eq!("Qh4e4", san("h4e4"));
+        assert_eq!("Q1e4", san("h1e4"));
+        assert_eq!("Qbe4", san("b4e4"));
+
+        assert_eq!("h4e4", b.parse_san("Qh4e4").unwrap().to_string());
+        assert_eq!("a1a3", b.parse_san("R1a3").unwrap().to_string());
+        assert_eq!("f1d2", b.parse_san("Nfd2").unwrap().to_string());
+        assert!(matches!(
+            san_error(&b, "Qe4"),
+            ChessifyError::AmbiguousMove(_)
+        ));
+    }
+
+    #[test]
+    fn check_and_mate_suffixes() {
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let castle = b.parse_san("O-O").unwrap();
+        assert_eq!("O-O", castle.to_san(&b));
+        assert_eq!("Ra8+", b.parse_san("Ra8").unwrap().to_san(&b));
+
+        let b = Board::from_fen("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1");
+        assert_eq!("Ra8#", b.parse_san("Ra8").unwrap().to_san(&b));
+        assert_eq!("O-O-O", b.parse_san("0-0-0").unwrap().to_san(&b));
+    }
+
+    #[test]
+    fn promotions() {
+        let b = Board::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let m = b.parse_san("axb8=Q+").unwrap();
+        assert_eq!("a7b8q", m.to_string());
+        assert_eq!("axb8=Q+", m.to_san(&b));
+        assert_eq!("a7a8n", b.parse_san("a8=N").unwrap().to_string());
+        assert_eq!("a7a8r", b.parse_san("a8R").unwrap().to_string());
+        assert!(matches!(
+            san_error(&b, "a8"),
+            ChessifyError::AmbiguousMove(_)
+        ));
+        assert!(matches!(
+            san_error(&b, "a8=K"),
+            ChessifyError::IllegalMove(_)
+        ));
+    }
+
+    #[test]
+    fn sloppy_input() {
+        let b = Board::default();
+        assert_eq!("e2e4", b.parse_san("Pe4").unwrap().to_string());
+        assert_eq!("e2e4", b.parse_san("e2e4").unwrap().to_string());
+        assert_eq!("e2e4", b.parse_san("e2-e4").unwrap().to_string());
+        assert_eq!("g1f3", b.parse_san("Ng1-f3").unwrap().to_string());
+        assert_eq!("g1f3", b.parse_san("Nf3!?").unwrap().to_string());
+        assert_eq!("g1f3", b.parse_san("g1f3").unwrap().to_string());
+        assert_eq!("g1f3", b.parse_san("g1-f3").unwrap().to_string());
+
+        let b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(MoveFlag::KingCastle, b.parse_san("e1g1").unwrap().flag());
+        assert_eq!(MoveFlag::QueenCastle, b.parse_san("e1c1").unwrap().flag());
+
+        let b = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        assert_eq!("e4d5", b.parse_san("ed5").unwrap().to_string());
+        assert_eq!("e4d5", b.parse_san("exd5").unwrap().to_string());
+        assert_eq!("e4d5", b.parse_san("e4:d5").unwrap().to_string());
+    }
+
+    #[test]
+    fn errors() {
+        let b = Board::default();
+        assert!(matches!(san_error(&b, "e5"), ChessifyError::IllegalMove(_)));
+        assert!(matches!(
+            san_error(&b, "O-O"),
+            ChessifyError::IllegalMove(_)
+        ));
+        assert!(matches!(san_error(&b, ""), ChessifyError::InvalidSan(_)));
+        assert!(matches!(
+            san_error(&b, "Nf3g"),
+            ChessifyError::InvalidSan(_)
+        ));
+        assert!(matches!(san_error(&b, "e9"), ChessifyError::InvalidSan(_)));
+        assert!(matches!(
+            san_error(&b, "g1e2"),
+            ChessifyError::IllegalMove(_)
+        ));
+
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+        assert!(matches!(
+            san_error(&b, "Nd2"),
+            ChessifyError::AmbiguousMove(_)
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let b = Board::from_fen(fen);
+            for m in b.legal_moves() {
+                let san = m.to_san(&b);
+                assert_eq!(m, b.parse_san(&san).unwrap(), "{} in {}", san, fen);
+            }
+        }
+    }
+}
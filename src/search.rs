@@ -0,0 +1,787 @@
+use crate::board::Board;
+use crate::chess_move::{Move, NULL_MOVE};
+use crate::color::Color;
+use crate::piece::PIECES;
+use crate::see::PieceValues;
+use crate::transposition::{ScoreBound, TranspositionTable};
+
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The score of checkmating on the spot, mates further away score closer to zero.
+pub const MATE_SCORE: i32 = 32_000;
+
+/// The deepest the search ever goes, quiescence search and check extensions included.
+pub const MAX_PLY: usize = 128;
+
+/// A score beyond any real one, bounding the initial search window.
+const INFINITY: i32 = MATE_SCORE + 1;
+
+/// Every score at least this high, or at most its negation, is a mate score.
+const MATE_BOUND: i32 = MATE_SCORE - MAX_PLY as i32;
+
+/// The half width of the first aspiration window around the previous score, in centipawns.
+const ASPIRATION_WINDOW: i32 = 25;
+
+/// Check whether a score announces a forced mate for either side.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_BOUND
+}
+
+/// Get the number of moves until mate for a mate score, negative if the side to move gets
+/// mated, as in the `score mate` of the UCI protocol.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+    let moves: i32 = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// A static evaluation of positions for the search.
+pub trait Evaluator {
+    /// Score a position in centipawns from the point of view of the side to move.
+    fn evaluate(&mut self, board: &Board) -> i32;
+
+    /// Start following a search from `board`.
+    ///
+    /// Together with [`Evaluator::make_move()`] and [`Evaluator::unmake_move()`] this lets
+    /// an evaluator update its state incrementally, instead of starting from scratch in
+    /// every position. Evaluators without such state can ignore all three.
+    fn reset(&mut self, _board: &Board) {}
+
+    /// Follow the search into `board`, which was reached by making a move.
+    fn make_move(&mut self, _board: &Board) {}
+
+    /// Follow the search back to the position before the last [`Evaluator::make_move()`].
+    fn unmake_move(&mut self) {}
+}
+
+/// Plain material counting, kings excluded.
+impl Evaluator for PieceValues {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let material = |c: Color| -> i32 {
+            PIECES[..PIECES.len() - 1]
+                .iter()
+                .map(|p| board.piece_bitboard(*p, c).popcount() as i32 * self.of(*p))
+                .sum()
+        };
+        let score: i32 = material(Color::White) - material(Color::Black);
+        match board.side_to_move() {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+}
+
+/// The limits of a search, which stops as soon as the first of them is hit.
+///
+/// Without any limit the search goes on until [`MAX_PLY`] or a forced mate is reached.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    /// The time after which the search is aborted.
+    pub time: Option<Duration>,
+    /// The time after which no further iteration is started.
+    pub soft_time: Option<Duration>,
+    /// A flag that aborts the search once another thread sets it.
+    pub stop: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimits {
+    /// Search to a fixed depth.
+    pub fn depth(depth: u32) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Search a fixed number of nodes.
+    pub fn nodes(nodes: u64) -> Self {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    /// Search for a fixed time.
+    pub fn time(time: Duration) -> Self {
+        SearchLimits {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+}
+
+/// The outcome of a search, or of one of its iterations.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchResult {
+    /// The move to play, or [`None`] if there is no legal move.
+    pub best_move: Option<Move>,
+    /// The principal variation, starting with the best move.
+    pub pv: Vec<Move>,
+    /// The score in centipawns from the point of view of the side to move, see
+    /// [`mate_in()`] for mate scores.
+    pub score: i32,
+    /// The depth of the last iteration that finished.
+    pub depth: u32,
+    /// The deepest ply any line reached, quiescence search included.
+    pub seldepth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+    /// How full the transposition table is in per mille, see
+    /// [`TranspositionTable::hashfull()`].
+    pub hashfull: u32,
+}
+
+impl SearchResult {
+    /// Get the number of moves until mate, see [`mate_in()`].
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+
+    /// Get the nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.time.as_secs_f64().max(1e-3)) as u64
+    }
+}
+
+/// A negamax alpha-beta search with iterative deepening, aspiration windows, principal
+/// variation search and a quiescence search on captures and promotions.
+///
+/// Moves are ordered by the previous principal variation, the best move found in the
+/// transposition table, the static exchange evaluation of captures, killer moves and the
+/// history heuristic. The transposition table, killer moves and history are kept from one
+/// search to the next until [`Searcher::clear()`] is called.
+pub struct Searcher<E = PieceValues> {
+    evaluator: E,
+    board: Board,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    seldepth: usize,
+    aborted: bool,
+    killers: [[Move; 2]; MAX_PLY],
+    /// Indexed by color, origin and destination of quiet moves.
+    history: Vec<i32>,
+    tt: Arc<TranspositionTable>,
+}
+
+impl Searcher {
+    /// Create a new [`Searcher`] that counts material with the default piece values.
+    pub fn new() -> Self {
+        Searcher::with_evaluator(PieceValues::default())
+    }
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher::new()
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    /// Create a new [`Searcher`] that evaluates positions with `evaluator`.
+    pub fn with_evaluator(evaluator: E) -> Self {
+        Searcher {
+            evaluator,
+            board: Board::default(),
+            limits: SearchLimits::default(),
+            start: Instant::now(),
+            nodes: 0,
+            seldepth: 0,
+            aborted: false,
+            killers: [[NULL_MOVE; 2]; MAX_PLY],
+            history: vec![0; 2 * 64 * 64],
+            tt: Arc::new(TranspositionTable::default()),
+        }
+    }
+
+    /// Get the transposition table.
+    pub fn tt(&self) -> &Arc<TranspositionTable> {
+        &self.tt
+    }
+
+    /// Replace the transposition table, e.g. with one of a different size or one shared with
+    /// other searchers.
+    pub fn set_tt(&mut self, tt: Arc<TranspositionTable>) {
+        self.tt = tt;
+    }
+
+    /// Get the evaluator.
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Get the evaluator mutably, e.g. to change its parameters between searches.
+    pub fn evaluator_mut(&mut self) -> &mut E {
+        &mut self.evaluator
+    }
+
+    /// Forget what was learned in earlier searches, e.g. before a new game.
+    pub fn clear(&mut self) {
+        self.killers = [[NULL_MOVE; 2]; MAX_PLY];
+        self.history.fill(0);
+        self.tt.clear();
+    }
+
+    /// Search `board` until a limit is hit.
+    pub fn search(&mut self, board: &Board, limits: &SearchLimits) -> SearchResult {
+        self.search_with(board, limits, |_| {})
+    }
+
+    /// Search `board` until a limit is hit, calling `report` after every finished iteration.
+    ///
+    /// If not even the first iteration finishes, the best move is the one that looked most
+    /// promising before searching.
+    pub fn search_with<F: FnMut(&SearchResult)>(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        mut report: F,
+    ) -> SearchResult {
+        self.board = board.clone();
+        self.evaluator.reset(&self.board);
+        self.limits = limits.clone();
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.aborted = false;
+        for h in self.history.iter_mut() {
+            *h /= 2;
+        }
+        self.tt.new_search();
+
+        let mut moves: Vec<Move> = self.board.legal_moves();
+        if moves.is_empty() {
+            return SearchResult {
+                score: if self.board.is_check() {
+                    -MATE_SCORE
+                } else {
+                    0
+                },
+                ..Default::default()
+            };
+        }
+        let tt_move: Option<Move> = self
+            .tt
+            .probe(self.board.zobrist_key())
+            .and_then(|e| e.best_move);
+        self.order(&mut moves, 0, tt_move);
+
+        let mut result: SearchResult = SearchResult {
+            best_move: moves.first().copied(),
+            ..Default::default()
+        };
+        let max_depth: u32 = limits
+            .depth
+            .unwrap_or(MAX_PLY as u32)
+            .min(MAX_PLY as u32 - 1);
+        for depth in 1..=max_depth {
+            let mut pv: Vec<Move> = result.pv.clone();
+            let score: i32 = self.aspiration(depth as i32, result.score, &mut pv);
+            if self.aborted {
+                break;
+            }
+
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                pv,
+                score,
+                depth,
+                seldepth: self.seldepth as u32,
+                nodes: self.nodes,
+                time: self.start.elapsed(),
+                hashfull: self.tt.hashfull(),
+            };
+            report(&result);
+
+            // A mate this close has been searched exhaustively, so no deeper search shortens it.
+            let mate_found: bool = is_mate_score(score) && MATE_SCORE - score.abs() <= depth as i32;
+            let out_of_time: bool = limits.soft_time.is_some_and(|t| self.start.elapsed() >= t);
+            if mate_found || out_of_time {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result
+    }
+
+    /// Search the root with a narrow window around the previous score, widening it until the
+    /// score falls inside.
+    fn aspiration(&mut self, depth: i32, previous: i32, pv: &mut Vec<Move>) -> i32 {
+        let mut delta: i32 = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = if depth >= 4 && !is_mate_score(previous) {
+            (previous - delta, previous + delta)
+        } else {
+            (-INFINITY, INFINITY)
+        };
+
+        loop {
+            let mut line: Vec<Move> = pv.clone();
+            let score: i32 = self.negamax(depth, 0, alpha, beta, &mut line);
+            if self.aborted {
+                return score;
+            }
+
+            if score <= alpha {
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                *pv = line;
+                return score;
+            }
+            delta *= 2;
+        }
+    }
+
+    /// Search `depth` plies deep, storing the principal variation in `pv`.
+    ///
+    /// On entry `pv` holds the line expected from this node, which is searched first.
+    fn negamax(
+        &mut self,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        mut beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        let expected: Vec<Move> = std::mem::take(pv);
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if ply > 0 {
+            if self.board.repetition_count() > 1 || self.board.is_insufficient_material() {
+                return 0;
+            }
+            // No mate found from here can be shorter than one already found closer to the root.
+            alpha = alpha.max(ply as i32 - MATE_SCORE);
+            beta = beta.min(MATE_SCORE - ply as i32 - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(&self.board);
+        }
+
+        let in_check: bool = self.board.is_check();
+        let depth: i32 = if in_check { depth + 1 } else { depth };
+        if depth <= 0 {
+            return self.quiesce(ply, alpha, beta);
+        }
+
+        let key: u64 = self.board.zobrist_key();
+        let mut tt_move: Option<Move> = None;
+        if let Some(entry) = self.tt.probe(key) {
+            tt_move = entry.best_move;
+            let score: i32 = score_from_tt(entry.score, ply);
+            let usable: bool = match entry.bound {
+                ScoreBound::Exact => true,
+                ScoreBound::Lower => score >= beta,
+                ScoreBound::Upper => score <= alpha,
+            };
+            // Principal variation nodes are searched anyway, to keep the line complete.
+            if ply > 0 && beta - alpha == 1 && entry.depth as i32 >= depth && usable {
+                return score;
+            }
+        }
+
+        let mut moves: Vec<Move> = self.board.legal_moves();
+        if moves.is_empty() {
+            return if in_check { ply as i32 - MATE_SCORE } else { 0 };
+        }
+        if ply > 0 && self.board.halfmove_clock() >= 100 {
+            return 0;
+        }
+        let pv_move: Option<Move> = expected.first().copied();
+        self.order(&mut moves, ply, pv_move.or(tt_move));
+
+        let original_alpha: i32 = alpha;
+        let mut best: i32 = -INFINITY;
+        let mut best_move: Option<Move> = None;
+        for (i, m) in moves.into_iter().enumerate() {
+            let mut line: Vec<Move> = if Some(m) == pv_move {
+                expected[1..].to_vec()
+            } else {
+                Vec::new()
+            };
+
+            self.board.make_move(m);
+            self.evaluator.make_move(&self.board);
+            let mut score: i32;
+            if i == 0 {
+                score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+            } else {
+                // Prove with a null window that the move is worse, searching it fully only if
+                // that fails.
+                score = -self.negamax(depth - 1, ply + 1, -alpha - 1, -alpha, &mut line);
+                if score > alpha && score < beta {
+                    line.clear();
+                    score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+                }
+            }
+            self.board.unmake_move(m);
+            self.evaluator.unmake_move();
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(m);
+                pv.clear();
+                pv.push(m);
+                pv.append(&mut line);
+                if score >= beta {
+                    if !m.is_capture() && !m.is_promotion() {
+                        self.reward_quiet(m, depth, ply);
+                    }
+                    break;
+                }
+            }
+        }
+
+        let bound: ScoreBound = if best >= beta {
+            ScoreBound::Lower
+        } else if best > original_alpha {
+            ScoreBound::Exact
+        } else {
+            ScoreBound::Upper
+        };
+        self.tt
+            .store(key, best_move, score_to_tt(best, ply), depth, bound);
+        best
+    }
+
+    /// Resolve the captures and promotions left at the end of the main search, and every
+    /// evasion when in check.
+    fn quiesce(&mut self, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(&self.board);
+        }
+
+        let mut moves: Vec<Move> = self.board.legal_moves();
+        let mut best: i32;
+        if self.board.is_check() {
+            if moves.is_empty() {
+                return ply as i32 - MATE_SCORE;
+            }
+            best = -INFINITY;
+            self.order(&mut moves, ply, None);
+        } else {
+            // Standing pat is allowed, as some quiet move is assumed to be at least as good.
+            best = self.evaluator.evaluate(&self.board);
+            if best >= beta {
+                return best;
+            }
+            alpha = alpha.max(best);
+            let mut scored: Vec<(Move, i32)> = moves
+                .into_iter()
+                .filter(|m| m.is_capture() || m.is_promotion())
+                .map(|m| (m, self.board.see(m)))
+                .filter(|(_, see)| *see >= 0)
+                .collect();
+            scored.sort_by_key(|(_, see)| Reverse(*see));
+            moves = scored.into_iter().map(|(m, _)| m).collect();
+        }
+
+        for m in moves {
+            self.board.make_move(m);
+            self.evaluator.make_move(&self.board);
+            let score: i32 = -self.quiesce(ply + 1, -beta, -alpha);
+            self.board.unmake_move(m);
+            self.evaluator.unmake_move();
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    if score >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Order moves from the most to the least promising, starting with `hint` if given.
+    fn order(&self, moves: &mut [Move], ply: usize, hint: Option<Move>) {
+        let us: usize = self.board.side_to_move().as_index();
+        moves.sort_by_cached_key(|m| {
+            let key: i32 = if Some(*m) == hint {
+                i32::MAX
+            } else if m.is_capture() || m.is_promotion() {
+                // Captures that lose material go after the quiet moves.
+                let see: i32 = self.board.see(*m);
+                if see >= 0 {
+                    2_000_000 + see
+                } else {
+                    -2_000_000 + see
+                }
+            } else if self.killers[ply][0] == *m {
+                1_000_001
+            } else if self.killers[ply][1] == *m {
+                1_000_000
+            } else {
+                self.history[history_index(us, *m)]
+            };
+            Reverse(key)
+        });
+    }
+
+    /// Remember a quiet move that caused a beta cutoff.
+    fn reward_quiet(&mut self, m: Move, depth: i32, ply: usize) {
+        if self.killers[ply][0] != m {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = m;
+        }
+        let i: usize = history_index(self.board.side_to_move().as_index(), m);
+        // Kept well below the killer scores in move ordering.
+        self.history[i] = (self.history[i] + depth * depth).min(500_000);
+    }
+
+    /// Check whether a limit has been hit, remembering the answer.
+    fn should_stop(&mut self) -> bool {
+        if self.aborted {
+            return true;
+        }
+        if self.limits.nodes.is_some_and(|n| self.nodes >= n) {
+            self.aborted = true;
+        } else if self.nodes & 1023 == 0 {
+            let stopped: bool = self
+                .limits
+                .stop
+                .as_ref()
+                .is_some_and(|s| s.load(Ordering::Relaxed));
+            let out_of_time: bool = self.limits.time.is_some_and(|t| self.start.elapsed() >= t);
+            self.aborted = stopped || out_of_time;
+        }
+        self.aborted
+    }
+}
+
+fn history_index(color: usize, m: Move) -> usize {
+    (color * 64 + m.from().0 as usize) * 64 + m.to().0 as usize
+}
+
+/// Make a mate score relative to the position rather than the root, as the same position can
+/// be reached at different plies.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Make a mate score read from the transposition table relative to the root again.
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(fen: &str, limits: &SearchLimits) -> SearchResult {
+        Searcher::new().search(&Board::from_fen(fen), limits)
+    }
+
+    fn uci(moves: &[Move]) -> Vec<String> {
+        moves.iter().map(|m| m.to_uci()).collect()
+    }
+
+    #[test]
+    fn mate_scores() {
+        assert_eq!(Some(1), mate_in(MATE_SCORE - 1));
+        assert_eq!(Some(2), mate_in(MATE_SCORE - 3));
+        assert_eq!(Some(-1), mate_in(-MATE_SCORE + 2));
+        assert_eq!(Some(0), mate_in(-MATE_SCORE));
+        assert_eq!(None, mate_in(900));
+        assert!(!is_mate_score(-MATE_BOUND + 1));
+    }
+
+    #[test]
+    fn finds_mates() {
+        let r = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &SearchLimits::depth(3));
+        assert_eq!(vec!["a1a8"], uci(&r.pv));
+        assert_eq!(Some(1), r.mate_in());
+
+        // Mate in two with a queen sacrifice.
+        let r = search(
+            "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 0",
+            &SearchLimits::depth(5),
+        );
+        assert_eq!(Some(2), r.mate_in());
+        assert_eq!(vec!["d5d8", "e7d8", "e1e8"], uci(&r.pv));
+
+        // Black is mated in one whatever it does.
+        let r = search("7k/8/6KQ/8/8/8/8/8 b - - 0 1", &SearchLimits::depth(4));
+        assert_eq!(Some(-1), r.mate_in());
+    }
+
+    #[test]
+    fn no_legal_moves() {
+        let r = search("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1", &SearchLimits::depth(3));
+        assert_eq!(None, r.best_move);
+        assert_eq!(Some(0), r.mate_in());
+
+        let r = search("7k/8/6QK/8/8/8/8/8 b - - 0 1", &SearchLimits::depth(3));
+        assert_eq!(None, r.best_move);
+        assert_eq!(0, r.score);
+    }
+
+    #[test]
+    fn wins_material() {
+        // The knight forks king and rook.
+        let r = search("r3k3/8/8/1N6/8/8/7P/4K3 w - - 0 1", &SearchLimits::depth(4));
+        assert_eq!(Some("b5c7"), r.best_move.map(|m| m.to_uci()).as_deref());
+        assert_eq!(400, r.score);
+
+        // The queen is defended, but giving up the rook for it is still the best deal.
+        let r = search(
+            "4k3/3p4/4q3/8/8/8/4R3/4K3 w - - 0 1",
+            &SearchLimits::depth(3),
+        );
+        assert_eq!(Some("e2e6"), r.best_move.map(|m| m.to_uci()).as_deref());
+        assert_eq!(-100, r.score);
+    }
+
+    #[test]
+    fn draws() {
+        // Nothing can be won with a lone knight.
+        let r = search("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", &SearchLimits::depth(3));
+        assert_eq!(0, r.score);
+
+        // Going back to e8 repeats the position, which saves black a rook down.
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        for uci in ["e1e2", "e8e7", "e2e1"] {
+            b.make_move(b.parse_uci_move(uci).unwrap());
+        }
+        let r = Searcher::new().search(&b, &SearchLimits::depth(3));
+        assert_eq!(Some("e7e8"), r.best_move.map(|m| m.to_uci()).as_deref());
+        assert_eq!(0, r.score);
+    }
+
+    #[test]
+    fn principal_variation() {
+        let board = Board::default();
+        let mut reports: Vec<SearchResult> = Vec::new();
+        let r = Searcher::new()
+            .search_with(&board, &SearchLimits::depth(4), |r| reports.push(r.clone()));
+
+        assert_eq!(4, reports.len());
+        assert_eq!(Some(&r.pv), reports.last().map(|r| &r.pv));
+        assert_eq!(4, r.depth);
+        assert!(r.seldepth >= 4);
+        assert_eq!(r.best_move, r.pv.first().copied());
+
+        let mut b = board.clone();
+        for m in &r.pv {
+            assert!(b.legal_moves().contains(m));
+            b.make_move(*m);
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let r = search(
+            Board::default().to_fen().as_str(),
+            &SearchLimits::nodes(2_000),
+        );
+        assert!(r.nodes <= 2_000);
+        assert!(r.best_move.is_some());
+
+        let start = Instant::now();
+        let r = search(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &SearchLimits::time(Duration::from_millis(50)),
+        );
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(r.best_move.is_some());
+
+        // A search stopped before it starts still comes up with a legal move.
+        let stop = Arc::new(AtomicBool::new(true));
+        let limits = SearchLimits {
+            stop: Some(Arc::clone(&stop)),
+            ..Default::default()
+        };
+        let r = search(Board::default().to_fen().as_str(), &limits);
+        assert_eq!(0, r.depth);
+        assert!(Board::default()
+            .legal_moves()
+            .contains(&r.best_move.unwrap()));
+    }
+
+    #[test]
+    fn transposition_table() {
+        assert_eq!(MATE_SCORE - 3, score_to_tt(MATE_SCORE - 5, 2));
+        assert_eq!(5 - MATE_SCORE, score_from_tt(3 - MATE_SCORE, 2));
+        assert_eq!(-250, score_from_tt(score_to_tt(-250, 7), 7));
+
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let mut searcher = Searcher::new();
+        let first = searcher.search(&board, &SearchLimits::depth(4));
+        assert!(first.hashfull > 0);
+        assert!(searcher.tt().probe(board.zobrist_key()).is_some());
+
+        // What the first search stored saves most of the work of the second one.
+        let second = searcher.search(&board, &SearchLimits::depth(4));
+        assert!(second.nodes < first.nodes / 2);
+        assert_eq!(first.best_move, second.best_move);
+
+        searcher.clear();
+        assert_eq!(None, searcher.tt().probe(board.zobrist_key()));
+
+        // Mates found through the table keep their distance.
+        let r = searcher.search(
+            &Board::from_fen("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 0"),
+            &SearchLimits::depth(6),
+        );
+        assert_eq!(Some(2), r.mate_in());
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        struct Fixed;
+        impl Evaluator for Fixed {
+            fn evaluate(&mut self, _: &Board) -> i32 {
+                42
+            }
+        }
+        let r = Searcher::with_evaluator(Fixed).search(&Board::default(), &SearchLimits::depth(2));
+        assert_eq!(42, r.score);
+    }
+}
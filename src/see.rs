@@ -0,0 +1,202 @@
+use crate::attacks::{bishop_attacks, rook_attacks};
+use crate::bitboard::{Bitboard, EMPTY};
+use crate::board::Board;
+use crate::chess_move::Move;
+use crate::color::Color;
+use crate::piece::{Piece, NUM_PIECES, PIECES};
+use crate::square::Square;
+
+/// The value of every piece type in centipawns, indexed by [`Piece::as_index()`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PieceValues(pub [i32; NUM_PIECES]);
+
+/// The classical piece values, with a king worth more than everything else combined.
+pub const DEFAULT_PIECE_VALUES: PieceValues = PieceValues([100, 300, 300, 500, 900, 20_000]);
+
+impl PieceValues {
+    /// Get the value of a piece.
+    pub fn of(&self, p: Piece) -> i32 {
+        self.0[p.as_index()]
+    }
+}
+
+impl Default for PieceValues {
+    fn default() -> Self {
+        DEFAULT_PIECE_VALUES
+    }
+}
+
+impl Board {
+    /// Statically evaluate the exchange started by a move, using the [`DEFAULT_PIECE_VALUES`].
+    ///
+    /// See [`Board::see_with()`].
+    pub fn see(&self, m: Move) -> i32 {
+        self.see_with(m, &DEFAULT_PIECE_VALUES)
+    }
+
+    /// Statically evaluate the material balance of the exchange started by a move.
+    ///
+    /// Both sides keep capturing on the destination square with their least valuable attacker,
+    /// sliders behind the capturing pieces joining in, and may stop whenever continuing would
+    /// lose material. The result is the material won by the side to move, which is negative
+    /// if the move loses material and zero for castling. Pins are not taken into account and
+    /// only the move itself may promote.
+    pub fn see_with(&self, m: Move, values: &PieceValues) -> i32 {
+        let (from, to) = (m.from(), m.to());
+        let mut attacker: Piece = match self.piece_at(from) {
+            Some(p) if !m.is_castle() => p,
+            _ => return 0,
+        };
+
+        let mut occupied: Bitboard = self.occupied() ^ Bitboard::from_square(from);
+        let mut gain: [i32; 32] = [0; 32];
+        gain[0] = if m.is_en_passant() {
+            let captured: Square = match self.side_to_move() {
+                Color::White => Square(to.0 + 8),
+                Color::Black => Square(to.0 - 8),
+            };
+            occupied ^= Bitboard::from_square(captured);
+            values.of(Piece::Pawn)
+        } else {
+            self.piece_at(to).map_or(0, |p| values.of(p))
+        };
+        if let Some(p) = m.promotion() {
+            gain[0] += values.of(p) - values.of(Piece::Pawn);
+            attacker = p;
+        }
+
+        let bishops: Bitboard = self.sliders(Piece::Bishop);
+        let rooks: Bitboard = self.sliders(Piece::Rook);
+        let mut attackers: Bitboard = self.attackers_to_occupied(to, occupied) & occupied;
+        let mut side: Color = !self.side_to_move();
+        let mut d: usize = 0;
+
+        loop {
+            d += 1;
+            // Speculatively assume that the last capturing piece is taken in turn.
+            gain[d] = values.of(attacker) - gain[d - 1];
+            if (-gain[d - 1]).max(gain[d]) < 0 {
+                break;
+            }
+
+            let (s, p) = match self.least_valuable_attacker(attackers & self.color_bitboard(side)) {
+                Some(lva) => lva,
+                None => break,
+            };
+            occupied ^= Bitboard::from_square(s);
+            attackers = (attackers
+                | (bishop_attacks(to, occupied) & bishops)
+                | (rook_attacks(to, occupied) & rooks))
+                & occupied;
+
+            // The king may only capture if nothing can take it back.
+            if p == Piece::King && attackers & self.color_bitboard(!side) != EMPTY {
+                break;
+            }
+
+            attacker = p;
+            side = !side;
+            if d + 1 == gain.len() {
+                break;
+            }
+        }
+
+        while d > 1 {
+            d -= 1;
+            gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+        }
+        gain[0]
+    }
+
+    /// Check whether the exchange started by a move wins at least `threshold` centipawns,
+    /// using the [`DEFAULT_PIECE_VALUES`].
+    pub fn see_ge(&self, m: Move, threshold: i32) -> bool {
+        self.see_with(m, &DEFAULT_PIECE_VALUES) >= threshold
+    }
+
+    /// Check whether the exchange started by a move wins at least `threshold` centipawns.
+    pub fn see_ge_with(&self, m: Move, threshold: i32, values: &PieceValues) -> bool {
+        self.see_with(m, values) >= threshold
+    }
+
+    /// All pieces of both colors that slide like the given piece, including queens.
+    fn sliders(&self, p: Piece) -> Bitboard {
+        [p, Piece::Queen].iter().fold(EMPTY, |acc, p| {
+            acc | self.piece_bitboard(*p, Color::White) | self.piece_bitboard(*p, Color::Black)
+        })
+    }
+
+    /// The least valuable piece among `attackers` together with its square.
+    fn least_valuable_attacker(&self, attackers: Bitboard) -> Option<(Square, Piece)> {
+        PIECES.iter().find_map(|p| {
+            let bb: Bitboard = attackers
+                & (self.piece_bitboard(*p, Color::White) | self.piece_bitboard(*p, Color::Black));
+            bb.lsb().map(|s| (s, *p))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see(fen: &str, uci: &str) -> i32 {
+        let b = Board::from_fen(fen);
+        b.see(b.parse_uci_move(uci).unwrap())
+    }
+
+    #[test]
+    fn simple_captures() {
+        assert_eq!(100, see("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"));
+        assert_eq!(
+            100,
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5")
+        );
+        // The pawn on d6 is defended by the pawn on c7.
+        assert_eq!(-400, see("4k3/2p5/3p4/8/8/8/3R4/4K3 w - - 0 1", "d2d6"));
+        assert_eq!(
+            -200,
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5"
+            )
+        );
+    }
+
+    #[test]
+    fn x_rays() {
+        // The second rook joins in once the first one has captured.
+        assert_eq!(100, see("3rk3/8/3p4/8/8/3R4/3R4/4K3 w - - 0 1", "d3d6"));
+        // Black stops after the first recapture rather than losing the rook too.
+        assert_eq!(-400, see("3rk3/3r4/3p4/8/8/3R4/8/4K3 w - - 0 1", "d3d6"));
+    }
+
+    #[test]
+    fn king_recaptures() {
+        assert_eq!(-400, see("4k3/4p3/8/8/8/8/4R3/4K3 w - - 0 1", "e2e7"));
+        // The rook behind makes the recapture by the king illegal.
+        assert_eq!(100, see("4k3/4p3/8/8/8/8/4R3/K3R3 w - - 0 1", "e2e7"));
+    }
+
+    #[test]
+    fn special_moves() {
+        assert_eq!(1_300, see("3r3k/2P5/8/8/8/8/8/K7 w - - 0 1", "c7d8q"));
+        assert_eq!(100, see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"));
+        assert_eq!(0, see("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"));
+        // A quiet move onto a square attacked by a pawn gives the piece away.
+        assert_eq!(-300, see("4k3/8/2p5/8/1N6/8/8/4K3 w - - 0 1", "b4d5"));
+    }
+
+    #[test]
+    fn threshold_and_values() {
+        let b = Board::from_fen("4k3/2p5/3p4/8/8/8/3R4/4K3 w - - 0 1");
+        let m = b.parse_uci_move("d2d6").unwrap();
+        assert!(b.see_ge(m, -400));
+        assert!(!b.see_ge(m, -399));
+
+        let values = PieceValues([100, 325, 325, 200, 975, 20_000]);
+        assert_eq!(-100, b.see_with(m, &values));
+        assert!(b.see_ge_with(m, -100, &values));
+        assert!(!b.see_ge_with(m, 0, &values));
+    }
+}
@@ -0,0 +1,12 @@
+use crate::bitboard::Bitboard;
+use crate::square::Square;
+
+/// Parse a square in algebraic notation, e.g. `e4`.
+pub(crate) fn sq(s: &str) -> Square {
+    Square::from_str(s)
+}
+
+/// Collect the squares given in algebraic notation into a bitboard.
+pub(crate) fn bb(squares: &[&str]) -> Bitboard {
+    squares.iter().map(|s| sq(s)).collect()
+}
@@ -0,0 +1,434 @@
+use crate::chess_move::{Move, NULL_MOVE};
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// The size of a [`TranspositionTable`] that is not given one, in megabytes.
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+
+/// The number of distinct ages, after which they wrap around.
+const AGE_CYCLE: u8 = 64;
+
+/// Exhaustive enum of the ways a [`TranspositionTable`] decides which entry of a bucket to
+/// overwrite when storing a position it does not hold yet.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ReplacementScheme {
+    /// The first entry of a bucket keeps the deepest search, the second one takes whatever
+    /// the first does not.
+    #[default]
+    TwoTier,
+    /// Overwrite the least valuable entry only if the new one was searched at least as deep,
+    /// or the old one is from an earlier search.
+    DepthPreferred,
+    /// Always overwrite the least valuable entry.
+    AlwaysReplace,
+}
+
+/// Exhaustive enum of how a score relates to the true one.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ScoreBound {
+    #[default]
+    Exact,
+    /// The true score is at least the given one.
+    Lower,
+    /// The true score is at most the given one.
+    Upper,
+}
+
+/// A position stored in a [`TranspositionTable`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: ScoreBound,
+    /// The age of the table when the entry was stored.
+    pub age: u8,
+}
+
+impl TtEntry {
+    /// Pack the entry into 64 bits, which are never all zero so that empty slots stand out.
+    ///
+    /// The move takes bits 0-15, the score bits 16-31, the depth bits 32-39, the bound bits
+    /// 40-41 and the age bits 42-47.
+    fn pack(&self) -> u64 {
+        let bound: u64 = match self.bound {
+            ScoreBound::Exact => 1,
+            ScoreBound::Lower => 2,
+            ScoreBound::Upper => 3,
+        };
+        debug_assert!(
+            (i16::MIN as i32..=i16::MAX as i32).contains(&self.score),
+            "score {} does not fit into 16 bits",
+            self.score
+        );
+        let score: i16 = self.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        self.best_move.unwrap_or(NULL_MOVE).0 as u64
+            | ((score as u16 as u64) << 16)
+            | ((self.depth as u64) << 32)
+            | (bound << 40)
+            | (((self.age % AGE_CYCLE) as u64) << 42)
+    }
+
+    fn unpack(data: u64) -> Option<TtEntry> {
+        let bound: ScoreBound = match (data >> 40) & 3 {
+            1 => ScoreBound::Exact,
+            2 => ScoreBound::Lower,
+            3 => ScoreBound::Upper,
+            _ => return None,
+        };
+        let m: Move = Move(data as u16);
+        Some(TtEntry {
+            best_move: if m.is_null() { None } else { Some(m) },
+            score: (data >> 16) as u16 as i16 as i32,
+            depth: (data >> 32) as u8,
+            bound,
+            age: ((data >> 42) & (AGE_CYCLE as u64 - 1)) as u8,
+        })
+    }
+}
+
+/// A slot holding the key XORed with the data, so that a slot torn by concurrent writes no
+/// longer matches the key of either write.
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Bucket {
+    slots: [Slot; 2],
+}
+
+/// A hash table of searched positions, keyed by [`Board::zobrist_key()`], that threads can
+/// share without locking.
+///
+/// Scores are stored as given, so mate scores have to be made relative to the position by
+/// the caller. Entries are not verified beyond their key, so a best move read from the table
+/// has to be checked for legality before it is played.
+///
+/// [`Board::zobrist_key()`]: crate::board::Board::zobrist_key
+#[derive(Debug)]
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+    scheme: ReplacementScheme,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_TT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Create a new empty [`TranspositionTable`] taking up `size_mb` megabytes, and at least
+    /// one bucket.
+    pub fn new(size_mb: usize) -> Self {
+        TranspositionTable::with_scheme(size_mb, ReplacementScheme::default())
+    }
+
+    /// Create a new empty [`TranspositionTable`] that replaces entries according to `scheme`.
+    pub fn with_scheme(size_mb: usize, scheme: ReplacementScheme) -> Self {
+        TranspositionTable {
+            buckets: new_buckets(size_mb),
+            age: AtomicU8::new(0),
+            scheme,
+        }
+    }
+
+    /// Get the replacement scheme.
+    pub fn scheme(&self) -> ReplacementScheme {
+        self.scheme
+    }
+
+    /// Get the number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * 2
+    }
+
+    /// Get the size of the table in megabytes, rounded down.
+    pub fn size_mb(&self) -> usize {
+        self.buckets.len() * std::mem::size_of::<Bucket>() / (1024 * 1024)
+    }
+
+    /// Change the size of the table, which empties it.
+    pub fn resize(&mut self, size_mb: usize) {
+        self.buckets = new_buckets(size_mb);
+    }
+
+    /// Empty the table.
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|b| b.slots.iter()) {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Get the current age, which tells entries from earlier searches apart.
+    pub fn age(&self) -> u8 {
+        self.age.load(Ordering::Relaxed)
+    }
+
+    /// Age the table before a new search, so that old entries are replaced first.
+    pub fn new_search(&self) {
+        let age: u8 = (self.age() + 1) % AGE_CYCLE;
+        self.age.store(age, Ordering::Relaxed);
+    }
+
+    /// Look up the entry for a position.
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        self.bucket(key).slots.iter().find_map(|slot| {
+            let data: u64 = slot.data.load(Ordering::Relaxed);
+            if slot.key.load(Ordering::Relaxed) ^ data == key {
+                TtEntry::unpack(data)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Store the result of searching a position, unless the replacement scheme keeps the
+    /// entries already there.
+    ///
+    /// The score has to fit into 16 bits and is clamped to them in release builds, the depth
+    /// is clamped to 0-255.
+    pub fn store(
+        &self,
+        key: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: i32,
+        bound: ScoreBound,
+    ) {
+        let age: u8 = self.age();
+        let mut entry: TtEntry = TtEntry {
+            best_move,
+            score,
+            depth: depth.clamp(0, u8::MAX as i32) as u8,
+            bound,
+            age,
+        };
+
+        let bucket: &Bucket = self.bucket(key);
+        let stored: [(u64, Option<TtEntry>); 2] = [0, 1].map(|i| {
+            let slot: &Slot = &bucket.slots[i];
+            let data: u64 = slot.data.load(Ordering::Relaxed);
+            (
+                slot.key.load(Ordering::Relaxed) ^ data,
+                TtEntry::unpack(data),
+            )
+        });
+
+        let i: usize = match stored.iter().position(|(k, e)| *k == key && e.is_some()) {
+            Some(i) => {
+                // A search that found no best move should not erase an earlier one.
+                if entry.best_move.is_none() {
+                    entry.best_move = stored[i].1.and_then(|e| e.best_move);
+                }
+                i
+            }
+            None => {
+                // Old entries are worth less than new ones of the same depth.
+                let worth = |e: &Option<TtEntry>| match e {
+                    Some(e) => e.depth as i32 - 8 * ((age + AGE_CYCLE - e.age) % AGE_CYCLE) as i32,
+                    None => i32::MIN,
+                };
+                let replaceable = |e: &Option<TtEntry>| match e {
+                    Some(e) => e.age != age || entry.depth >= e.depth,
+                    None => true,
+                };
+                let least: usize = if worth(&stored[1].1) < worth(&stored[0].1) {
+                    1
+                } else {
+                    0
+                };
+                match self.scheme {
+                    // Empty slots are filled first.
+                    _ if stored[least].1.is_none() => least,
+                    ReplacementScheme::TwoTier if replaceable(&stored[0].1) => 0,
+                    ReplacementScheme::TwoTier => 1,
+                    ReplacementScheme::DepthPreferred if replaceable(&stored[least].1) => least,
+                    ReplacementScheme::DepthPreferred => return,
+                    ReplacementScheme::AlwaysReplace => least,
+                }
+            }
+        };
+
+        let data: u64 = entry.pack();
+        let slot: &Slot = &bucket.slots[i];
+        slot.data.store(data, Ordering::Relaxed);
+        slot.key.store(key ^ data, Ordering::Relaxed);
+    }
+
+    /// Get how full the table is in per mille, counting only entries of the current age
+    /// among the first thousand or so.
+    pub fn hashfull(&self) -> u32 {
+        let age: u8 = self.age();
+        let sample: &[Bucket] = &self.buckets[..self.buckets.len().min(500)];
+        let used: usize = sample
+            .iter()
+            .flat_map(|b| b.slots.iter())
+            .filter_map(|slot| TtEntry::unpack(slot.data.load(Ordering::Relaxed)))
+            .filter(|e| e.age == age)
+            .count();
+        (used * 1000 / (sample.len() * 2)) as u32
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        // Maps the key onto the buckets without requiring a power of two.
+        let i: usize = ((key as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[i]
+    }
+}
+
+fn new_buckets(size_mb: usize) -> Vec<Bucket> {
+    let count: usize = (size_mb * 1024 * 1024 / std::mem::size_of::<Bucket>()).max(1);
+    (0..count).map(|_| Bucket::default()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    use std::sync::Arc;
+    use std::thread;
+
+    fn entry(tt: &TranspositionTable, key: u64) -> Option<(i32, u8)> {
+        tt.probe(key).map(|e| (e.score, e.depth))
+    }
+
+    /// A table with a single bucket, which every key maps to.
+    fn single_bucket() -> TranspositionTable {
+        TranspositionTable::with_scheme(0, ReplacementScheme::TwoTier)
+    }
+
+    #[test]
+    fn store_and_probe() {
+        let tt: TranspositionTable = TranspositionTable::new(1);
+        assert_eq!(1, tt.size_mb());
+        assert_eq!(1024 * 1024 / 16, tt.capacity());
+
+        let board: Board = Board::default();
+        let m: Move = board.parse_uci_move("e2e4").unwrap();
+        let key: u64 = board.zobrist_key();
+        assert_eq!(None, tt.probe(key));
+
+        tt.store(key, Some(m), -31_950, 300, ScoreBound::Lower);
+        let expected: TtEntry = TtEntry {
+            best_move: Some(m),
+            score: -31_950,
+            depth: 255,
+            bound: ScoreBound::Lower,
+            age: 0,
+        };
+        assert_eq!(Some(expected), tt.probe(key));
+        assert_eq!(None, tt.probe(key ^ 1));
+
+        // The same position is always replaced, but keeps its best move.
+        tt.store(key, None, 12, 1, ScoreBound::Upper);
+        let entry: TtEntry = tt.probe(key).unwrap();
+        assert_eq!(
+            (Some(m), 12, 1),
+            (entry.best_move, entry.score, entry.depth)
+        );
+        assert_eq!(ScoreBound::Upper, entry.bound);
+    }
+
+    #[test]
+    fn replacement() {
+        let tt: TranspositionTable = single_bucket();
+        assert_eq!(2, tt.capacity());
+        tt.store(1, None, 1, 5, ScoreBound::Exact);
+        tt.store(2, None, 2, 3, ScoreBound::Exact);
+        // The shallower search goes to the second slot.
+        tt.store(3, None, 3, 4, ScoreBound::Exact);
+        assert_eq!(Some((1, 5)), entry(&tt, 1));
+        assert_eq!(None, entry(&tt, 2));
+        assert_eq!(Some((3, 4)), entry(&tt, 3));
+        // A new search frees the first slot.
+        tt.new_search();
+        tt.store(4, None, 4, 1, ScoreBound::Exact);
+        assert_eq!(None, entry(&tt, 1));
+        assert_eq!(Some((4, 1)), entry(&tt, 4));
+
+        let tt: TranspositionTable =
+            TranspositionTable::with_scheme(0, ReplacementScheme::DepthPreferred);
+        tt.store(1, None, 1, 5, ScoreBound::Exact);
+        tt.store(2, None, 2, 3, ScoreBound::Exact);
+        tt.store(3, None, 3, 2, ScoreBound::Exact);
+        assert_eq!(None, entry(&tt, 3));
+        tt.store(3, None, 3, 3, ScoreBound::Exact);
+        assert_eq!(None, entry(&tt, 2));
+        assert_eq!(Some((3, 3)), entry(&tt, 3));
+        tt.new_search();
+        tt.store(4, None, 4, 0, ScoreBound::Exact);
+        assert_eq!(None, entry(&tt, 3));
+        assert_eq!(Some((1, 5)), entry(&tt, 1));
+
+        let tt: TranspositionTable =
+            TranspositionTable::with_scheme(0, ReplacementScheme::AlwaysReplace);
+        tt.store(1, None, 1, 5, ScoreBound::Exact);
+        tt.store(2, None, 2, 3, ScoreBound::Exact);
+        tt.store(3, None, 3, 0, ScoreBound::Exact);
+        assert_eq!(None, entry(&tt, 2));
+        assert_eq!(Some((3, 0)), entry(&tt, 3));
+    }
+
+    #[test]
+    fn hashfull_and_clear() {
+        let mut tt: TranspositionTable = TranspositionTable::new(1);
+        assert_eq!(0, tt.hashfull());
+        for key in 0..200_000u64 {
+            tt.store(
+                key.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                None,
+                0,
+                1,
+                ScoreBound::Exact,
+            );
+        }
+        assert!(tt.hashfull() > 950);
+
+        // Entries of earlier searches do not count.
+        tt.new_search();
+        assert_eq!(0, tt.hashfull());
+        tt.store(0, None, 0, 1, ScoreBound::Exact);
+        assert_eq!(1, tt.age());
+
+        tt.clear();
+        assert_eq!(0, tt.age());
+        assert_eq!(0, tt.hashfull());
+        assert_eq!(None, tt.probe(0));
+
+        tt.resize(2);
+        assert_eq!(2, tt.size_mb());
+        assert_eq!(0, tt.hashfull());
+    }
+
+    #[test]
+    fn concurrent_writes() {
+        let tt: Arc<TranspositionTable> = Arc::new(single_bucket());
+        let threads: Vec<_> = (0..4u64)
+            .map(|t| {
+                let tt: Arc<TranspositionTable> = tt.clone();
+                thread::spawn(move || {
+                    for i in 0..20_000u64 {
+                        let key: u64 = (i % 8) << 8 | t;
+                        // Every key has its own score, so a torn entry would be noticed.
+                        tt.store(key, None, key as i32, 1, ScoreBound::Exact);
+                        let probed: u64 = (i % 8) << 8 | ((t + 1) % 4);
+                        if let Some(entry) = tt.probe(probed) {
+                            assert_eq!(probed as i32, entry.score);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}
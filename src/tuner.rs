@@ -0,0 +1,677 @@
+use crate::board::{Board, BoardBuilder};
+use crate::chess_move::Move;
+use crate::error::{ChessifyError, Result};
+use crate::eval::{EvalFeatures, EvalParams, Score, MAX_PHASE, NUM_EVAL_WEIGHTS};
+use crate::pgn::GameResult;
+
+use std::cmp::Reverse;
+use std::error;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+/// The deepest the quiescence search that resolves training positions goes.
+const MAX_QUIESCENCE_PLY: usize = 32;
+
+/// Exhaustive enum of the reasons tuning data can be invalid.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TuningErrorKind {
+    /// A training position is not followed by a label.
+    MissingLabel,
+    /// A label is neither a game result nor an engine score.
+    InvalidLabel(String),
+    /// A training position is not a valid FEN string.
+    InvalidFen(String),
+    /// A weight is not named as in [`EvalParams::weight_name()`].
+    UnknownWeight(String),
+    /// A weight is not followed by a middlegame and an endgame value.
+    InvalidWeight(String),
+}
+
+impl fmt::Display for TuningErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningErrorKind::MissingLabel => write!(f, "position without a label"),
+            TuningErrorKind::InvalidLabel(s) => write!(f, "`{}` is not a valid label", s),
+            TuningErrorKind::InvalidFen(s) => write!(f, "`{}` is an invalid FEN string", s),
+            TuningErrorKind::UnknownWeight(s) => write!(f, "`{}` is not a known weight", s),
+            TuningErrorKind::InvalidWeight(s) => write!(f, "`{}` is not a valid weight", s),
+        }
+    }
+}
+
+fn tuning_error(line: usize, kind: TuningErrorKind) -> Box<dyn error::Error> {
+    Box::new(ChessifyError::InvalidTuningData { line, kind })
+}
+
+/// What a training position is known to be worth.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TrainingLabel {
+    /// The result of the game the position was taken from.
+    Result(GameResult),
+    /// An engine score in centipawns from the point of view of white.
+    Score(i32),
+}
+
+/// A position to fit the evaluation to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingPosition {
+    pub board: Board,
+    pub label: TrainingLabel,
+}
+
+impl TrainingPosition {
+    /// Try and parse a line of a training data set, which is a FEN string followed by a label.
+    ///
+    /// The label is either a game result like `1-0`, optionally in quotes or in the bracketed
+    /// form `[1.0]`, `[0.5]` and `[0.0]`, or an engine score like `cp -35` from the point of
+    /// view of white. The clocks of the FEN string may be left out, anything else between it
+    /// and the label such as EPD opcodes is ignored, as is a trailing `;`.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidTuningData`] error at line 1 if the label is missing or
+    /// invalid, or the FEN string is rejected by [`BoardBuilder::try_from_fen()`].
+    pub fn try_from_line(line: &str) -> Result<Self> {
+        TrainingPosition::parse(line).map_err(|kind| tuning_error(1, kind))
+    }
+
+    fn parse(line: &str) -> std::result::Result<Self, TuningErrorKind> {
+        let tokens: Vec<&str> = line
+            .trim_end()
+            .trim_end_matches(';')
+            .split_whitespace()
+            .collect();
+        // A full FEN string without anything after it ends in the clocks, not in a label.
+        let full_fen: bool =
+            tokens.len() == 6 && tokens[4..].iter().all(|t| t.parse::<usize>().is_ok());
+        if tokens.len() <= 4 || full_fen {
+            return Err(TuningErrorKind::MissingLabel);
+        }
+
+        let last: &str = tokens[tokens.len() - 1].trim_matches('"');
+        let (label, label_len): (TrainingLabel, usize) = if tokens[tokens.len() - 2] == "cp" {
+            let score: i32 = last
+                .parse()
+                .map_err(|_| TuningErrorKind::InvalidLabel(last.to_string()))?;
+            (TrainingLabel::Score(score), 2)
+        } else {
+            let result: Option<GameResult> = match last {
+                "[1.0]" | "[1]" => Some(GameResult::WhiteWins),
+                "[0.5]" => Some(GameResult::Draw),
+                "[0.0]" | "[0]" => Some(GameResult::BlackWins),
+                _ => GameResult::try_from_str(last).filter(|r| *r != GameResult::Unknown),
+            };
+            match result {
+                Some(result) => (TrainingLabel::Result(result), 1),
+                None => return Err(TuningErrorKind::InvalidLabel(last.to_string())),
+            }
+        };
+
+        // The clocks are the only optional fields that are numbers.
+        let before_label: &[&str] = &tokens[..tokens.len() - label_len];
+        let clocks: usize = before_label[4.min(before_label.len())..]
+            .iter()
+            .take(2)
+            .take_while(|t| t.parse::<usize>().is_ok())
+            .count();
+        let fields: usize = if clocks == 2 { 6 } else { 4 };
+        if before_label.len() < fields {
+            return Err(TuningErrorKind::MissingLabel);
+        }
+        let fen: String = before_label[..fields].join(" ");
+        let board: Board = BoardBuilder::try_from_fen(&fen)
+            .and_then(|builder| builder.try_build())
+            .map_err(|_| TuningErrorKind::InvalidFen(fen.clone()))?;
+
+        Ok(TrainingPosition { board, label })
+    }
+}
+
+/// Read a training data set with one position per line, see
+/// [`TrainingPosition::try_from_line()`].
+///
+/// Empty lines and lines starting with `#` are skipped.
+///
+/// # Errors
+/// Returns a [`ChessifyError::InvalidTuningData`] error with the number of the first invalid
+/// line, or the underlying I/O error if reading fails.
+pub fn read_training_positions<R: BufRead>(reader: R) -> Result<Vec<TrainingPosition>> {
+    let mut positions: Vec<TrainingPosition> = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let position: TrainingPosition =
+            TrainingPosition::parse(&line).map_err(|kind| tuning_error(i + 1, kind))?;
+        positions.push(position);
+    }
+    Ok(positions)
+}
+
+/// The settings of a [`Tuner`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TunerConfig {
+    /// The constant `K` the evaluation is scaled by before the sigmoid, which is fitted to the
+    /// data if [`None`].
+    pub scale: Option<f64>,
+    /// The largest step a weight takes at once, in centipawns.
+    pub learning_rate: f64,
+    /// The decay rate of the average gradient.
+    pub beta1: f64,
+    /// The decay rate of the average squared gradient.
+    pub beta2: f64,
+    /// Whether to play out the captures quiescence search expects before evaluating a
+    /// position, so that the evaluation does not need to account for hanging pieces.
+    pub quiesce: bool,
+}
+
+impl Default for TunerConfig {
+    fn default() -> Self {
+        TunerConfig {
+            scale: None,
+            learning_rate: 1.0,
+            beta1: 0.9,
+            beta2: 0.999,
+            quiesce: true,
+        }
+    }
+}
+
+/// A position reduced to what the evaluation sees, with what it should evaluate to.
+struct Sample {
+    features: EvalFeatures,
+    label: TrainingLabel,
+}
+
+/// A Texel tuner, which fits the weights of [`EvalParams`] to labeled positions.
+///
+/// The evaluation of a position from the point of view of white is mapped to an expected game
+/// result by the sigmoid `1 / (1 + 10^(-K * eval / 400))`, which is compared to the game
+/// result or to the engine score mapped the same way. The mean squared error is minimized by
+/// the Adam optimizer, every step of which goes through all positions.
+pub struct Tuner {
+    config: TunerConfig,
+    samples: Vec<Sample>,
+    scale: f64,
+    /// The middlegame and endgame value of every weight, interleaved.
+    weights: Vec<f64>,
+    /// The moving averages of the gradient and of its square.
+    m: Vec<f64>,
+    v: Vec<f64>,
+    steps: usize,
+}
+
+impl Tuner {
+    /// Create a new [`Tuner`] starting from `params`, which also resolve the positions if the
+    /// configuration asks for it.
+    pub fn new(params: &EvalParams, positions: &[TrainingPosition], config: TunerConfig) -> Self {
+        let samples: Vec<Sample> = positions
+            .iter()
+            .map(|p| {
+                let board: Board = if config.quiesce {
+                    resolve(&p.board, params)
+                } else {
+                    p.board.clone()
+                };
+                Sample {
+                    features: EvalFeatures::new(&board),
+                    label: p.label,
+                }
+            })
+            .collect();
+        let weights: Vec<f64> = params
+            .weights()
+            .iter()
+            .flat_map(|w| [w.mg as f64, w.eg as f64])
+            .collect();
+
+        let mut tuner: Tuner = Tuner {
+            samples,
+            scale: config.scale.unwrap_or(1.0),
+            m: vec![0.0; weights.len()],
+            v: vec![0.0; weights.len()],
+            weights,
+            steps: 0,
+            config,
+        };
+        if tuner.config.scale.is_none() {
+            tuner.scale = tuner.fit_scale();
+        }
+        tuner
+    }
+
+    /// Get the constant `K` the evaluation is scaled by.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Get the number of steps taken so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Get the current weights, rounded to whole centipawns.
+    pub fn params(&self) -> EvalParams {
+        let weights: Vec<Score> = self
+            .weights
+            .chunks(2)
+            .map(|w| Score::new(w[0].round() as i32, w[1].round() as i32))
+            .collect();
+        EvalParams::from_weights(&weights)
+    }
+
+    /// Get the mean squared error of the current weights.
+    pub fn error(&self) -> f64 {
+        self.error_with_scale(self.scale)
+    }
+
+    /// Take a step towards better weights, returning the error before it.
+    pub fn step(&mut self) -> f64 {
+        let mut gradient: Vec<f64> = vec![0.0; self.weights.len()];
+        let mut error: f64 = 0.0;
+        // The derivative of the sigmoid without the factor `s * (1 - s)`.
+        let slope: f64 = self.scale * std::f64::consts::LN_10 / 400.0;
+        for sample in &self.samples {
+            let s: f64 = sigmoid(self.scale, self.evaluate(&sample.features));
+            let diff: f64 = target(sample.label, self.scale) - s;
+            error += diff * diff;
+
+            let d: f64 = -2.0 * diff * s * (1.0 - s) * slope;
+            let mg: f64 = sample.features.phase as f64 / MAX_PHASE as f64;
+            for (i, count) in &sample.features.counts {
+                gradient[2 * i] += d * *count as f64 * mg;
+                gradient[2 * i + 1] += d * *count as f64 * (1.0 - mg);
+            }
+        }
+
+        let n: f64 = self.samples.len().max(1) as f64;
+        self.steps += 1;
+        let (beta1, beta2) = (self.config.beta1, self.config.beta2);
+        for (i, g) in gradient.iter().enumerate() {
+            let g: f64 = g / n;
+            self.m[i] = beta1 * self.m[i] + (1.0 - beta1) * g;
+            self.v[i] = beta2 * self.v[i] + (1.0 - beta2) * g * g;
+            let m: f64 = self.m[i] / (1.0 - beta1.powi(self.steps as i32));
+            let v: f64 = self.v[i] / (1.0 - beta2.powi(self.steps as i32));
+            self.weights[i] -= self.config.learning_rate * m / (v.sqrt() + 1e-8);
+        }
+        error / n
+    }
+
+    /// Take `steps` steps, calling `report` with the number of steps taken and the error
+    /// before the last one after each of them.
+    pub fn tune<F: FnMut(usize, f64)>(&mut self, steps: usize, mut report: F) {
+        for _ in 0..steps {
+            let error: f64 = self.step();
+            report(self.steps, error);
+        }
+    }
+
+    /// Find the `K` that minimizes the error of the current weights.
+    fn fit_scale(&self) -> f64 {
+        // The error is unimodal in `K`, so ternary search finds its minimum.
+        let (mut low, mut high) = (0.0f64, 10.0f64);
+        for _ in 0..100 {
+            let a: f64 = low + (high - low) / 3.0;
+            let b: f64 = high - (high - low) / 3.0;
+            if self.error_with_scale(a) < self.error_with_scale(b) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        (low + high) / 2.0
+    }
+
+    fn error_with_scale(&self, scale: f64) -> f64 {
+        let sum: f64 = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let s: f64 = sigmoid(scale, self.evaluate(&sample.features));
+                let diff: f64 = target(sample.label, scale) - s;
+                diff * diff
+            })
+            .sum();
+        sum / self.samples.len().max(1) as f64
+    }
+
+    /// Evaluate from the point of view of white without rounding.
+    fn evaluate(&self, features: &EvalFeatures) -> f64 {
+        let mg: f64 = features.phase as f64 / MAX_PHASE as f64;
+        features
+            .counts
+            .iter()
+            .map(|(i, count)| {
+                let w: f64 = self.weights[2 * i] * mg + self.weights[2 * i + 1] * (1.0 - mg);
+                w * *count as f64
+            })
+            .sum()
+    }
+}
+
+/// Map an evaluation from the point of view of white to the expected result for white.
+fn sigmoid(scale: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scale * eval / 400.0))
+}
+
+/// Get the expected result for white that a label stands for.
+fn target(label: TrainingLabel, scale: f64) -> f64 {
+    match label {
+        TrainingLabel::Result(GameResult::WhiteWins) => 1.0,
+        TrainingLabel::Result(GameResult::BlackWins) => 0.0,
+        TrainingLabel::Result(_) => 0.5,
+        TrainingLabel::Score(cp) => sigmoid(scale, cp as f64),
+    }
+}
+
+/// Play out the captures and promotions that a quiescence search with `params` expects.
+fn resolve(board: &Board, params: &EvalParams) -> Board {
+    let mut board: Board = board.clone();
+    let mut pv: Vec<Move> = Vec::new();
+    quiesce(&mut board, params, -i32::MAX, i32::MAX, 0, &mut pv);
+    for m in pv {
+        board.make_move(m);
+    }
+    board
+}
+
+fn quiesce(
+    board: &mut Board,
+    params: &EvalParams,
+    mut alpha: i32,
+    beta: i32,
+    ply: usize,
+    pv: &mut Vec<Move>,
+) -> i32 {
+    let stand_pat: i32 = params.evaluate(board);
+    if stand_pat >= beta || ply >= MAX_QUIESCENCE_PLY {
+        return stand_pat;
+    }
+    alpha = alpha.max(stand_pat);
+
+    let mut moves: Vec<Move> = board.legal_moves();
+    moves.retain(|m| (m.is_capture() || m.is_promotion()) && board.see(*m) >= 0);
+    moves.sort_by_cached_key(|m| Reverse(board.see(*m)));
+    for m in moves {
+        let mut line: Vec<Move> = Vec::new();
+        board.make_move(m);
+        let score: i32 = -quiesce(board, params, -beta, -alpha, ply + 1, &mut line);
+        board.unmake_move(m);
+        if score > alpha {
+            alpha = score;
+            pv.clear();
+            pv.push(m);
+            pv.append(&mut line);
+            if score >= beta {
+                break;
+            }
+        }
+    }
+    alpha
+}
+
+impl EvalParams {
+    /// Write every weight on a line of its own as its name, see
+    /// [`EvalParams::weight_name()`], followed by its middlegame and endgame value.
+    pub fn write_weights<W: Write>(&self, mut writer: W) -> Result<()> {
+        for (i, w) in self.weights().iter().enumerate() {
+            writeln!(writer, "{} {} {}", EvalParams::weight_name(i), w.mg, w.eg)?;
+        }
+        Ok(())
+    }
+
+    /// Read weights as written by [`EvalParams::write_weights()`], on top of the
+    /// [`DEFAULT_EVAL_PARAMS`](crate::eval::DEFAULT_EVAL_PARAMS).
+    ///
+    /// Weights may be left out, empty lines and lines starting with `#` are skipped.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidTuningData`] error with the number of the first invalid
+    /// line, or the underlying I/O error if reading fails.
+    pub fn read_weights<R: BufRead>(reader: R) -> Result<EvalParams> {
+        let names: Vec<String> = (0..NUM_EVAL_WEIGHTS).map(EvalParams::weight_name).collect();
+        let mut weights: Vec<Score> = EvalParams::default().weights();
+        for (i, line) in reader.lines().enumerate() {
+            let line: String = line?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let index: usize = names.iter().position(|n| n == tokens[0]).ok_or_else(|| {
+                tuning_error(i + 1, TuningErrorKind::UnknownWeight(tokens[0].to_string()))
+            })?;
+            match tokens[1..] {
+                [mg, eg] => match (mg.parse(), eg.parse()) {
+                    (Ok(mg), Ok(eg)) => weights[index] = Score::new(mg, eg),
+                    _ => return Err(tuning_error(i + 1, TuningErrorKind::InvalidWeight(line))),
+                },
+                _ => return Err(tuning_error(i + 1, TuningErrorKind::InvalidWeight(line))),
+            }
+        }
+        Ok(EvalParams::from_weights(&weights))
+    }
+
+    /// Format the weights as the Rust source of a constant called `name`.
+    pub fn to_rust_source(&self, name: &str) -> String {
+        let list = |scores: &[Score], indent: &str| -> String {
+            let lines: Vec<String> = scores
+                .chunks(4)
+                .map(|chunk| {
+                    let items: Vec<String> = chunk
+                        .iter()
+                        .map(|s| format!("Score::new({}, {}),", s.mg, s.eg))
+                        .collect();
+                    format!("{}{}", indent, items.join(" "))
+                })
+                .collect();
+            format!("[\n{}\n{}]", lines.join("\n"), &indent[4..])
+        };
+
+        let mut s: String = format!("pub const {}: EvalParams = EvalParams {{\n", name);
+        s += &format!("    material: {},\n", list(&self.material, "        "));
+        s += "    psqt: [\n";
+        for table in &self.psqt {
+            s += &format!("        {},\n", list(table, "            "));
+        }
+        s += "    ],\n";
+        s += &format!("    mobility: {},\n", list(&self.mobility, "        "));
+        for (field, w) in [
+            ("doubled_pawn", self.doubled_pawn),
+            ("isolated_pawn", self.isolated_pawn),
+            ("backward_pawn", self.backward_pawn),
+        ] {
+            s += &format!("    {}: Score::new({}, {}),\n", field, w.mg, w.eg);
+        }
+        s += &format!(
+            "    passed_pawn: {},\n",
+            list(&self.passed_pawn, "        ")
+        );
+        s += &format!(
+            "    pawn_shield: Score::new({}, {}),\n",
+            self.pawn_shield.mg, self.pawn_shield.eg
+        );
+        s += &format!(
+            "    king_attack: {},\n",
+            list(&self.king_attack, "        ")
+        );
+        s += &format!(
+            "    bishop_pair: Score::new({}, {}),\n",
+            self.bishop_pair.mg, self.bishop_pair.eg
+        );
+        s + "};\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::Piece;
+
+    fn label(line: &str) -> TrainingLabel {
+        TrainingPosition::try_from_line(line).unwrap().label
+    }
+
+    fn error_kind(e: Box<dyn error::Error>) -> (usize, TuningErrorKind) {
+        match *e.downcast::<ChessifyError>().unwrap() {
+            ChessifyError::InvalidTuningData { line, kind } => (line, kind),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn labels() {
+        let start: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        for (suffix, expected) in [
+            (" 0 1 [1.0]", TrainingLabel::Result(GameResult::WhiteWins)),
+            (" [0.5]", TrainingLabel::Result(GameResult::Draw)),
+            (" 0 1 0-1", TrainingLabel::Result(GameResult::BlackWins)),
+            (" c9 \"1/2-1/2\";", TrainingLabel::Result(GameResult::Draw)),
+            (" 0 1 cp -35", TrainingLabel::Score(-35)),
+        ] {
+            assert_eq!(expected, label(&format!("{}{}", start, suffix)));
+        }
+        let position = TrainingPosition::try_from_line(&format!("{} 3 12 1-0", start)).unwrap();
+        assert_eq!(12, position.board.fullmove_number());
+
+        for (line, kind) in [
+            (start.to_string(), TuningErrorKind::MissingLabel),
+            (format!("{} 0 1", start), TuningErrorKind::MissingLabel),
+            (
+                format!("{} *", start),
+                TuningErrorKind::InvalidLabel("*".to_string()),
+            ),
+            (
+                format!("{} cp x", start),
+                TuningErrorKind::InvalidLabel("x".to_string()),
+            ),
+            (
+                "8/8/8 w - - 1-0".to_string(),
+                TuningErrorKind::InvalidFen("8/8/8 w - -".to_string()),
+            ),
+        ] {
+            let e = TrainingPosition::try_from_line(&line).unwrap_err();
+            assert_eq!((1, kind), error_kind(e));
+        }
+    }
+
+    #[test]
+    fn read_positions() {
+        let data: &str = "# comment\n\n4k3/8/8/8/8/8/8/4K3 w - - 0 1 [0.5]\n\
+                          4k3/8/8/8/8/8/8/QQQQK3 w - - 0 1 1-0\n";
+        let positions = read_training_positions(data.as_bytes()).unwrap();
+        assert_eq!(2, positions.len());
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            positions[0].board
+        );
+
+        let data: String = format!("{}4k3/8/8/8/8/8/8/4K3 w - - 0 1 1-0 x\n", data);
+        let e = read_training_positions(data.as_bytes()).unwrap_err();
+        assert_eq!(
+            (5, TuningErrorKind::InvalidLabel("x".to_string())),
+            error_kind(e)
+        );
+    }
+
+    #[test]
+    fn resolves_captures() {
+        let params: EvalParams = EvalParams::default();
+        // The queen can be taken for free, after which nothing else is worth capturing.
+        let board: Board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        let resolved: Board = resolve(&board, &params);
+        assert_eq!(
+            Board::from_fen("4k3/8/8/3R4/8/8/8/4K3 b - - 0 1").to_fen(),
+            resolved.to_fen()
+        );
+        // A quiet position stays as it is.
+        assert_eq!(Board::default(), resolve(&Board::default(), &params));
+    }
+
+    #[test]
+    fn tunes_weights() {
+        let mut params: EvalParams = EvalParams::default();
+        params.material[Piece::Knight.as_index()] = Score::ZERO;
+        let positions: Vec<TrainingPosition> = [
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/1N2K3 w - - 0 1 1-0",
+            "1n2k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1 0-1",
+            "1n2k3/pppppppp/8/8/8/8/PPPPPPPP/1N2K3 w - - 0 1 1/2-1/2",
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/1NN1K3 w - - 0 1 cp 600",
+        ]
+        .iter()
+        .map(|line| TrainingPosition::try_from_line(line).unwrap())
+        .collect();
+
+        let config: TunerConfig = TunerConfig {
+            scale: Some(1.0),
+            learning_rate: 5.0,
+            quiesce: false,
+            ..Default::default()
+        };
+        let mut tuner: Tuner = Tuner::new(&params, &positions, config);
+        assert_eq!(params, tuner.params());
+        let before: f64 = tuner.error();
+        let mut reports: Vec<f64> = Vec::new();
+        tuner.tune(50, |_, error| reports.push(error));
+
+        assert_eq!(50, tuner.steps());
+        assert_eq!(before, reports[0]);
+        assert!(tuner.error() < before / 2.0);
+        assert!(tuner.params().material[Piece::Knight.as_index()].mg > 100);
+
+        // Without a given scale it is fitted to the data, here to a knight being worth three
+        // quarters of a point.
+        let draw: TrainingPosition = TrainingPosition {
+            label: TrainingLabel::Result(GameResult::Draw),
+            ..positions[0].clone()
+        };
+        let positions: Vec<TrainingPosition> = vec![positions[0].clone(), draw];
+        let tuner: Tuner = Tuner::new(&EvalParams::default(), &positions, TunerConfig::default());
+        let eval: i32 = EvalParams::default().evaluate(&positions[0].board);
+        let expected: f64 = 400.0 * 3f64.log10() / eval as f64;
+        assert!((tuner.scale() - expected).abs() < 0.05, "{}", tuner.scale());
+    }
+
+    #[test]
+    fn weights_files() {
+        let mut params: EvalParams = EvalParams::default();
+        params.psqt[2][36] = Score::new(-7, 11);
+        params.bishop_pair = Score::new(45, 60);
+
+        let mut out: Vec<u8> = Vec::new();
+        params.write_weights(&mut out).unwrap();
+        let text: String = String::from_utf8(out).unwrap();
+        assert_eq!(NUM_EVAL_WEIGHTS, text.lines().count());
+        assert!(text.contains("\npsqt[2][36] -7 11\n"));
+        assert_eq!(params, EvalParams::read_weights(text.as_bytes()).unwrap());
+
+        let partial: &str = "# tuned\nbishop_pair 45 60\n\npsqt[2][36] -7 11\n";
+        assert_eq!(
+            params,
+            EvalParams::read_weights(partial.as_bytes()).unwrap()
+        );
+
+        let e = EvalParams::read_weights("bishop_pair 1 2\nrook_pair 1 2\n".as_bytes());
+        assert_eq!(
+            (2, TuningErrorKind::UnknownWeight("rook_pair".to_string())),
+            error_kind(e.unwrap_err())
+        );
+        let e = EvalParams::read_weights("bishop_pair 1\n".as_bytes());
+        assert_eq!(
+            (
+                1,
+                TuningErrorKind::InvalidWeight("bishop_pair 1".to_string())
+            ),
+            error_kind(e.unwrap_err())
+        );
+
+        let source: String = params.to_rust_source("TUNED");
+        assert!(source.starts_with("pub const TUNED: EvalParams = EvalParams {\n"));
+        assert!(source.contains(
+            "    material: [\n        Score::new(82, 94), Score::new(337, 281), \
+             Score::new(365, 297), Score::new(477, 512),\n"
+        ));
+        assert!(source.contains("    bishop_pair: Score::new(45, 60),\n};\n"));
+        assert_eq!(NUM_EVAL_WEIGHTS, source.matches("Score::new(").count());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::board::Board;
+use crate::chess_move::{Move, NULL_MOVE};
+use crate::error::{ChessifyError, Result};
+use crate::piece::Piece;
+use crate::square::Square;
+
+/// Exhaustive enum of the ways castling moves are written in UCI notation.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum CastlingMode {
+    /// The king moves two squares, e.g. `e1g1`.
+    #[default]
+    Standard,
+    /// The king captures its own rook, e.g. `e1h1`, as required for Chess960.
+    Chess960,
+}
+
+impl Move {
+    /// Format the move in the long algebraic notation of the UCI protocol, e.g. `e7e8q`.
+    ///
+    /// Castling is written as the king moving two squares. The [`NULL_MOVE`] is written as
+    /// `0000`.
+    pub fn to_uci(&self) -> String {
+        self.to_string()
+    }
+
+    /// Format the move in UCI notation, writing castling moves according to `mode`.
+    ///
+    /// The board is the position before the move and is needed to locate the castling rook.
+    pub fn to_uci_with_mode(&self, board: &Board, mode: CastlingMode) -> String {
+        if mode == CastlingMode::Chess960 && self.is_castle() {
+            let (rook, _) = board.castling_rook_squares(*self);
+            format!("{}{}", self.from(), rook)
+        } else {
+            self.to_uci()
+        }
+    }
+}
+
+impl Board {
+    /// Parse a move in UCI notation and match it to a legal move.
+    ///
+    /// Castling has to be written as the king moving two squares. `0000` is parsed as the
+    /// [`NULL_MOVE`], which can not be played on the board.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::InvalidUciMove`] error if the string is not in UCI notation
+    /// and a [`ChessifyError::IllegalMove`] error if it does not describe a legal move.
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move> {
+        self.parse_uci_move_with_mode(uci, CastlingMode::Standard)
+    }
+
+    /// Parse a move in UCI notation, expecting castling moves to be written according to `mode`.
+    ///
+    /// # Errors
+    /// See [`Board::parse_uci_move()`].
+    pub fn parse_uci_move_with_mode(&self, uci: &str, mode: CastlingMode) -> Result<Move> {
+        if uci == "0000" {
+            return Ok(NULL_MOVE);
+        }
+
+        let bytes: &[u8] = uci.as_bytes();
+        let is_square = |s: &[u8]| (b'a'..=b'h').contains(&s[0]) && (b'1'..=b'8').contains(&s[1]);
+        if !(bytes.len() == 4 || bytes.len() == 5)
+            || !is_square(&bytes[..2])
+            || !is_square(&bytes[2..4])
+        {
+            return Err(Box::new(ChessifyError::InvalidUciMove(uci.to_string())));
+        }
+
+        let from: Square = Square::try_from(&uci[..2])?;
+        let to: Square = Square::try_from(&uci[2..4])?;
+        let promotion: Option<Piece> = match bytes.get(4) {
+            None => None,
+            Some(b'n') => Some(Piece::Knight),
+            Some(b'b') => Some(Piece::Bishop),
+            Some(b'r') => Some(Piece::Rook),
+            Some(b'q') => Some(Piece::Queen),
+            Some(_) => return Err(Box::new(ChessifyError::InvalidUciMove(uci.to_string()))),
+        };
+
+        let found: Option<Move> = self.legal_moves().into_iter().find(|m| {
+            if m.from() != from || m.promotion() != promotion {
+                return false;
+            }
+            match mode {
+                CastlingMode::Chess960 if m.is_castle() => self.castling_rook_squares(*m).0 == to,
+                _ => m.to() == to,
+            }
+        });
+        match found {
+            Some(m) => Ok(m),
+            None => Err(Box::new(ChessifyError::IllegalMove(uci.to_string()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::MoveFlag;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn uci_error(b: &Board, uci: &str, mode: CastlingMode) -> ChessifyError {
+        *b.parse_uci_move_with_mode(uci, mode)
+            .unwrap_err()
+            .downcast::<ChessifyError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let b = Board::default();
+        let m = b.parse_uci_move("g1f3").unwrap();
+        assert_eq!(
+            Move::new(
+                Square::from_str("g1"),
+                Square::from_str("f3"),
+                MoveFlag::Quiet
+            ),
+            m
+        );
+        assert_eq!(
+            MoveFlag::DoublePawnPush,
+            b.parse_uci_move("e2e4").unwrap().flag()
+        );
+        assert_eq!(NULL_MOVE, b.parse_uci_move("0000").unwrap());
+
+        let b = Board::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let m = b.parse_uci_move("a7b8r").unwrap();
+        assert_eq!(MoveFlag::RookPromotionCapture, m.flag());
+        assert_eq!("a7b8r", m.to_uci());
+    }
+
+    #[test]
+    fn castling_modes() {
+        let b = Board::from_fen(KIWIPETE);
+        let short = b.parse_uci_move("e1g1").unwrap();
+        let long = b.parse_uci_move("e1c1").unwrap();
+        assert_eq!(MoveFlag::KingCastle, short.flag());
+        assert_eq!(MoveFlag::QueenCastle, long.flag());
+
+        assert_eq!("e1g1", short.to_uci_with_mode(&b, CastlingMode::Standard));
+        assert_eq!("e1h1", short.to_uci_with_mode(&b, CastlingMode::Chess960));
+        assert_eq!("e1a1", long.to_uci_with_mode(&b, CastlingMode::Chess960));
+
+        let chess960 = CastlingMode::Chess960;
+        assert_eq!(short, b.parse_uci_move_with_mode("e1h1", chess960).unwrap());
+        assert_eq!(long, b.parse_uci_move_with_mode("e1a1", chess960).unwrap());
+        assert!(matches!(
+            uci_error(&b, "e1g1", chess960),
+            ChessifyError::IllegalMove(_)
+        ));
+        assert!(matches!(
+            uci_error(&b, "e1h1", CastlingMode::Standard),
+            ChessifyError::IllegalMove(_)
+        ));
+
+        // Ordinary king moves are unaffected by the mode.
+        assert_eq!(
+            "e1f1",
+            b.parse_uci_move_with_mode("e1f1", chess960)
+                .unwrap()
+                .to_uci()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let b = Board::default();
+        let standard = CastlingMode::Standard;
+        for uci in [
+            "", "e2", "e2e", "e2e4qq", "E2E4", "e2e9", "i2e4", "e7e8k", "e2e4 ",
+        ] {
+            assert!(
+                matches!(
+                    uci_error(&b, uci, standard),
+                    ChessifyError::InvalidUciMove(_)
+                ),
+                "{}",
+                uci
+            );
+        }
+        assert!(matches!(
+            uci_error(&b, "e2e5", standard),
+            ChessifyError::IllegalMove(_)
+        ));
+        assert!(matches!(
+            uci_error(&b, "e2e4q", standard),
+            ChessifyError::IllegalMove(_)
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            KIWIPETE,
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let b = Board::from_fen(fen);
+            for m in b.legal_moves() {
+                for mode in [CastlingMode::Standard, CastlingMode::Chess960] {
+                    let uci = m.to_uci_with_mode(&b, mode);
+                    assert_eq!(
+                        m,
+                        b.parse_uci_move_with_mode(&uci, mode).unwrap(),
+                        "{}",
+                        uci
+                    );
+                }
+            }
+        }
+    }
+}
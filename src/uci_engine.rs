@@ -0,0 +1,1073 @@
+use crate::board::{Board, DEFAULT_BOARD_FEN};
+use crate::chess_move::Move;
+use crate::color::Color;
+use crate::error::{ChessifyError, Result};
+use crate::transposition::ScoreBound;
+use crate::uci::CastlingMode;
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long an engine gets to answer a command, and to stop once a timed search is over.
+pub const DEFAULT_ENGINE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The keywords of an `info` line, which end the move lists in between them.
+const INFO_KEYWORDS: [&str; 20] = [
+    "depth",
+    "seldepth",
+    "time",
+    "nodes",
+    "pv",
+    "multipv",
+    "score",
+    "lowerbound",
+    "upperbound",
+    "currmove",
+    "currmovenumber",
+    "hashfull",
+    "nps",
+    "tbhits",
+    "sbhits",
+    "cpuload",
+    "string",
+    "refutation",
+    "currline",
+    "wdl",
+];
+
+/// Exhaustive enum of the ways talking to a UCI engine can fail.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum EngineErrorKind {
+    /// The engine process exited or closed its output.
+    Terminated,
+    /// The engine did not answer the command in time.
+    Timeout(String),
+    /// The engine did not announce an option of this name.
+    UnknownOption(String),
+    /// The value is not allowed for the option.
+    InvalidOptionValue { name: String, value: String },
+    /// An `info` line could not be parsed.
+    InvalidInfo(String),
+    /// The engine answered with a best move that is not legal.
+    InvalidBestMove(String),
+    /// The command needs a running search, but there is none.
+    NotSearching,
+    /// The command can not be sent while the engine is searching.
+    Searching,
+}
+
+impl fmt::Display for EngineErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineErrorKind::Terminated => write!(f, "engine terminated unexpectedly"),
+            EngineErrorKind::Timeout(s) => write!(f, "engine did not answer `{}` in time", s),
+            EngineErrorKind::UnknownOption(s) => write!(f, "engine has no option `{}`", s),
+            EngineErrorKind::InvalidOptionValue { name, value } => {
+                write!(f, "`{}` is not a valid value for option `{}`", value, name)
+            }
+            EngineErrorKind::InvalidInfo(s) => write!(f, "`{}` is not a valid info line", s),
+            EngineErrorKind::InvalidBestMove(s) => write!(f, "`{}` is not a legal best move", s),
+            EngineErrorKind::NotSearching => write!(f, "engine is not searching"),
+            EngineErrorKind::Searching => write!(f, "engine is still searching"),
+        }
+    }
+}
+
+fn engine_error(kind: EngineErrorKind) -> Box<dyn Error> {
+    Box::new(ChessifyError::Engine(kind))
+}
+
+/// Exhaustive enum of the types of options an engine can announce, with their constraints.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum UciOptionKind {
+    /// Either `true` or `false`.
+    Check { default: bool },
+    /// An integer in the range from `min` to `max`.
+    Spin { default: i64, min: i64, max: i64 },
+    /// One of a fixed list of strings.
+    Combo { default: String, vars: Vec<String> },
+    /// A command without a value.
+    Button,
+    /// Any string, the empty one included.
+    String { default: String },
+}
+
+/// An option announced by an engine in answer to `uci`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct UciOption {
+    pub name: String,
+    pub kind: UciOptionKind,
+}
+
+impl UciOption {
+    /// Parse an `option name <name> type <type> ...` line.
+    ///
+    /// Returns [`None`] if the line does not describe an option.
+    pub fn parse(line: &str) -> Option<UciOption> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.first() != Some(&"option") || tokens.get(1) != Some(&"name") {
+            return None;
+        }
+
+        // Every keyword is followed by a value, which may consist of several words.
+        let mut name: Vec<&str> = Vec::new();
+        let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+        for token in &tokens[2..] {
+            match *token {
+                "type" | "default" | "min" | "max" | "var" => fields.push((token, Vec::new())),
+                _ => match fields.last_mut() {
+                    Some((_, value)) => value.push(token),
+                    None => name.push(token),
+                },
+            }
+        }
+        let field = |key: &str| -> Option<String> {
+            fields
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value.join(" "))
+        };
+        let number = |key: &str| -> Option<i64> { field(key)?.parse().ok() };
+
+        let kind: UciOptionKind = match field("type")?.as_str() {
+            "check" => UciOptionKind::Check {
+                default: field("default")? == "true",
+            },
+            "spin" => UciOptionKind::Spin {
+                default: number("default")?,
+                min: number("min")?,
+                max: number("max")?,
+            },
+            "combo" => UciOptionKind::Combo {
+                default: field("default")?,
+                vars: fields
+                    .iter()
+                    .filter(|(k, _)| *k == "var")
+                    .map(|(_, value)| value.join(" "))
+                    .collect(),
+            },
+            "button" => UciOptionKind::Button,
+            "string" => UciOptionKind::String {
+                default: match field("default") {
+                    Some(s) if s != "<empty>" => s,
+                    _ => String::new(),
+                },
+            },
+            _ => return None,
+        };
+        Some(UciOption {
+            name: name.join(" "),
+            kind,
+        })
+    }
+
+    /// Check whether the option may be set to `value`.
+    ///
+    /// Combo values are compared case insensitively, as option names are.
+    pub fn accepts(&self, value: &str) -> bool {
+        match &self.kind {
+            UciOptionKind::Check { .. } => value == "true" || value == "false",
+            UciOptionKind::Spin { min, max, .. } => value
+                .parse::<i64>()
+                .is_ok_and(|v| (*min..=*max).contains(&v)),
+            UciOptionKind::Combo { vars, .. } => vars.iter().any(|v| v.eq_ignore_ascii_case(value)),
+            UciOptionKind::Button => value.is_empty(),
+            UciOptionKind::String { .. } => true,
+        }
+    }
+}
+
+/// The evaluation reported by an engine, from the point of view of the side to move.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum UciScore {
+    /// An advantage in centipawns.
+    Centipawns(i32),
+    /// Mate in the given number of moves, negative if the side to move gets mated.
+    Mate(i32),
+}
+
+/// The chances of winning, drawing and losing in per mille, for the side to move.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Wdl {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+/// The fields of an `info` line, each of which the engine may leave out.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UciInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    /// The rank of the line, starting at 1, when several are searched.
+    pub multipv: Option<u32>,
+    pub score: Option<UciScore>,
+    pub bound: ScoreBound,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    /// How full the hash table is in per mille.
+    pub hashfull: Option<u32>,
+    pub tbhits: Option<u64>,
+    pub wdl: Option<Wdl>,
+    pub currmove: Option<Move>,
+    pub currmovenumber: Option<u32>,
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+impl UciInfo {
+    /// Parse an `info` line sent while searching `board`, with castling written as in `mode`.
+    ///
+    /// Fields that chessify does not know are skipped.
+    ///
+    /// # Errors
+    /// Returns a [`EngineErrorKind::InvalidInfo`] error if the line does not start with `info`,
+    /// a value is missing or malformed, or a move is not legal.
+    pub fn parse(line: &str, board: &Board, mode: CastlingMode) -> Result<UciInfo> {
+        let invalid = || engine_error(EngineErrorKind::InvalidInfo(line.to_string()));
+        let mut tokens = line.split_whitespace().peekable();
+        if tokens.next() != Some("info") {
+            return Err(invalid());
+        }
+
+        let mut info: UciInfo = UciInfo::default();
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = Some(next_number(&mut tokens, line)?),
+                "seldepth" => info.seldepth = Some(next_number(&mut tokens, line)?),
+                "multipv" => info.multipv = Some(next_number(&mut tokens, line)?),
+                "nodes" => info.nodes = Some(next_number(&mut tokens, line)?),
+                "nps" => info.nps = Some(next_number(&mut tokens, line)?),
+                "time" => info.time = Some(Duration::from_millis(next_number(&mut tokens, line)?)),
+                "hashfull" => info.hashfull = Some(next_number(&mut tokens, line)?),
+                "tbhits" => info.tbhits = Some(next_number(&mut tokens, line)?),
+                "currmovenumber" => info.currmovenumber = Some(next_number(&mut tokens, line)?),
+                "sbhits" | "cpuload" => {
+                    let _: u64 = next_number(&mut tokens, line)?;
+                }
+                "score" => {
+                    let kind: Option<&str> = tokens.next();
+                    let value: i32 = next_number(&mut tokens, line)?;
+                    info.score = Some(match kind {
+                        Some("cp") => UciScore::Centipawns(value),
+                        Some("mate") => UciScore::Mate(value),
+                        _ => return Err(invalid()),
+                    });
+                }
+                "lowerbound" => info.bound = ScoreBound::Lower,
+                "upperbound" => info.bound = ScoreBound::Upper,
+                "wdl" => {
+                    info.wdl = Some(Wdl {
+                        win: next_number(&mut tokens, line)?,
+                        draw: next_number(&mut tokens, line)?,
+                        loss: next_number(&mut tokens, line)?,
+                    })
+                }
+                "currmove" => {
+                    let uci: &str = tokens.next().ok_or_else(invalid)?;
+                    let m: Move = board
+                        .parse_uci_move_with_mode(uci, mode)
+                        .map_err(|_| invalid())?;
+                    info.currmove = Some(m);
+                }
+                "pv" => {
+                    let mut board: Board = board.clone();
+                    while let Some(uci) = tokens.next_if(|t| !INFO_KEYWORDS.contains(t)) {
+                        let m: Move = board
+                            .parse_uci_move_with_mode(uci, mode)
+                            .map_err(|_| invalid())?;
+                        if m.is_null() {
+                            return Err(invalid());
+                        }
+                        board.make_move(m);
+                        info.pv.push(m);
+                    }
+                }
+                "refutation" | "currline" => {
+                    while tokens.next_if(|t| !INFO_KEYWORDS.contains(t)).is_some() {}
+                }
+                "string" => {
+                    info.string = Some(tokens.by_ref().collect::<Vec<&str>>().join(" "));
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// Parse the next token as a number, failing for the whole `info` line.
+fn next_number<'a, T: FromStr>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+    line: &str,
+) -> Result<T> {
+    match tokens.next().map(str::parse) {
+        Some(Ok(n)) => Ok(n),
+        _ => Err(engine_error(EngineErrorKind::InvalidInfo(line.to_string()))),
+    }
+}
+
+/// The parameters of a `go` command, all of them optional.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GoParams {
+    /// Only consider these moves at the root.
+    pub searchmoves: Vec<Move>,
+    pub ponder: bool,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    /// Search for a mate in this many moves.
+    pub mate: Option<u32>,
+    pub movetime: Option<Duration>,
+    pub infinite: bool,
+}
+
+impl GoParams {
+    /// Search to a fixed depth.
+    pub fn depth(depth: u32) -> Self {
+        GoParams {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Search a fixed number of nodes.
+    pub fn nodes(nodes: u64) -> Self {
+        GoParams {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    /// Search for a fixed time.
+    pub fn movetime(movetime: Duration) -> Self {
+        GoParams {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    /// Search until stopped.
+    pub fn infinite() -> Self {
+        GoParams {
+            infinite: true,
+            ..Default::default()
+        }
+    }
+
+    /// Format the `go` command for a search of `board`.
+    fn to_command(&self, board: &Board, mode: CastlingMode) -> String {
+        let mut command: String = String::from("go");
+        let millis = |d: Duration| d.as_millis().to_string();
+        let fields: [(&str, Option<String>); 10] = [
+            ("wtime", self.wtime.map(millis)),
+            ("btime", self.btime.map(millis)),
+            ("winc", self.winc.map(millis)),
+            ("binc", self.binc.map(millis)),
+            ("movestogo", self.movestogo.map(|n| n.to_string())),
+            ("depth", self.depth.map(|n| n.to_string())),
+            ("nodes", self.nodes.map(|n| n.to_string())),
+            ("mate", self.mate.map(|n| n.to_string())),
+            ("movetime", self.movetime.map(millis)),
+            ("infinite", self.infinite.then(String::new)),
+        ];
+        if self.ponder {
+            command.push_str(" ponder");
+        }
+        for (key, value) in fields.iter() {
+            if let Some(value) = value {
+                command.push(' ');
+                command.push_str(key);
+                if !value.is_empty() {
+                    command.push(' ');
+                    command.push_str(value);
+                }
+            }
+        }
+        if !self.searchmoves.is_empty() {
+            command.push_str(" searchmoves");
+            for m in &self.searchmoves {
+                command.push(' ');
+                command.push_str(&m.to_uci_with_mode(board, mode));
+            }
+        }
+        command
+    }
+
+    /// The longest the search may take once the clock runs, if it is timed at all.
+    fn time_limit(&self, us: Color) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        let clock: Option<Duration> = match us {
+            Color::White => self.wtime,
+            Color::Black => self.btime,
+        };
+        self.movetime.or(clock)
+    }
+}
+
+/// The answer of an engine to a search.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UciBestMove {
+    /// The move to play, or [`None`] if there is no legal move.
+    pub best_move: Option<Move>,
+    /// The expected reply, which is dropped if it is not legal.
+    pub ponder: Option<Move>,
+    /// All `info` lines sent during the search, in order.
+    pub infos: Vec<UciInfo>,
+}
+
+impl UciBestMove {
+    /// Get the last score reported for the principal variation.
+    pub fn score(&self) -> Option<UciScore> {
+        self.infos
+            .iter()
+            .rev()
+            .filter(|info| info.multipv.unwrap_or(1) == 1)
+            .find_map(|info| info.score)
+    }
+}
+
+/// A search that an engine is carrying out.
+struct Search {
+    board: Board,
+    infos: Vec<UciInfo>,
+    /// The line holding the best move, once it has arrived.
+    bestmove: Option<String>,
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+/// A UCI engine running as a child process.
+///
+/// Engine output that is not part of the protocol is ignored, and so are `info` lines that
+/// can not be parsed. The engine is asked to quit, and killed if need be, when dropped.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: String,
+    author: String,
+    options: Vec<UciOption>,
+    timeout: Duration,
+    chess960: bool,
+    position: Board,
+    search: Option<Search>,
+}
+
+impl UciEngine {
+    /// Start the engine at `program` and wait for it to identify itself.
+    ///
+    /// # Errors
+    /// Returns an IO error if the engine can not be started, and a [`ChessifyError::Engine`]
+    /// error if it does not answer `uci` with `uciok`.
+    pub fn spawn<P: AsRef<OsStr>>(program: P) -> Result<UciEngine> {
+        UciEngine::from_command(Command::new(program))
+    }
+
+    /// Start an engine from a prepared command, e.g. one with arguments or a working
+    /// directory, and wait for it to identify itself.
+    ///
+    /// Standard input and output are taken over to talk to the engine.
+    ///
+    /// # Errors
+    /// See [`UciEngine::spawn()`].
+    pub fn from_command(mut command: Command) -> Result<UciEngine> {
+        let mut child: Child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(engine_error(EngineErrorKind::Terminated)),
+        };
+
+        // Lines are read on a thread of their own so that waiting for them can time out.
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let sent: bool = match line {
+                    Ok(line) => sender.send(line).is_ok(),
+                    Err(_) => false,
+                };
+                if !sent {
+                    break;
+                }
+            }
+        });
+
+        let mut engine: UciEngine = UciEngine {
+            child,
+            stdin,
+            lines,
+            name: String::new(),
+            author: String::new(),
+            options: Vec::new(),
+            timeout: DEFAULT_ENGINE_TIMEOUT,
+            chess960: false,
+            position: Board::default(),
+            search: None,
+        };
+        engine.send("uci")?;
+        let deadline: Instant = Instant::now() + engine.timeout;
+        loop {
+            let line: String = engine.read_line("uci", Some(deadline))?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                engine.author = author.trim().to_string();
+            } else if let Some(option) = UciOption::parse(&line) {
+                engine.options.push(option);
+            } else if line.trim() == "uciok" {
+                return Ok(engine);
+            }
+        }
+    }
+
+    /// Get the name the engine gave.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the author the engine gave.
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// Get the options the engine announced.
+    pub fn options(&self) -> &[UciOption] {
+        &self.options
+    }
+
+    /// Get an announced option by its case insensitive name.
+    pub fn option(&self, name: &str) -> Option<&UciOption> {
+        self.options
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get how long the engine gets to answer a command.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set how long the engine gets to answer a command, and to stop once a timed search is
+    /// over, which defaults to [`DEFAULT_ENGINE_TIMEOUT`].
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set an announced option, using an empty value for buttons.
+    ///
+    /// Setting `UCI_Chess960` also switches how castling moves are written.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::Engine`] error if the engine has no such option, the value
+    /// is not allowed for it, or the engine is searching.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        let option: &UciOption = self
+            .option(name)
+            .ok_or_else(|| engine_error(EngineErrorKind::UnknownOption(name.to_string())))?;
+        if !option.accepts(value) {
+            return Err(engine_error(EngineErrorKind::InvalidOptionValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            }));
+        }
+        let command: String = match option.kind {
+            UciOptionKind::Button => format!("setoption name {}", option.name),
+            _ => format!("setoption name {} value {}", option.name, value),
+        };
+        let chess960: bool = option.name.eq_ignore_ascii_case("UCI_Chess960");
+        self.ensure_idle()?;
+
+        self.send(&command)?;
+        if chess960 {
+            self.chess960 = value == "true";
+        }
+        Ok(())
+    }
+
+    /// Wait for the engine to be ready for more commands.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::Engine`] error if the engine does not answer in time.
+    pub fn is_ready(&mut self) -> Result<()> {
+        self.send("isready")?;
+        let deadline: Instant = Instant::now() + self.timeout;
+        loop {
+            let line: String = self.read_line("isready", Some(deadline))?;
+            if line.trim() == "readyok" {
+                return Ok(());
+            }
+            self.record(line);
+        }
+    }
+
+    /// Tell the engine that the next position is from a different game and wait for it.
+    ///
+    /// # Errors
+    /// See [`UciEngine::is_ready()`].
+    pub fn new_game(&mut self) -> Result<()> {
+        self.ensure_idle()?;
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Set the position to search, i.e., `board` after playing `moves`.
+    ///
+    /// Sending the moves instead of the final position lets the engine know which positions
+    /// have occurred before.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::IllegalMove`] error if a move is not legal and a
+    /// [`ChessifyError::Engine`] error if the engine is searching.
+    pub fn set_position(&mut self, board: &Board, moves: &[Move]) -> Result<()> {
+        self.ensure_idle()?;
+        let fen: String = board.to_fen();
+        let mut command: String = if fen == DEFAULT_BOARD_FEN {
+            String::from("position startpos")
+        } else {
+            format!("position fen {}", fen)
+        };
+
+        let mut position: Board = board.clone();
+        if !moves.is_empty() {
+            command.push_str(" moves");
+        }
+        for m in moves {
+            if !position.legal_moves().contains(m) {
+                return Err(Box::new(ChessifyError::IllegalMove(m.to_uci())));
+            }
+            command.push(' ');
+            command.push_str(&m.to_uci_with_mode(&position, self.castling_mode()));
+            position.make_move(*m);
+        }
+
+        self.send(&command)?;
+        self.position = position;
+        Ok(())
+    }
+
+    /// Search the current position and wait for the best move.
+    ///
+    /// Searches without a limit only end once [`UciEngine::stop()`] is called from elsewhere,
+    /// so use [`UciEngine::start()`] for those.
+    ///
+    /// # Errors
+    /// See [`UciEngine::wait()`].
+    pub fn go(&mut self, params: &GoParams) -> Result<UciBestMove> {
+        self.start(params)?;
+        self.wait()
+    }
+
+    /// Start searching the current position without waiting for the result.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::Engine`] error if the engine is already searching or has
+    /// terminated.
+    pub fn start(&mut self, params: &GoParams) -> Result<()> {
+        self.ensure_idle()?;
+        self.send(&params.to_command(&self.position, self.castling_mode()))?;
+
+        let time_limit: Option<Duration> = params.time_limit(self.position.side_to_move());
+        self.search = Some(Search {
+            board: self.position.clone(),
+            infos: Vec::new(),
+            bestmove: None,
+            time_limit,
+            deadline: match time_limit {
+                Some(limit) if !params.ponder => Some(Instant::now() + limit + self.timeout),
+                _ => None,
+            },
+        });
+        Ok(())
+    }
+
+    /// Tell a pondering engine that the expected move was played, which starts its clock.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::Engine`] error if the engine is not searching.
+    pub fn ponderhit(&mut self) -> Result<()> {
+        let timeout: Duration = self.timeout;
+        let search: &mut Search = self
+            .search
+            .as_mut()
+            .ok_or_else(|| engine_error(EngineErrorKind::NotSearching))?;
+        search.deadline = search
+            .time_limit
+            .map(|limit| Instant::now() + limit + timeout);
+        self.send("ponderhit")
+    }
+
+    /// Stop the search and wait for the best move.
+    ///
+    /// # Errors
+    /// See [`UciEngine::wait()`].
+    pub fn stop(&mut self) -> Result<UciBestMove> {
+        let search: &mut Search = self
+            .search
+            .as_mut()
+            .ok_or_else(|| engine_error(EngineErrorKind::NotSearching))?;
+        search.deadline = Some(Instant::now() + self.timeout);
+        self.send("stop")?;
+        self.wait()
+    }
+
+    /// Wait for the running search to end.
+    ///
+    /// A timed search that overruns its time limit by more than the timeout is stopped, and
+    /// given the timeout again to answer. An engine that still does not answer is killed, so
+    /// that a late best move can not be taken for the result of a later search.
+    ///
+    /// # Errors
+    /// Returns a [`ChessifyError::Engine`] error if the engine is not searching, does not
+    /// answer in time, terminates, or answers with an illegal move. Once the engine has been
+    /// killed, every later command fails with [`EngineErrorKind::Terminated`].
+    pub fn wait(&mut self) -> Result<UciBestMove> {
+        let mut stopped: bool = false;
+        loop {
+            let search: &mut Search = self
+                .search
+                .as_mut()
+                .ok_or_else(|| engine_error(EngineErrorKind::NotSearching))?;
+            if let Some(line) = search.bestmove.take() {
+                let search: Search = self.search.take().unwrap();
+                return self.parse_bestmove(&line, search);
+            }
+
+            let deadline: Option<Instant> = search.deadline;
+            match self.read_line("go", deadline) {
+                Ok(line) => self.record(line),
+                Err(_) if !stopped && self.is_running() => {
+                    stopped = true;
+                    if let Some(search) = self.search.as_mut() {
+                        search.deadline = Some(Instant::now() + self.timeout);
+                    }
+                    self.send("stop")?;
+                }
+                Err(e) => {
+                    self.search = None;
+                    let _ = self.child.kill();
+                    let _ = self.child.wait();
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Ask the engine to quit and wait for it to exit, killing it after the timeout.
+    ///
+    /// # Errors
+    /// Returns an IO error if waiting for the process fails.
+    pub fn quit(mut self) -> Result<ExitStatus> {
+        Ok(self.shut_down(self.timeout)?)
+    }
+
+    fn castling_mode(&self) -> CastlingMode {
+        if self.chess960 {
+            CastlingMode::Chess960
+        } else {
+            CastlingMode::Standard
+        }
+    }
+
+    fn ensure_idle(&self) -> Result<()> {
+        match self.search {
+            Some(_) => Err(engine_error(EngineErrorKind::Searching)),
+            None => Ok(()),
+        }
+    }
+
+    fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn send(&mut self, command: &str) -> Result<()> {
+        // Writing only fails once the engine has closed its input.
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| engine_error(EngineErrorKind::Terminated))?;
+        Ok(())
+    }
+
+    /// Read the next line, waiting until `deadline` at most.
+    fn read_line(&mut self, command: &str, deadline: Option<Instant>) -> Result<String> {
+        let line = match deadline {
+            Some(deadline) => self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match line {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(engine_error(EngineErrorKind::Timeout(command.to_string())))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(engine_error(EngineErrorKind::Terminated)),
+        }
+    }
+
+    /// Keep the output of the running search, if any.
+    fn record(&mut self, line: String) {
+        let mode: CastlingMode = self.castling_mode();
+        if let Some(search) = self.search.as_mut() {
+            if line.starts_with("info") {
+                if let Ok(info) = UciInfo::parse(&line, &search.board, mode) {
+                    search.infos.push(info);
+                }
+            } else if line.starts_with("bestmove") {
+                search.bestmove = Some(line);
+            }
+        }
+    }
+
+    /// Parse a `bestmove <move> [ponder <move>]` line ending `search`.
+    fn parse_bestmove(&self, line: &str, search: Search) -> Result<UciBestMove> {
+        let mode: CastlingMode = self.castling_mode();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let best_move: Option<Move> = match tokens.get(1) {
+            None | Some(&"(none)") | Some(&"0000") => None,
+            Some(uci) => match search.board.parse_uci_move_with_mode(uci, mode) {
+                Ok(m) => Some(m),
+                Err(_) => {
+                    return Err(engine_error(EngineErrorKind::InvalidBestMove(
+                        uci.to_string(),
+                    )))
+                }
+            },
+        };
+
+        let ponder: Option<Move> = match (best_move, tokens.get(2..4)) {
+            (Some(m), Some(&["ponder", uci])) => {
+                let mut board: Board = search.board.clone();
+                board.make_move(m);
+                board
+                    .parse_uci_move_with_mode(uci, mode)
+                    .ok()
+                    .filter(|m| !m.is_null())
+            }
+            _ => None,
+        };
+
+        Ok(UciBestMove {
+            best_move,
+            ponder,
+            infos: search.infos,
+        })
+    }
+
+    /// Send `quit` and give the engine `grace` to exit before killing it.
+    fn shut_down(&mut self, grace: Duration) -> std::io::Result<ExitStatus> {
+        if let Some(status) = self.child.try_wait()? {
+            return Ok(status);
+        }
+        let _ = self.send("quit");
+        let deadline: Instant = Instant::now() + grace;
+        while Instant::now() < deadline {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(status);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        self.child.kill()?;
+        self.child.wait()
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.shut_down(Duration::from_millis(100));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn info(line: &str) -> UciInfo {
+        UciInfo::parse(line, &Board::default(), CastlingMode::Standard).unwrap()
+    }
+
+    fn info_error(line: &str) -> ChessifyError {
+        *UciInfo::parse(line, &Board::default(), CastlingMode::Standard)
+            .unwrap_err()
+            .downcast::<ChessifyError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_info() {
+        let b = Board::default();
+        let i = info(
+            "info depth 12 seldepth 18 multipv 2 score cp -31 lowerbound nodes 123456 \
+             nps 654321 hashfull 42 tbhits 7 time 189 wdl 120 700 180 pv e2e4 e7e5 g1f3",
+        );
+        assert_eq!(Some(12), i.depth);
+        assert_eq!(Some(18), i.seldepth);
+        assert_eq!(Some(2), i.multipv);
+        assert_eq!(Some(UciScore::Centipawns(-31)), i.score);
+        assert_eq!(ScoreBound::Lower, i.bound);
+        assert_eq!(Some(123_456), i.nodes);
+        assert_eq!(Some(654_321), i.nps);
+        assert_eq!(Some(42), i.hashfull);
+        assert_eq!(Some(7), i.tbhits);
+        assert_eq!(Some(Duration::from_millis(189)), i.time);
+        assert_eq!(
+            Some(Wdl {
+                win: 120,
+                draw: 700,
+                loss: 180
+            }),
+            i.wdl
+        );
+        let pv: Vec<String> = i.pv.iter().map(|m| m.to_uci()).collect();
+        assert_eq!(vec!["e2e4", "e7e5", "g1f3"], pv);
+        assert_eq!(b.parse_uci_move("e2e4").unwrap(), i.pv[0]);
+
+        let i = info("info score mate -3 upperbound currmove d2d4 currmovenumber 5 cpuload 900");
+        assert_eq!(Some(UciScore::Mate(-3)), i.score);
+        assert_eq!(ScoreBound::Upper, i.bound);
+        assert_eq!(Some(b.parse_uci_move("d2d4").unwrap()), i.currmove);
+        assert_eq!(Some(5), i.currmovenumber);
+
+        // The move list ends at the next keyword.
+        let i = info("info pv g1f3 d7d5 depth 2 string hello  depth 3 world");
+        assert_eq!(2, i.pv.len());
+        assert_eq!(Some(2), i.depth);
+        assert_eq!(Some("hello depth 3 world".to_string()), i.string);
+
+        assert_eq!(UciInfo::default(), info("info refutation e2e4 e7e5 foo"));
+    }
+
+    #[test]
+    fn invalid_info() {
+        for line in [
+            "depth 3",
+            "info depth",
+            "info depth -1",
+            "info score 10",
+            "info score pawns 10",
+            "info wdl 1 2",
+            "info pv e2e4 e2e4",
+            "info currmove e1g1",
+        ] {
+            assert!(
+                matches!(
+                    info_error(line),
+                    ChessifyError::Engine(EngineErrorKind::InvalidInfo(_))
+                ),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn parse_options() {
+        let option = |line: &str| UciOption::parse(line).unwrap();
+        let hash = option("option name Hash type spin default 16 min 1 max 33554432");
+        assert_eq!("Hash", hash.name);
+        assert_eq!(
+            UciOptionKind::Spin {
+                default: 16,
+                min: 1,
+                max: 33_554_432
+            },
+            hash.kind
+        );
+        assert!(hash.accepts("1024"));
+        assert!(!hash.accepts("0"));
+        assert!(!hash.accepts("lots"));
+
+        let style = option(
+            "option name Play Style type combo default Normal var Solid var Normal var Very Risky",
+        );
+        assert_eq!("Play Style", style.name);
+        assert_eq!(
+            UciOptionKind::Combo {
+                default: "Normal".to_string(),
+                vars: vec!["Solid".into(), "Normal".into(), "Very Risky".into()]
+            },
+            style.kind
+        );
+        assert!(style.accepts("very risky"));
+        assert!(!style.accepts("Reckless"));
+
+        assert_eq!(
+            UciOptionKind::Check { default: true },
+            option("option name Ponder type check default true").kind
+        );
+        assert_eq!(
+            UciOptionKind::String {
+                default: String::new()
+            },
+            option("option name SyzygyPath type string default <empty>").kind
+        );
+        let clear = option("option name Clear Hash type button");
+        assert_eq!(UciOptionKind::Button, clear.kind);
+        assert!(clear.accepts(""));
+
+        assert_eq!(
+            None,
+            UciOption::parse("option name Hash type spin default 16")
+        );
+        assert_eq!(None, UciOption::parse("option name Hash type colour"));
+        assert_eq!(None, UciOption::parse("id name Hash"));
+    }
+
+    #[test]
+    fn go_command() {
+        let b = Board::from_fen(KIWIPETE);
+        let standard = CastlingMode::Standard;
+        assert_eq!("go depth 7", GoParams::depth(7).to_command(&b, standard));
+        assert_eq!("go infinite", GoParams::infinite().to_command(&b, standard));
+
+        let params = GoParams {
+            ponder: true,
+            wtime: Some(Duration::from_secs(60)),
+            btime: Some(Duration::from_millis(59_500)),
+            winc: Some(Duration::from_secs(1)),
+            binc: Some(Duration::from_secs(1)),
+            movestogo: Some(20),
+            searchmoves: vec![b.parse_uci_move("e1g1").unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            "go ponder wtime 60000 btime 59500 winc 1000 binc 1000 movestogo 20 searchmoves e1g1",
+            params.to_command(&b, standard)
+        );
+        assert_eq!(
+            "go ponder wtime 60000 btime 59500 winc 1000 binc 1000 movestogo 20 searchmoves e1h1",
+            params.to_command(&b, CastlingMode::Chess960)
+        );
+
+        assert_eq!(
+            Some(Duration::from_secs(60)),
+            params.time_limit(Color::White)
+        );
+        assert_eq!(None, GoParams::depth(7).time_limit(Color::White));
+        assert_eq!(
+            Some(Duration::from_secs(1)),
+            GoParams::movetime(Duration::from_secs(1)).time_limit(Color::Black)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use chessify::{Board, CastlingMode, Move};
+
+use std::io::{self, BufRead};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+/// A scripted engine that plays the first legal move and echoes the last `position` command.
+///
+/// `go nodes` makes it crash and `go movetime` makes it ignore everything for half a second
+/// before it answers.
+fn main() {
+    println!("Fake Engine 1.0 by the chessify developers");
+    let mut board: Board = Board::default();
+    let mut position: String = String::new();
+    let mut mode: CastlingMode = CastlingMode::Standard;
+    let mut pending: Option<String> = None;
+
+    for line in io::stdin().lock().lines() {
+        let line: String = line.unwrap();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("quit") => return,
+            Some("uci") => {
+                println!("id name Fake Engine");
+                println!("id author The chessify developers");
+                println!("option name Hash type spin default 16 min 1 max 1024");
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name Style type combo default Normal var Solid var Normal");
+                println!("option name Clear Hash type button");
+                println!("option name Book File type string default <empty>");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") if line == "setoption name UCI_Chess960 value true" => {
+                mode = CastlingMode::Chess960;
+            }
+            Some("position") => {
+                let moves_at: usize = tokens
+                    .iter()
+                    .position(|t| *t == "moves")
+                    .unwrap_or(tokens.len());
+                board = match tokens[1] {
+                    "startpos" => Board::default(),
+                    _ => Board::from_fen(&tokens[2..moves_at].join(" ")),
+                };
+                for uci in tokens.iter().skip(moves_at + 1) {
+                    board.make_move(board.parse_uci_move_with_mode(uci, mode).unwrap());
+                }
+                position = line.clone();
+            }
+            Some("go") => {
+                let mut after: Board = board.clone();
+                let best: Move = board.legal_moves()[0];
+                after.make_move(best);
+                let reply: Move = after.legal_moves()[0];
+                let best_uci: String = best.to_uci_with_mode(&board, mode);
+                let reply_uci: String = reply.to_uci_with_mode(&after, mode);
+                let pv: String = format!("{} {}", best_uci, reply_uci);
+                let bestmove: String = format!("bestmove {} ponder {}", best_uci, reply_uci);
+
+                println!("info string {}", position);
+                match tokens.get(1).copied() {
+                    Some("depth") => {
+                        let depth: u32 = tokens[2].parse().unwrap();
+                        for d in 1..=depth {
+                            println!(
+                                "info depth {} seldepth {} score cp {} nodes {} nps 1000 hashfull {} wdl 300 500 200 pv {}",
+                                d,
+                                d + 2,
+                                10 * d,
+                                100 * d,
+                                d,
+                                pv
+                            );
+                        }
+                        // Garbage is ignored rather than failing the search.
+                        println!("info depth x");
+                        println!("{}", bestmove);
+                    }
+                    Some("infinite") | Some("ponder") => {
+                        println!("info depth 1 score mate 2 pv {}", pv);
+                        pending = Some(bestmove);
+                    }
+                    Some("nodes") => process::exit(3),
+                    _ => {
+                        thread::sleep(Duration::from_millis(500));
+                        println!("{}", bestmove);
+                    }
+                }
+            }
+            Some("stop") | Some("ponderhit") => {
+                if let Some(bestmove) = pending.take() {
+                    println!("{}", bestmove);
+                }
+            }
+            _ => {}
+        }
+    }
+}
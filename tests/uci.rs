@@ -0,0 +1,165 @@
+use chessify::Board;
+
+use std::io::Write;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn spawn() -> Child {
+    Command::new(env!("CARGO_BIN_EXE_chessify"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+/// Pipe a script into the engine and collect everything it prints until it exits.
+fn run(script: &str) -> Vec<String> {
+    let mut child: Child = spawn();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    lines(child)
+}
+
+fn lines(child: Child) -> Vec<String> {
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+fn send(stdin: &mut ChildStdin, command: &str) {
+    writeln!(stdin, "{}", command).unwrap();
+}
+
+fn best_move(lines: &[String]) -> &str {
+    let bestmoves: Vec<&String> = lines.iter().filter(|l| l.starts_with("bestmove")).collect();
+    assert_eq!(1, bestmoves.len(), "{:?}", lines);
+    bestmoves[0].split_whitespace().nth(1).unwrap()
+}
+
+#[test]
+fn handshake() {
+    let lines = run("uci\nisready\nquit\n");
+    assert!(lines[0].starts_with("id name chessify"));
+    assert!(lines.contains(&"option name UCI_Chess960 type check default false".to_string()));
+    assert!(lines.contains(&"option name Hash type spin default 16 min 1 max 4096".to_string()));
+    assert_eq!(["uciok", "readyok"], lines[lines.len() - 2..]);
+}
+
+#[test]
+fn hash() {
+    let script: &str = "setoption name Hash value 1\nsetoption name hash value 0\n\
+                        setoption name Clear Hash\ngo depth 3\n";
+    let lines = run(script);
+    assert_eq!("info string invalid Hash value 0", lines[0]);
+    assert!(lines
+        .iter()
+        .any(|l| l.starts_with("info depth 3 ") && l.contains(" hashfull ")));
+    assert!(Board::default().parse_uci_move(best_move(&lines)).is_ok());
+}
+
+#[test]
+fn go_depth() {
+    let lines = run("ucinewgame\nposition startpos moves e2e4 e7e5\ngo depth 3\n");
+    for depth in 1..=3 {
+        let prefix: String = format!("info depth {} seldepth ", depth);
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with(&prefix) && l.contains(" score cp ")),
+            "{:?}",
+            lines
+        );
+    }
+
+    let mut board: Board = Board::default();
+    for uci in ["e2e4", "e7e5"] {
+        board.make_move(board.parse_uci_move(uci).unwrap());
+    }
+    assert!(board.parse_uci_move(best_move(&lines)).is_ok());
+}
+
+#[test]
+fn finds_mate() {
+    let lines = run("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 4\n");
+    assert_eq!("a1a8", best_move(&lines));
+    assert!(lines.iter().any(|l| l.contains("score mate 1 ")));
+
+    let lines = run("position fen 7k/6Q1/6K1/8/8/8/8/8 b - - 0 1\ngo depth 2\n");
+    assert_eq!(["info depth 0 score mate 0", "bestmove 0000"], lines[..]);
+}
+
+#[test]
+fn limits() {
+    let start: Instant = Instant::now();
+    let lines = run("go nodes 500\ngo movetime 100\ngo wtime 1000 btime 1000 winc 10 binc 10\n");
+    assert_eq!(
+        3,
+        lines.iter().filter(|l| l.starts_with("bestmove")).count()
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn chess960() {
+    let fen: &str = "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1";
+    let script: String = format!(
+        "setoption name UCI_Chess960 value true\nposition fen {} moves e1h1 e8d8\nisready\n",
+        fen
+    );
+    assert_eq!(["readyok"], run(&script)[..]);
+
+    // Castling the standard way is not a legal move in Chess960 mode.
+    let script: String = format!(
+        "setoption name UCI_Chess960 value true\nposition fen {} moves e1g1\n",
+        fen
+    );
+    assert!(run(&script)[0].starts_with("info string"));
+}
+
+#[test]
+fn invalid_position() {
+    let lines = run("position startpos moves e2e5\nposition fen 8/8/8 w\nposition\ngo depth 1\n");
+    assert_eq!(
+        3,
+        lines
+            .iter()
+            .filter(|l| l.starts_with("info string"))
+            .count()
+    );
+    // The position stays at the start.
+    assert!(Board::default().parse_uci_move(best_move(&lines)).is_ok());
+}
+
+#[test]
+fn stop() {
+    let mut child: Child = spawn();
+    let mut stdin: ChildStdin = child.stdin.take().unwrap();
+    send(&mut stdin, "go infinite");
+    thread::sleep(Duration::from_millis(200));
+    send(&mut stdin, "stop");
+    send(&mut stdin, "quit");
+    let lines = lines(child);
+    assert!(Board::default().parse_uci_move(best_move(&lines)).is_ok());
+}
+
+#[test]
+fn ponderhit() {
+    let mut child: Child = spawn();
+    let mut stdin: ChildStdin = child.stdin.take().unwrap();
+    send(&mut stdin, "go ponder movetime 100");
+    // A pondering search does not stop on its own, not even when it runs out of time.
+    thread::sleep(Duration::from_millis(300));
+    send(&mut stdin, "ponderhit");
+    drop(stdin);
+    let lines = lines(child);
+    assert!(Board::default().parse_uci_move(best_move(&lines)).is_ok());
+}
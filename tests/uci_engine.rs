@@ -0,0 +1,220 @@
+use chessify::{
+    Board, ChessifyError, EngineErrorKind, GoParams, Move, UciEngine, UciOptionKind, UciScore,
+};
+
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn spawn() -> UciEngine {
+    UciEngine::spawn(env!("CARGO_BIN_EXE_fake_uci_engine")).unwrap()
+}
+
+fn engine_error(e: Box<dyn Error>) -> EngineErrorKind {
+    match *e.downcast::<ChessifyError>().unwrap() {
+        ChessifyError::Engine(kind) => kind,
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn handshake() {
+    let engine: UciEngine = spawn();
+    assert_eq!("Fake Engine", engine.name());
+    assert_eq!("The chessify developers", engine.author());
+    assert_eq!(5, engine.options().len());
+    assert_eq!(
+        UciOptionKind::Spin {
+            default: 16,
+            min: 1,
+            max: 1024
+        },
+        engine.option("hash").unwrap().kind
+    );
+    assert_eq!("Book File", engine.option("book file").unwrap().name);
+    assert!(engine.quit().unwrap().success());
+}
+
+#[test]
+fn set_option() {
+    let mut engine: UciEngine = spawn();
+    engine.set_option("Hash", "128").unwrap();
+    engine.set_option("style", "solid").unwrap();
+    engine.set_option("Clear Hash", "").unwrap();
+    engine.set_option("Book File", "").unwrap();
+    engine.is_ready().unwrap();
+
+    assert_eq!(
+        EngineErrorKind::InvalidOptionValue {
+            name: "Hash".to_string(),
+            value: "0".to_string()
+        },
+        engine_error(engine.set_option("Hash", "0").unwrap_err())
+    );
+    assert_eq!(
+        EngineErrorKind::UnknownOption("Threads".to_string()),
+        engine_error(engine.set_option("Threads", "4").unwrap_err())
+    );
+}
+
+#[test]
+fn go_depth() {
+    let mut engine: UciEngine = spawn();
+    engine.new_game().unwrap();
+    let board: Board = Board::default();
+    let mut after: Board = board.clone();
+    let mut moves: Vec<Move> = Vec::new();
+    for uci in ["e2e4", "e7e5"] {
+        let m: Move = after.parse_uci_move(uci).unwrap();
+        after.make_move(m);
+        moves.push(m);
+    }
+    engine.set_position(&board, &moves).unwrap();
+
+    let result = engine.go(&GoParams::depth(3)).unwrap();
+    assert_eq!(
+        Some("position startpos moves e2e4 e7e5"),
+        result.infos[0].string.as_deref()
+    );
+    assert_eq!(4, result.infos.len());
+    let last = &result.infos[3];
+    assert_eq!(Some(3), last.depth);
+    assert_eq!(Some(5), last.seldepth);
+    assert_eq!(Some(UciScore::Centipawns(30)), result.score());
+    assert_eq!(Some(300), last.nodes);
+    assert_eq!(Some(3), last.hashfull);
+    assert_eq!(500, last.wdl.unwrap().draw);
+
+    let best: Move = result.best_move.unwrap();
+    assert_eq!(after.legal_moves()[0], best);
+    assert_eq!(vec![best, result.ponder.unwrap()], last.pv);
+
+    // An illegal move is caught before anything is sent.
+    let illegal: Move = after.legal_moves()[0];
+    assert!(engine.set_position(&board, &[illegal]).is_err());
+}
+
+#[test]
+fn chess960() {
+    let mut engine: UciEngine = spawn();
+    engine.set_option("UCI_Chess960", "true").unwrap();
+    let board: Board = Board::from_fen(KIWIPETE);
+    let castle: Move = board.parse_uci_move("e1g1").unwrap();
+    engine.set_position(&board, &[castle]).unwrap();
+
+    let result = engine.go(&GoParams::depth(1)).unwrap();
+    assert_eq!(
+        Some(format!("position fen {} moves e1h1", KIWIPETE).as_str()),
+        result.infos[0].string.as_deref()
+    );
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn infinite_and_stop() {
+    let mut engine: UciEngine = spawn();
+    engine.start(&GoParams::infinite()).unwrap();
+    assert_eq!(
+        EngineErrorKind::Searching,
+        engine_error(engine.set_position(&Board::default(), &[]).unwrap_err())
+    );
+    engine.is_ready().unwrap();
+
+    let result = engine.stop().unwrap();
+    assert!(result.best_move.is_some());
+    assert_eq!(Some(UciScore::Mate(2)), result.score());
+    assert_eq!(
+        EngineErrorKind::NotSearching,
+        engine_error(engine.stop().unwrap_err())
+    );
+}
+
+#[test]
+fn ponderhit() {
+    let mut engine: UciEngine = spawn();
+    let params: GoParams = GoParams {
+        ponder: true,
+        movetime: Some(Duration::from_millis(10)),
+        ..Default::default()
+    };
+    engine.start(&params).unwrap();
+    engine.ponderhit().unwrap();
+    assert!(engine.wait().unwrap().ponder.is_some());
+}
+
+#[test]
+fn crash() {
+    let mut engine: UciEngine = spawn();
+    assert_eq!(
+        EngineErrorKind::Terminated,
+        engine_error(engine.go(&GoParams::nodes(1)).unwrap_err())
+    );
+    assert_eq!(
+        EngineErrorKind::Terminated,
+        engine_error(engine.is_ready().unwrap_err())
+    );
+}
+
+#[test]
+fn timeout() {
+    let mut engine: UciEngine = spawn();
+    engine.set_timeout(Duration::from_millis(100));
+    let start: Instant = Instant::now();
+    assert_eq!(
+        EngineErrorKind::Timeout("go".to_string()),
+        engine_error(
+            engine
+                .go(&GoParams::movetime(Duration::from_millis(50)))
+                .unwrap_err()
+        )
+    );
+    // The search time, the timeout and the timeout again after sending `stop`.
+    assert!(start.elapsed() >= Duration::from_millis(250));
+
+    // Dropping the engine that did not answer does not wait for it.
+    let start: Instant = Instant::now();
+    drop(engine);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn late_bestmove() {
+    let mut engine: UciEngine = spawn();
+    engine.set_timeout(Duration::from_millis(100));
+    assert_eq!(
+        EngineErrorKind::Timeout("go".to_string()),
+        engine_error(
+            engine
+                .go(&GoParams::movetime(Duration::from_millis(50)))
+                .unwrap_err()
+        )
+    );
+
+    // The abandoned search would answer by now, but the engine is gone.
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(
+        EngineErrorKind::Terminated,
+        engine_error(engine.set_position(&Board::default(), &[]).unwrap_err())
+    );
+    assert_eq!(
+        EngineErrorKind::Terminated,
+        engine_error(engine.go(&GoParams::depth(1)).unwrap_err())
+    );
+}
+
+#[test]
+fn chessify_engine() {
+    let mut engine: UciEngine = UciEngine::spawn(env!("CARGO_BIN_EXE_chessify")).unwrap();
+    assert!(engine.name().starts_with("chessify"));
+    engine.new_game().unwrap();
+    engine.set_position(&Board::default(), &[]).unwrap();
+
+    let result = engine.go(&GoParams::depth(2)).unwrap();
+    assert_eq!(2, result.infos.len());
+    assert!(Board::default()
+        .legal_moves()
+        .contains(&result.best_move.unwrap()));
+    assert!(engine.quit().unwrap().success());
+}